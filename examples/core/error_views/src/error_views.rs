@@ -113,6 +113,8 @@ pub fn get_error_views<G: Html>() -> ErrorViews<G> {
             // Severe failures in working with the browser (this doesn't do a lot
             // right now, but it will in future, as Perseus supports PWAs etc.)
             ClientError::PlatformError(_) |
+            // Only if you're using fallible views (e.g. `.view_fallible()`)
+            ClientError::TemplateRenderFailed { .. } |
             // Only if you're using preloads (these are usually better
             // caught at the time of the function's execution, but sometimes
             // you'll just want to leave them to a popup error)
@@ -126,6 +126,16 @@ pub enum ClientError {
     FetchError(#[from] FetchError),
     #[error(transparent)]
     PlatformError(#[from] ClientPlatformError),
+    // This is returned by fallible view functions (e.g. `.view_fallible()`), and the error
+    // could be of any type the user likes
+    #[error("the view for '{path}' failed to render")]
+    TemplateRenderFailed {
+        path: String,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    #[error("the view for template '{template_name}' was never set (and the template is in strict mode)")]
+    ViewNotSet { template_name: String },
     #[error(transparent)]
     PreloadError(#[from] ClientPreloadError), /* #[error(transparent)]
                                                * FetchError(#[from] FetchError),
@@ -300,6 +310,49 @@ pub enum ServerError {
     DepTreeNotResolved,
     #[error("the template name did not prefix the path (this request was severely malformed)")]
     TemplateNameNotInPath,
+    #[error("state generated for page '{path}' of template '{template_name}' was invalid")]
+    StateValidationFailed {
+        template_name: String,
+        path: String,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    #[error("failed to write rendered page")]
+    WriteFailed {
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("failed to generate preview state for path '{path}' of template '{template_name}'")]
+    PreviewFailed {
+        template_name: String,
+        path: String,
+        #[source]
+        source: Box<ServerError>,
+    },
+    #[error("encoded state for template '{template_name}' could not be decoded ({reason})")]
+    InvalidEncodedState {
+        template_name: String,
+        reason: String,
+    },
+    #[error("request state for template '{template_name}' timed out after {timeout:?}")]
+    RequestStateTimeout {
+        template_name: String,
+        timeout: std::time::Duration,
+    },
+    #[error("template '{template_name}' has an invalid cache control header")]
+    InvalidCacheControlHeader {
+        template_name: String,
+        #[source]
+        source: http::header::InvalidHeaderValue,
+    },
+    // This wraps errors from the user's state functions, which may not know their paths
+    #[error("error in page '{path}' of template '{template_name}'")]
+    InPage {
+        template_name: String,
+        path: String,
+        #[source]
+        source: Box<ServerError>,
+    },
 
     #[error(transparent)]
     StoreError(#[from] StoreError),
@@ -317,16 +370,35 @@ pub enum ServerError {
     #[error(transparent)]
     ClientError(#[from] ClientError),
 }
+#[cfg(engine)]
+impl ServerError {
+    /// Attaches the given page path to this error if it came from one of the
+    /// user's own functions, which won't otherwise say which page they were
+    /// generating for. Any other errors are returned as they are.
+    pub(crate) fn in_page(self, template_name: String, path: String) -> Self {
+        match self {
+            Self::RenderFnFailed { .. } => Self::InPage {
+                template_name,
+                path,
+                source: Box::new(self),
+            },
+            _ => self,
+        }
+    }
+}
 /// Converts a server error into an HTTP status code.
 #[cfg(engine)]
 pub fn err_to_status_code(err: &ServerError) -> u16 {
     match err {
         ServerError::ServeError(ServeError::PageNotFound { .. }) => 404,
+        ServerError::ServeError(ServeError::Unauthorized { .. }) => 401,
+        ServerError::ServeError(ServeError::MethodNotAllowed { .. }) => 405,
+        // Skipped pages are only generated at request-time through incremental generation
+        ServerError::BuildError(BuildError::PathSkipped { .. }) => 404,
         // Ambiguous (user-generated error), we'll rely on the given cause
-        ServerError::RenderFnFailed { blame, .. } => match blame {
-            ErrorBlame::Client(code) => code.unwrap_or(400),
-            ErrorBlame::Server(code) => code.unwrap_or(500),
-        },
+        ServerError::RenderFnFailed { blame, .. } => blame.status_code(),
+        ServerError::RequestStateTimeout { .. } => 504,
+        ServerError::InPage { source, .. } => err_to_status_code(source),
         // Any other errors go to a 500, they'll be misconfigurations or internal server errors
         _ => 500,
     }
@@ -351,6 +423,19 @@ pub enum StoreError {
         #[source]
         source: Box<dyn std::error::Error + Send + Sync>,
     },
+    #[error("asset '{name}' couldn't be deleted from store")]
+    DeleteFailed {
+        name: String,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+}
+
+/// Errors that can occur while merging collections of templates.
+#[derive(Error, Debug)]
+pub enum MergeError {
+    #[error("a template with path '{path}' is in both of the collections being merged")]
+    DuplicatePath { path: String },
 }
 
 /// Errors that can occur while fetching a resource from the server.
@@ -424,6 +509,44 @@ pub enum BuildError {
         #[source]
         source: serde_json::Error,
     },
+    #[error("template '{template_name}' was expected to be basic, but it uses these features: {}", .features.join(", "))]
+    TemplateNotBasic {
+        template_name: String,
+        features: Vec<String>,
+    },
+    #[error("template '{template_name}' requires authorization, but it doesn't generate request state (static pages can't be protected)")]
+    AuthWithoutRequestState { template_name: String },
+    #[error("path '{path}' of template '{template_name}' is invalid: {reason}")]
+    InvalidPagePath {
+        template_name: String,
+        path: String,
+        reason: String,
+    },
+    #[error("build state function of template '{template_name}' skipped path '{path}'")]
+    PathSkipped { template_name: String, path: String },
+    #[error("batch build state function of template '{template_name}' returned {found} states for {expected} paths")]
+    BatchLengthMismatch {
+        template_name: String,
+        expected: usize,
+        found: usize,
+    },
+    #[error("feature '{feature_name}' of template '{template_name}' was used for path '{path}' out of order: {reason}")]
+    FeatureOrderViolation {
+        template_name: String,
+        path: String,
+        feature_name: String,
+        reason: String,
+    },
+    #[error(
+        "build paths function of template '{template_name}' returned path '{path}' more than once"
+    )]
+    DuplicateBuildPath { template_name: String, path: String },
+    #[error("template '{template_name}' has invalid revalidation schedule '{schedule}': {reason}")]
+    InvalidCronSchedule {
+        template_name: String,
+        schedule: String,
+        reason: String,
+    },
 }
 
 /// Errors that can occur while exporting an app to static files.
@@ -448,6 +571,10 @@ pub enum ExportError {
 pub enum ServeError {
     #[error("page/widget at '{path}' not found")]
     PageNotFound { path: String },
+    #[error("request for page/widget at '{path}' was not authorized")]
+    Unauthorized { path: String },
+    #[error("request for page/widget at '{path}' used disallowed method '{method}'")]
+    MethodNotAllowed { path: String, method: String },
     #[error("both build and request states were defined for a template when only one or fewer were expected (should it be able to amalgamate states?)")]
     BothStatesDefined,
     #[cfg(engine)]
@@ -473,6 +600,15 @@ impl Default for ErrorBlame {
         Self::Server(None)
     }
 }
+impl ErrorBlame {
+    /// Gets the HTTP status code that this blame will produce.
+    pub fn status_code(&self) -> u16 {
+        match self {
+            Self::Client(code) => code.unwrap_or(400),
+            Self::Server(code) => code.unwrap_or(500),
+        }
+    }
+}
 
 /// An error that has an attached cause that blames either the client or the
 /// server for its occurrence. You can convert any error into this with
@@ -501,6 +637,25 @@ impl<E: Into<Box<dyn std::error::Error + Send + Sync + 'static>> + Send + Sync>
         }
     }
 }
+#[cfg(engine)]
+impl<E: Send + Sync> BlamedError<E> {
+    /// Creates a new blamed error that will produce the given HTTP status
+    /// code. Codes in the 4xx range will be blamed on the client, and all
+    /// others on the server.
+    pub fn with_status(error: E, status: u16) -> Self {
+        let blame = if (400..500).contains(&status) {
+            ErrorBlame::Client(Some(status))
+        } else {
+            ErrorBlame::Server(Some(status))
+        };
+        Self { error, blame }
+    }
+    /// Creates a new blamed error that will produce a *404 Not Found*, which
+    /// is useful when a request is for a resource that doesn't exist.
+    pub fn not_found(error: E) -> Self {
+        Self::with_status(error, 404)
+    }
+}
 // We should be able to convert any error into this easily (e.g. with `?`) with
 // the default being to blame the server
 #[cfg(engine)]
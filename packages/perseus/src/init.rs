@@ -1,6 +1,8 @@
 #[cfg(engine)]
 use crate::server::HtmlShell;
 #[cfg(engine)]
+use crate::template::ManifestEntry;
+#[cfg(engine)]
 use crate::utils::get_path_prefix_server;
 use crate::{
     error_views::ErrorViews,
@@ -114,6 +116,11 @@ pub struct PerseusAppBase<G: Html, M: MutableStore, T: TranslationsManager> {
     pub(crate) root: String,
     /// A list of all the templates and capsules that the app uses.
     pub(crate) entities: EntityMap<G>,
+    /// Pairs of names of templates and capsules that have been registered
+    /// at the same paths, which would lead to the earlier ones silently
+    /// never being served.
+    #[cfg(engine)]
+    pub(crate) path_conflicts: Vec<(String, String)>,
     /// The app's error pages.
     #[cfg(client)]
     pub(crate) error_views: Option<Rc<ErrorViews<G>>>,
@@ -337,6 +344,8 @@ impl<G: Html, M: MutableStore, T: TranslationsManager> PerseusAppBase<G, M, T> {
             // We do initialize with no templates, because an app without templates is in theory
             // possible (and it's more convenient to call `.template()` for each one)
             entities: HashMap::new(),
+            #[cfg(engine)]
+            path_conflicts: Vec::new(),
             // We do offer default error views, but they'll panic if they're called for production
             // building
             error_views: None,
@@ -479,8 +488,9 @@ impl<G: Html, M: MutableStore, T: TranslationsManager> PerseusAppBase<G, M, T> {
             Forever::StaticRef(capsule_ref) => (&capsule_ref.inner).into(),
         };
 
-        let path = entity.get_path();
-        self.entities.insert(path, entity);
+        #[cfg(engine)]
+        self.record_path_conflict(&entity);
+        self.entities.insert(entity.get_path(), entity);
         self
     }
     // TODO
@@ -535,10 +545,22 @@ impl<G: Html, M: MutableStore, T: TranslationsManager> PerseusAppBase<G, M, T> {
             Forever::StaticRef(capsule_ref) => (&capsule_ref.inner).into(),
         };
 
-        let path = entity.get_path();
-        self.entities.insert(path, entity);
+        #[cfg(engine)]
+        self.record_path_conflict(&entity);
+        self.entities.insert(entity.get_path(), entity);
         self
     }
+    /// Records a conflict if an entity has already been registered at the
+    /// path of the given one, as the names of the existing and new entities.
+    #[cfg(engine)]
+    fn record_path_conflict(&mut self, entity: &Entity<G>) {
+        if let Some(existing) = self.entities.get(&entity.get_path()) {
+            self.path_conflicts.push((
+                existing.get_name().to_string(),
+                entity.get_name().to_string(),
+            ));
+        }
+    }
     /// Sets the app's error views. See [`ErrorViews`] for further details.
     // Internally, this will extract a copy of the main handler for panic
     // usage. Note that the default value of this is extracted from the default
@@ -930,6 +952,43 @@ impl<G: Html, M: MutableStore, T: TranslationsManager> PerseusAppBase<G, M, T> {
     // pub fn get_global_state_creator(&self) -> Arc<GlobalStateCreator> {
     //     self.global_state_creator.clone()
     // }
+    /// Gets the pairs of templates and capsules in this app whose paths
+    /// conflict with each other, as the names of the existing entity and of
+    /// the one that was registered over it. For now, only identical paths
+    /// (including both `index` and an empty path) are detected, in which
+    /// case the later entity will have replaced the earlier one. This is
+    /// intended to be called in tests, to catch routing bugs before they
+    /// reach production.
+    #[cfg(engine)]
+    pub fn detect_conflicts(&self) -> Vec<(String, String)> {
+        self.path_conflicts.clone()
+    }
+    /// Describes all the templates in this app (not including capsules) in
+    /// [`ManifestEntry`]s, sorted by path, for use by client-side routers and
+    /// the like. If the app uses i18n, each entry will list all the app's
+    /// locales.
+    #[cfg(engine)]
+    pub fn to_manifest(&self) -> Result<Vec<ManifestEntry>, PluginError> {
+        let locales = self.get_locales()?;
+        let locales: Vec<String> = if locales.using_i18n {
+            locales.get_all().into_iter().cloned().collect()
+        } else {
+            Vec::new()
+        };
+
+        let mut manifest: Vec<ManifestEntry> = self
+            .entities
+            .values()
+            .filter(|entity| !entity.is_capsule)
+            .map(|entity| ManifestEntry {
+                locales: locales.clone(),
+                ..entity.to_manifest_entry()
+            })
+            .collect();
+        manifest.sort_by(|a, b| a.path.cmp(&b.path));
+
+        Ok(manifest)
+    }
     /// Gets the locales information.
     pub fn get_locales(&self) -> Result<Locales, PluginError> {
         let locales = self.locales.clone();
@@ -1093,3 +1152,24 @@ pub type PerseusAppWithTranslationsManager<G, T> = PerseusAppBase<G, FsMutableSt
 /// mutable store and a custom translations manager. Alternatively, you could
 /// just use [`PerseusAppBase`] directly.
 pub type PerseusAppWithMutableStoreAndTranslationsManager<G, M, T> = PerseusAppBase<G, M, T>;
+
+#[cfg(all(test, engine))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duplicate_template_paths_are_conflicts() {
+        let app = PerseusApp::<SsrNode>::new()
+            .template(Template::build("about").build())
+            .template(Template::build("index").build())
+            .template(Template::build("about").build())
+            .template(Template::build("").build());
+        assert_eq!(
+            app.detect_conflicts(),
+            vec![
+                ("about".to_string(), "about".to_string()),
+                ("index".to_string(), "".to_string())
+            ]
+        );
+    }
+}
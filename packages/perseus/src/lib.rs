@@ -9,6 +9,7 @@
 - `hydrate` --- enables Sycamore's *experimental* hydration system (if you experience odd issues, try disabling this)
 - `preload-wasm-on-redirect` --- *experimentally* preloads the Wasm bundle for locale redirections (this only partially works right now)
 - `idb-freezing` --- enables utilities for freezing your app's state to IndexedDB in the browser (see the book)
+- `markdown` --- enables creating templates for pages that are just rendered Markdown with `Template::from_markdown()`
 - `live-reload` (default) --- enables reloading the browser automatically when you make changes to your app
 - `hsr` (default) --- enables *hot state reloading*, which reloads the state of your app right before you made code changes in development, allowing you to pick up where you left off
 
@@ -142,13 +143,18 @@ pub mod prelude {
     pub use crate::errors::{BlamedError, ErrorBlame};
     pub use crate::init::*;
     pub use crate::reactor::Reactor;
-    pub use crate::state::{BuildPaths, RxResult, RxResultRx, SerdeInfallible, StateGeneratorInfo};
+    #[cfg(engine)]
+    pub use crate::state::ResponseMeta;
+    pub use crate::state::{
+        BuildPathMeta, BuildPaths, BuildStateOutcome, RenderOutcome, RequestStateOutcome, RxResult,
+        RxResultRx, SerdeInfallible, StateGeneratorInfo,
+    };
     pub use crate::template::{Capsule, Template};
     pub use sycamore::web::Html;
     pub use sycamore_router::{navigate, navigate_replace};
 
     #[cfg(engine)]
-    pub use crate::utils::{cache_fallible_res, cache_res};
+    pub use crate::utils::{cache_fallible_res, cache_res, RequestQueryExt};
     pub use crate::web_log;
     #[cfg(any(feature = "translator-fluent", feature = "translator-lightweight"))]
     pub use crate::{link, t};
@@ -34,3 +34,14 @@ pub struct PageDataPartial {
     /// The string to interpolate into the document's `<head>`.
     pub head: String,
 }
+
+/// What is sent to the client instead of [`PageDataPartial`] when a page's
+/// request state redirects the user elsewhere on a subsequent load. A real
+/// HTTP redirect would be followed transparently by the browser, giving the
+/// app shell an HTML page where it expected page data, so the app shell
+/// performs the navigation itself instead.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PageRedirect {
+    /// The URL to redirect to.
+    pub redirect: String,
+}
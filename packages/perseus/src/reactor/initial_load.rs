@@ -80,9 +80,19 @@ impl<G: Html> Reactor<G> {
                 });
                 self.router_state.set_last_verdict(slim_verdict);
 
+                // If the server sent this template's fallback because the page is yet to be
+                // generated, there won't be any state yet, and we'll have to fetch the real page
+                let is_fallback = matches!(
+                    WindowVariable::new_str("__PERSEUS_INITIAL_STATE"),
+                    WindowVariable::Some(state_str) if state_str == "fallback"
+                );
                 // Get the initial state and decide what to do from that. We can guarantee that
                 // this locale is supported because it came from `match_route`.
-                let state = self.get_initial_state(locale)?;
+                let state = if is_fallback {
+                    TemplateState::empty()
+                } else {
+                    self.get_initial_state(locale)?
+                };
 
                 // Get the translator from the page (this has to exist, or the server stuffed
                 // up); doing this without a network request minimizes
@@ -100,8 +110,9 @@ impl<G: Html> Reactor<G> {
                 self.translations_manager
                     .set_translator_for_translations_str(locale, &translations_str)?;
 
+                // The fallback has no head worth caching
                 #[cfg(feature = "cache-initial-load")]
-                {
+                if !is_fallback {
                     // Cache the page's head in the PSS (getting it as reliably as we can, which
                     // isn't perfect, hence the feature-gate). Without this, we
                     // would have to get the head from the server on
@@ -128,6 +139,15 @@ impl<G: Html> Reactor<G> {
                     self.state_store.add_initial_widget(widget_path, state_res);
                 }
 
+                if is_fallback {
+                    // The router state stays as loading until the real page has been fetched
+                    // (the template must have a fallback if the server sent one)
+                    let (view, disposer) = entity
+                        .render_fallback_client(cx)
+                        .ok_or(ClientInvariantError::InitialState)?;
+                    return Ok(InitialView::Fallback(view, disposer));
+                }
+
                 // Render the actual template to the root (done imperatively due to child
                 // scopes)
                 let (view, disposer) =
@@ -306,4 +326,8 @@ pub(crate) enum InitialView<'app, G: Html> {
     /// theoretically also be used for server-level reloads, if those
     /// directives are ever supported.
     Redirect(String),
+    /// The server sent the fallback of a page that is yet to be generated,
+    /// and the provided view and scope disposer are ready to render that. The
+    /// real page should be fetched once the router is ready.
+    Fallback(View<G>, ScopeDisposer<'app>),
 }
@@ -225,7 +225,18 @@ impl Reactor<BrowserNodeType> {
         // can simply report errors, but, because we don't actually have a place to put
         // page-wide errors yet, we need to know what this will return so we know if we
         // should proceed.
+        let mut is_fallback = false;
         let (starting_view, is_err) = match self.get_initial_view(cx) {
+            Ok(InitialView::Fallback(view, disposer)) => {
+                // SAFETY: There's nothing in there right now, and we know that for sure
+                // because it's the initial load. Also, we're in the app-level scope.
+                unsafe {
+                    page_disposer.update(disposer);
+                }
+
+                is_fallback = true;
+                (view, false)
+            }
             Ok(InitialView::View(view, disposer)) => {
                 // SAFETY: There's nothing in there right now, and we know that for sure
                 // because it's the initial load (asserted above). Also, we're in the app-level
@@ -422,6 +433,12 @@ impl Reactor<BrowserNodeType> {
                            * a full render */
         );
 
+        // Now that the router exists, we can fetch the real page if we only had its fallback (the
+        // reload commander will treat this as a subsequent load)
+        if is_fallback {
+            self.router_state.reload();
+        }
+
         // If we successfully got here, the app is running!
         true
     }
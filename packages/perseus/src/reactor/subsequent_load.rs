@@ -8,7 +8,7 @@ use sycamore::{
 use crate::{
     errors::{AssetType, ClientError, ClientInvariantError},
     i18n::detect_locale,
-    page_data::PageDataPartial,
+    page_data::{PageDataPartial, PageRedirect},
     path::PathMaybeWithLocale,
     router::{FullRouteInfo, FullRouteVerdict, RouteVerdict, RouterLoadState},
     state::{PssContains, TemplateState},
@@ -24,8 +24,8 @@ impl<G: Html> Reactor<G> {
     /// constructed here, not deserialized from provided data.
     ///
     /// # Panics
-    /// This function will panic on a locale redirection if a router has not
-    /// been created on the given scope.
+    /// This function will panic on a locale redirection, or a redirection
+    /// from the server, if a router has not been created on the given scope.
     ///
     /// This function will also panic if the given route verdict stores a
     /// template name that is not known to this reactor (i.e. it must have
@@ -77,6 +77,15 @@ impl<G: Html> Reactor<G> {
                         let page_data_str = fetch(&asset_url, AssetType::Page).await?;
                         match &page_data_str {
                             Some(page_data_str) => {
+                                // The server tells us about redirects this way, since the browser
+                                // would have followed a real one to an HTML page
+                                if let Ok(PageRedirect { redirect }) =
+                                    serde_json::from_str::<PageRedirect>(page_data_str)
+                                {
+                                    follow_redirect(&redirect);
+                                    // The router will take it from here
+                                    return Ok((View::empty(), create_scope(|_| {})));
+                                }
                                 // All good, deserialize the page data
                                 let page_data =
                                     serde_json::from_str::<PageDataPartial>(page_data_str);
@@ -193,3 +202,16 @@ impl<G: Html> Reactor<G> {
         }
     }
 }
+
+/// Navigates to the given redirect target as the server would have on an
+/// initial load, replacing the page that redirected in the user's history.
+/// Paths within the app are handled by the router, and anything else gets a
+/// full browser navigation.
+fn follow_redirect(to: &str) {
+    if to.starts_with('/') && !to.starts_with("//") {
+        sycamore_router::navigate_replace(to);
+    } else {
+        // If this fails, there's nothing we can do
+        let _ = web_sys::window().unwrap().location().replace(to);
+    }
+}
@@ -60,6 +60,17 @@ fn get_template_for_path<'a, G: Html>(
     }
 }
 
+/// Resolves the given path to the root path of the given entity if it's one
+/// of that entity's aliases, since aliases have no build artifacts of their
+/// own.
+fn resolve_alias<G: Html>(path: PathWithoutLocale, entity: &Entity<G>) -> PathWithoutLocale {
+    if entity.is_alias(&path) {
+        PathWithoutLocale(entity.get_path())
+    } else {
+        path
+    }
+}
+
 /// Matches the given path to a `RouteVerdict`. This takes a `TemplateMap` to
 /// match against, the render configuration to index, and it needs to know if
 /// i18n is being used. The path this takes should be raw, it may or may not
@@ -91,7 +102,7 @@ pub(crate) fn match_route<G: Html>(
                 Some(entity) => RouteVerdict::Found(RouteInfo {
                     locale: locale.to_string(),
                     // This will be used in asset fetching from the server
-                    path: path_without_locale,
+                    path: resolve_alias(path_without_locale, entity),
                     // The user can get the full entity again if they want to, we just use it to
                     // make sure the path exists
                     entity_name: entity.get_path(),
@@ -125,7 +136,7 @@ pub(crate) fn match_route<G: Html>(
             Some(entity) => RouteVerdict::Found(RouteInfo {
                 locale: locales.default.to_string(),
                 // This will be used in asset fetching from the server
-                path: path_joined,
+                path: resolve_alias(path_joined, entity),
                 // The user can get the full entity again if they want to, we just use it to make
                 // sure the path exists
                 entity_name: entity.get_path(),
@@ -253,6 +253,38 @@ impl HtmlShell {
         self
     }
 
+    /// Interpolates the fallback for a page that is yet to be incrementally
+    /// generated into the shell. Instead of the page's state, the app shell
+    /// will be told to fetch the real page itself, which will generate it.
+    ///
+    /// This needs the same global state and translations as
+    /// `.page_data()`, but nothing page-specific.
+    pub(crate) fn fallback_page(
+        mut self,
+        fallback_html: &str,
+        global_state: &TemplateState,
+        locale: &str,
+        translations: &str,
+    ) -> Self {
+        self.locale = locale.to_string();
+        let global_state = escape_page_data(&global_state.state.to_string());
+        let translations = escape_page_data(translations);
+
+        // This can't be confused with real state, which is always valid JSON
+        self.scripts_after_boundary
+            .push("window.__PERSEUS_INITIAL_STATE = `fallback`;".to_string());
+        // Fallbacks can't use widgets
+        self.scripts_after_boundary
+            .push("window.__PERSEUS_INITIAL_WIDGET_STATES = `{}`;".to_string());
+        let global_state = format!("window.__PERSEUS_GLOBAL_STATE = `{}`;", global_state);
+        self.scripts_before_boundary.push(global_state);
+        let translations = format!("window.__PERSEUS_TRANSLATIONS = `{}`;", translations);
+        self.scripts_after_boundary.push(translations);
+        self.content = fallback_html.into();
+
+        self
+    }
+
     /// Interpolates a fallback for locale redirection pages such that, even if
     /// JavaScript is disabled, the user will still be redirected to the default
     /// locale. From there, Perseus' inbuilt progressive enhancement can
@@ -0,0 +1,13 @@
+/// The outcome of a skippable build state function (see
+/// `.skippable_build_state_fn()`), which can either generate state for the
+/// page, or tell Perseus not to generate the page at all. The latter is
+/// useful for paths that turn out not to be ready when their state is
+/// generated, like unpublished items.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BuildStateOutcome<S> {
+    /// Use this state for the page.
+    State(S),
+    /// Don't generate this page. At build-time, the path will be dropped
+    /// entirely, and, at request-time, a 404 will be returned.
+    Skip,
+}
@@ -1,6 +1,11 @@
+mod build_state_outcome;
 #[cfg(any(client, doc))]
 mod freeze; // This has `FrozenApp` etc.
 mod global_state;
+mod render_outcome;
+mod request_state_outcome;
+#[cfg(engine)]
+mod response_meta;
 mod rx_result;
 mod rx_state;
 mod state_generator_info;
@@ -11,14 +16,19 @@ mod template_state;
 // #[cfg(feature = "rx-collections")]
 pub mod rx_collections;
 
+pub use build_state_outcome::BuildStateOutcome;
 #[cfg(any(client, doc))]
 pub use freeze::{FrozenApp, PageThawPrefs, ThawPrefs};
 #[cfg(any(client, doc))]
 pub(crate) use global_state::FrozenGlobalState;
 pub use global_state::{GlobalState, GlobalStateCreator, GlobalStateType};
+pub use render_outcome::RenderOutcome;
+pub use request_state_outcome::RequestStateOutcome;
+#[cfg(engine)]
+pub use response_meta::ResponseMeta;
 pub use rx_result::{RxResult, RxResultRx, SerdeInfallible};
 pub use rx_state::{AnyFreeze, Freeze, MakeRx, MakeUnrx, UnreactiveState};
-pub use state_generator_info::{BuildPaths, StateGeneratorInfo};
+pub use state_generator_info::{BuildPathMeta, BuildPaths, StateGeneratorInfo};
 pub use state_store::{PageStateStore, PssContains, PssEntry, PssState};
 pub use template_state::{TemplateState, TemplateStateWithType, UnknownStateType};
 
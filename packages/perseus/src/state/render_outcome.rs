@@ -0,0 +1,19 @@
+/// The outcome of a redirecting request state function (see
+/// `.redirecting_request_state_fn()`), which can either render the page with
+/// some state, or redirect the user to another URL entirely. This is useful
+/// for things like sending unauthenticated users to a login page.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RenderOutcome<S> {
+    /// Render the page with this state.
+    Render(S),
+    /// Redirect the user to the given URL instead of rendering the page. If
+    /// `permanent` is `true`, this will be sent as a *301 Moved Permanently*,
+    /// otherwise it will be a *302 Found*.
+    Redirect {
+        /// The URL to redirect to. This will be sent as-is in the `Location`
+        /// header.
+        to: String,
+        /// Whether or not the redirect is permanent.
+        permanent: bool,
+    },
+}
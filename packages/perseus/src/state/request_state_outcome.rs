@@ -0,0 +1,13 @@
+/// The outcome of a conditional request state function (see
+/// `.conditional_request_state_fn()`), which can either generate state for
+/// the request, or tell Perseus to use the page's build state instead. The
+/// latter is useful for avoiding expensive state generation in common cases,
+/// like requests from anonymous users.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RequestStateOutcome<S> {
+    /// Use this state for the request.
+    State(S),
+    /// Use the state generated for the page at build-time (or at its last
+    /// revalidation) instead.
+    UseBuildState,
+}
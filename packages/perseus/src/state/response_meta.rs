@@ -0,0 +1,72 @@
+use http::{
+    header::{self, HeaderName, InvalidHeaderValue},
+    HeaderMap, HeaderValue, StatusCode,
+};
+
+/// Metadata about the HTTP response for a page, which can be generated
+/// alongside its request state (see `.request_state_with_meta_fn()`). This
+/// allows request state functions to, for example, set cookies for session
+/// handling.
+///
+/// Any headers set here will be added to the response *after* those from
+/// `.set_headers_fn()`, and so may override them.
+#[derive(Debug, Clone, Default)]
+pub struct ResponseMeta {
+    /// The additional headers for the response.
+    pub headers: HeaderMap,
+    /// An override for the HTTP status code of the response. This only applies
+    /// to initial loads, since the app shell expects page data to come with a
+    /// *200 OK*.
+    pub status: Option<StatusCode>,
+}
+impl ResponseMeta {
+    /// Creates some empty response metadata, which won't change the response
+    /// at all.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Adds the given header to the response. This will not replace any
+    /// previous values for the same header, so it can be called several
+    /// times to set multiple cookies.
+    pub fn header(mut self, name: HeaderName, val: HeaderValue) -> Self {
+        self.headers.append(name, val);
+        self
+    }
+    /// Overrides the HTTP status code of the response.
+    pub fn status(mut self, status: StatusCode) -> Self {
+        self.status = Some(status);
+        self
+    }
+    /// Creates response metadata that will redirect the user to the given URL,
+    /// with a *301 Moved Permanently* if `permanent` is `true`, or a *302
+    /// Found* otherwise. This will fail if the URL can't be used as a header
+    /// value.
+    pub fn redirect(to: &str, permanent: bool) -> Result<Self, InvalidHeaderValue> {
+        let status = if permanent {
+            StatusCode::MOVED_PERMANENTLY
+        } else {
+            StatusCode::FOUND
+        };
+        Ok(Self::new()
+            .header(header::LOCATION, HeaderValue::from_str(to)?)
+            .status(status))
+    }
+    /// Whether or not this metadata redirects the user elsewhere, in which
+    /// case the page itself won't be rendered.
+    pub fn is_redirect(&self) -> bool {
+        self.status.is_some_and(|status| status.is_redirection())
+            && self.headers.contains_key(header::LOCATION)
+    }
+    /// If this metadata redirects the user elsewhere, removes the redirect
+    /// (both the `Location` header and the status code) and returns the URL
+    /// it pointed to. Any other headers will be left as they are.
+    pub(crate) fn take_redirect(&mut self) -> Option<String> {
+        if !self.is_redirect() {
+            return None;
+        }
+        self.status = None;
+        self.headers
+            .remove(header::LOCATION)
+            .and_then(|to| to.to_str().ok().map(|to| to.to_string()))
+    }
+}
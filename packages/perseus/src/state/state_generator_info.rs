@@ -16,6 +16,33 @@ pub struct BuildPaths {
     pub extra: TemplateState,
 }
 
+/// A single path to be rendered under a template, along with some metadata
+/// about it, which could be used for things like generating sitemaps. These
+/// can be generated with `.build_paths_meta_fn()` on a template.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BuildPathMeta {
+    /// The path to render underneath the template, without the template name
+    /// or leading forward slashes.
+    pub path: String,
+    /// When the content at this path was last modified, in whatever format
+    /// you like (W3C datetimes are used in sitemaps).
+    pub last_modified: Option<String>,
+    /// The priority of this path relative to other paths on your site, which
+    /// should be between `0.0` and `1.0`.
+    pub priority: Option<f32>,
+}
+impl BuildPathMeta {
+    /// Creates metadata for the given path with no modification time or
+    /// priority.
+    pub fn new(path: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            last_modified: None,
+            priority: None,
+        }
+    }
+}
+
 /// The information any function that generates state will be provided.
 ///
 /// This must be able to be shared safely between threads.
@@ -1,7 +1,7 @@
 use crate::errors::*;
 #[cfg(engine)]
 use tokio::{
-    fs::{create_dir_all, File},
+    fs::{create_dir_all, remove_file, File},
     io::{AsyncReadExt, AsyncWriteExt},
 };
 
@@ -32,6 +32,18 @@ pub trait MutableStore: std::fmt::Debug + Clone + Send + Sync {
     /// Writes data to the named asset. This will create a new asset if one
     /// doesn't exist already.
     async fn write(&self, name: &str, content: &str) -> Result<(), StoreError>;
+    /// Deletes the named asset. This is used to clean up pages that have been
+    /// evicted from the cache of incrementally generated pages (see
+    /// `.incremental_cache_limit()` on `Template`). Deleting an asset that
+    /// doesn't exist should succeed.
+    ///
+    /// By default, this does nothing, so evicted pages will simply be left in
+    /// the store (they'll be regenerated on their next request regardless).
+    /// Implementations should override this if they want evicted pages to
+    /// actually free up space.
+    async fn delete(&self, _name: &str) -> Result<(), StoreError> {
+        Ok(())
+    }
 }
 
 /// The default [`MutableStore`], which simply uses the filesystem. This is
@@ -135,6 +147,19 @@ impl MutableStore for FsMutableStore {
 
         Ok(())
     }
+    #[cfg(engine)]
+    async fn delete(&self, name: &str) -> Result<(), StoreError> {
+        let asset_path = format!("{}/{}", self.root_path, name);
+        match remove_file(&asset_path).await {
+            Ok(_) => Ok(()),
+            // If it's already gone, there's nothing to do
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(StoreError::DeleteFailed {
+                name: asset_path,
+                source: err.into(),
+            }),
+        }
+    }
     #[cfg(client)]
     async fn read(&self, _name: &str) -> Result<String, StoreError> {
         Ok(String::new())
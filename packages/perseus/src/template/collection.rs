@@ -0,0 +1,117 @@
+use super::Template;
+use crate::errors::MergeError;
+use sycamore::web::Html;
+
+/// How conflicts between templates with the same path should be resolved when
+/// merging [`TemplateCollection`]s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeStrategy {
+    /// Any conflict will produce a [`MergeError::DuplicatePath`].
+    Error,
+    /// The template from the collection being merged into will be kept.
+    KeepFirst,
+    /// The template from the collection being merged in will replace the
+    /// existing one.
+    KeepSecond,
+}
+
+/// A collection of templates, which is useful for assembling an app from
+/// templates defined in several different crates. Each crate can export a
+/// collection of its templates, and these can be merged together before being
+/// given to `PerseusApp::templates()` with `.into_templates()`.
+#[derive(Debug)]
+pub struct TemplateCollection<G: Html> {
+    templates: Vec<Template<G>>,
+}
+impl<G: Html> Default for TemplateCollection<G> {
+    fn default() -> Self {
+        Self {
+            templates: Vec::new(),
+        }
+    }
+}
+impl<G: Html> TemplateCollection<G> {
+    /// Creates a new, empty collection of templates.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Adds the given template to this collection. If there's already a
+    /// template with the same path, it will be replaced.
+    pub fn template(mut self, val: Template<G>) -> Self {
+        let path = val.get_path();
+        self.templates
+            .retain(|template| template.get_path() != path);
+        self.templates.push(val);
+        self
+    }
+    /// Merges the given collection into this one, resolving any conflicts
+    /// between templates with the same path using the given strategy. The
+    /// templates in this collection will keep their order, and any new ones
+    /// will be added after them.
+    pub fn merge(mut self, other: Self, strategy: MergeStrategy) -> Result<Self, MergeError> {
+        for template in other.templates {
+            match self
+                .templates
+                .iter()
+                .position(|existing| existing.get_path() == template.get_path())
+            {
+                Some(idx) => match strategy {
+                    MergeStrategy::Error => {
+                        return Err(MergeError::DuplicatePath {
+                            path: template.get_path(),
+                        })
+                    }
+                    MergeStrategy::KeepFirst => {}
+                    MergeStrategy::KeepSecond => self.templates[idx] = template,
+                },
+                None => self.templates.push(template),
+            }
+        }
+
+        Ok(self)
+    }
+    /// Gets the templates in this collection, for use with
+    /// `PerseusApp::templates()`.
+    pub fn into_templates(self) -> Vec<Template<G>> {
+        self.templates
+    }
+}
+
+#[cfg(all(test, engine))]
+mod tests {
+    use super::*;
+    use sycamore::web::SsrNode;
+
+    #[test]
+    fn template_collections_merge_with_strategy() {
+        let first = || {
+            TemplateCollection::new()
+                .template(Template::<SsrNode>::build("about").build())
+                .template(Template::build("blog").content_type("text/html").build())
+        };
+        let second = || {
+            TemplateCollection::new().template(
+                Template::build("blog")
+                    .content_type("application/rss+xml")
+                    .build(),
+            )
+        };
+
+        let err = first().merge(second(), MergeStrategy::Error).unwrap_err();
+        assert!(matches!(err, MergeError::DuplicatePath { path } if path == "blog"));
+
+        let kept_first = first()
+            .merge(second(), MergeStrategy::KeepFirst)
+            .unwrap()
+            .into_templates();
+        assert_eq!(kept_first.len(), 2);
+        assert_eq!(kept_first[1].content_type_header(), "text/html");
+
+        let kept_second = first()
+            .merge(second(), MergeStrategy::KeepSecond)
+            .unwrap()
+            .into_templates();
+        assert_eq!(kept_second.len(), 2);
+        assert_eq!(kept_second[1].content_type_header(), "application/rss+xml");
+    }
+}
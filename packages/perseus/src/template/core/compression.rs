@@ -0,0 +1,21 @@
+/// Whether or not the server should compress the responses for a template's
+/// pages (e.g. with gzip or brotli), which can be set with `.compress()`.
+/// Perseus doesn't compress anything itself, this is a hint for server
+/// integrations, which can check it with `.should_compress()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Compression {
+    /// Responses will be compressed if they're large enough for compression
+    /// to be worthwhile, and if the template's content type is textual.
+    #[default]
+    Auto,
+    /// Responses will always be compressed.
+    Always,
+    /// Responses will never be compressed.
+    Never,
+}
+impl Compression {
+    /// The size in bytes above which responses will be compressed with
+    /// [`Compression::Auto`]. Below this, the compressed body would likely
+    /// be barely smaller than the original.
+    pub const AUTO_THRESHOLD: usize = 1024;
+}
@@ -0,0 +1,52 @@
+#[cfg(engine)]
+use crate::utils::ComputedDuration;
+use crate::utils::PerseusDuration;
+
+/// A set of defaults that can be applied to many templates at once with
+/// `.apply_defaults()`, which is useful for site-wide policies (e.g. every
+/// page revalidating every hour). Any values a template sets itself will
+/// always take precedence over these.
+#[derive(Debug, Clone, Default)]
+pub struct TemplateDefaults {
+    #[cfg(engine)]
+    pub(crate) revalidate_after: Option<ComputedDuration>,
+    #[cfg(engine)]
+    pub(crate) cache_control: Option<String>,
+}
+impl TemplateDefaults {
+    /// Creates a new, empty set of template defaults.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Sets the default revalidation interval, which takes the same formats
+    /// as `.revalidate_after()` on templates. This will only be applied to
+    /// templates that use the *build state* strategy.
+    #[cfg(engine)]
+    pub fn revalidate_after<I: PerseusDuration>(mut self, val: I) -> Self {
+        let computed_duration = match val.into_computed() {
+            Ok(val) => val,
+            // As with templates, this will show up before runtime
+            Err(_) => panic!("invalid revalidation interval"),
+        };
+        self.revalidate_after = Some(computed_duration);
+        self
+    }
+    /// Sets the default revalidation interval, which takes the same formats
+    /// as `.revalidate_after()` on templates. This will only be applied to
+    /// templates that use the *build state* strategy.
+    #[cfg(any(client, doc))]
+    pub fn revalidate_after<I: PerseusDuration>(self, _val: I) -> Self {
+        self
+    }
+    /// Sets the default `Cache-Control` header.
+    #[cfg(engine)]
+    pub fn cache_control(mut self, val: impl Into<String>) -> Self {
+        self.cache_control = Some(val.into());
+        self
+    }
+    /// Sets the default `Cache-Control` header.
+    #[cfg(any(client, doc))]
+    pub fn cache_control(self, _val: impl Into<String>) -> Self {
+        self
+    }
+}
@@ -1,6 +1,23 @@
+#[cfg(engine)]
+use super::validate_path;
 use super::TemplateInner;
 #[cfg(engine)]
-use crate::utils::ComputedDuration;
+use super::{Compression, ManifestEntry, RenderStrategy, RevalidationKind, RevalidationPolicy};
+#[cfg(engine)]
+use crate::errors::BuildError;
+#[cfg(engine)]
+use crate::state::TemplateState;
+#[cfg(engine)]
+use crate::utils::{ComputedDuration, CronSchedule, StableHasher};
+#[cfg(engine)]
+use crate::Request;
+use std::borrow::Cow;
+#[cfg(engine)]
+use std::collections::HashMap;
+#[cfg(engine)]
+use std::hash::{Hash, Hasher};
+#[cfg(engine)]
+use std::time::SystemTime;
 use sycamore::web::Html;
 
 impl<G: Html> TemplateInner<G> {
@@ -12,34 +29,119 @@ impl<G: Html> TemplateInner<G> {
     ///
     /// Note that this will prepend `__capsule/` to any capsules automatically.
     pub fn get_path(&self) -> String {
-        let base = if self.path == "index" {
-            String::new()
-        } else {
-            self.path.clone()
-        };
+        self.path().into_owned()
+    }
+    /// Gets every path at which this template's root page will be served:
+    /// its own path, followed by any aliases set with `.alias()`.
+    pub fn all_paths(&self) -> Vec<String> {
+        let mut paths = vec![self.get_path()];
+        paths.extend(self.aliases.iter().cloned());
+        paths
+    }
+    /// Checks if the given path is one of this template's aliases, rather
+    /// than a path it would usually render.
+    pub fn is_alias(&self, path: &str) -> bool {
+        self.aliases.iter().any(|alias| alias == path)
+    }
+    /// Gets the path of the template, exactly as with `.get_path()`, but
+    /// without allocating, except for capsules (which need their prefix
+    /// added). This is useful for comparing paths in hot loops.
+    pub fn path(&self) -> Cow<'_, str> {
+        let base = if self.path == "index" { "" } else { &self.path };
         if self.is_capsule {
-            format!("__capsule/{}", base)
+            Cow::Owned(format!("__capsule/{}", base))
         } else {
-            base
+            Cow::Borrowed(base)
         }
     }
+    /// Gets the name this template was defined with, which, unlike its path,
+    /// won't have been normalized (i.e. `index` will remain as it is, and
+    /// capsules won't have their prefix).
+    pub fn get_name(&self) -> &str {
+        &self.path
+    }
     /// Gets the interval after which the template will next revalidate.
     #[cfg(engine)]
     pub fn get_revalidate_interval(&self) -> Option<ComputedDuration> {
         self.revalidate_after.clone()
     }
 
+    /// Gets the time at which a page of this template last rendered at the
+    /// given time will be due for revalidation, if this template revalidates
+    /// after some time. This doesn't account for logic-based revalidation.
+    #[cfg(engine)]
+    pub fn next_revalidation(&self, last_rendered: SystemTime) -> Option<SystemTime> {
+        self.revalidate_after.as_ref()?.add_to(last_rendered)
+    }
+    /// Gets the first time after the given time at which this template's
+    /// cron revalidation schedule is due, if it has one (and it's valid).
+    #[cfg(engine)]
+    pub fn next_revalidation_cron(&self, now: SystemTime) -> Option<SystemTime> {
+        let schedule = CronSchedule::parse(self.revalidate_cron.as_ref()?).ok()?;
+        schedule.next_after(now.into()).map(SystemTime::from)
+    }
+    /// Gets the first time after the given time at which this template's
+    /// cron revalidation schedule is due, exactly as with
+    /// `.next_revalidation_cron()`, except this will return an error if the
+    /// schedule is invalid, or if it never matches any upcoming time, rather
+    /// than `None`. This will only return `None` if there is no schedule.
+    #[cfg(engine)]
+    pub(crate) fn next_revalidation_cron_checked(
+        &self,
+        now: SystemTime,
+    ) -> Result<Option<SystemTime>, BuildError> {
+        let schedule = match &self.revalidate_cron {
+            Some(schedule) => schedule,
+            None => return Ok(None),
+        };
+        let err = |reason: String| BuildError::InvalidCronSchedule {
+            template_name: self.get_path(),
+            schedule: schedule.to_string(),
+            reason,
+        };
+
+        let parsed = CronSchedule::parse(schedule).map_err(err)?;
+        match parsed.next_after(now.into()) {
+            Some(next) => Ok(Some(next.into())),
+            None => Err(err("schedule never matches any upcoming time".to_string())),
+        }
+    }
+    /// Checks whether or not a page of this template last rendered at the
+    /// given time is unchanged since the time in the given
+    /// `If-Modified-Since` header value, in which case a server can respond
+    /// with a *304 Not Modified*. Since HTTP dates only have second
+    /// precision, any fraction of a second in the render time is ignored.
+    /// If the header can't be parsed, this will return `false`.
+    #[cfg(engine)]
+    pub fn not_modified_since(&self, last_rendered: SystemTime, header: &str) -> bool {
+        let since = match chrono::DateTime::parse_from_rfc2822(header.trim()) {
+            Ok(since) => since,
+            Err(_) => return false,
+        };
+        let last_rendered: chrono::DateTime<chrono::Utc> = last_rendered.into();
+        last_rendered.timestamp() <= since.timestamp()
+    }
+
     // Render characteristic checkers
+    /// Checks whether or not a path should be evicted from the cache of paths
+    /// generated on-demand with incremental generation, given how many are
+    /// currently cached. This will always be `false` if no limit has been
+    /// set with `.incremental_cache_limit()`.
+    #[cfg(engine)]
+    pub fn should_evict(&self, current_count: usize) -> bool {
+        self.incremental_cache_limit
+            .is_some_and(|limit| current_count > limit)
+    }
     /// Checks if this template can revalidate existing prerendered templates.
     #[cfg(engine)]
     pub fn revalidates(&self) -> bool {
-        self.should_revalidate.is_some() || self.revalidate_after.is_some()
+        self.should_revalidate.is_some() || self.revalidates_with_time()
     }
     /// Checks if this template can revalidate existing prerendered templates
     /// after a given time.
     #[cfg(engine)]
     pub fn revalidates_with_time(&self) -> bool {
-        self.revalidate_after.is_some()
+        self.revalidate_after.is_some() || self.revalidate_cron.is_some()
     }
     /// Checks if this template can revalidate existing prerendered templates
     /// based on some given logic.
@@ -47,12 +149,76 @@ impl<G: Html> TemplateInner<G> {
     pub fn revalidates_with_logic(&self) -> bool {
         self.should_revalidate.is_some()
     }
+    /// Checks if this template's revalidation logic needs the state its pages
+    /// were last generated with (i.e. if it was set with
+    /// `.should_revalidate_with_state_fn()`).
+    #[cfg(engine)]
+    pub fn revalidates_with_cached_state(&self) -> bool {
+        self.should_revalidate_uses_state
+    }
+    /// Checks if this template updates the existing state of its pages when
+    /// they revalidate, rather than generating it again from scratch.
+    #[cfg(engine)]
+    pub fn uses_revalidate_state(&self) -> bool {
+        self.revalidate_state.is_some()
+    }
+    /// Gets the kind of revalidation this template uses, distinguishing
+    /// between time-based and logic-based revalidation.
+    #[cfg(engine)]
+    pub fn revalidation_kind(&self) -> RevalidationKind {
+        match (self.revalidates_with_time(), self.revalidates_with_logic()) {
+            (false, false) => RevalidationKind::None,
+            (true, false) => RevalidationKind::TimeBased,
+            (false, true) => RevalidationKind::LogicBased,
+            (true, true) => RevalidationKind::Both,
+        }
+    }
+    /// Gets the policy for serving pages of this template that need
+    /// revalidation, which will allow stale pages to be served if
+    /// `.stale_while_revalidate()` has been set.
+    #[cfg(engine)]
+    pub fn revalidation_policy(&self) -> RevalidationPolicy {
+        if !self.revalidates() {
+            RevalidationPolicy::None
+        } else if self.stale_while_revalidate {
+            RevalidationPolicy::StaleWhileRevalidate
+        } else {
+            RevalidationPolicy::Blocking
+        }
+    }
     /// Checks if this template can render more templates beyond those paths it
     /// explicitly defines.
     #[cfg(engine)]
     pub fn uses_incremental(&self) -> bool {
         self.incremental_generation
     }
+    /// Checks if this template has a fallback to send for pages that are yet
+    /// to be incrementally generated.
+    pub fn has_fallback(&self) -> bool {
+        self.fallback_view.is_some()
+    }
+    /// Checks if this template is allowed to incrementally generate the given
+    /// path, which should be relative to the template root. This will
+    /// always be `true` if no incremental path matcher has been set.
+    #[cfg(engine)]
+    pub fn can_render_incremental(&self, path: &str) -> bool {
+        match &self.incremental_path_matcher {
+            Some(matcher) => matcher(path),
+            None => true,
+        }
+    }
+    /// Checks if the given path, which should include this template's name
+    /// (but not the locale), is one this template will refuse to generate
+    /// with incremental generation, because its incremental path matcher
+    /// rejects it.
+    #[cfg(engine)]
+    pub(crate) fn rejects_incremental_path(&self, path: &str) -> bool {
+        let template_path = self.get_path();
+        let pure_path = path.strip_suffix('/').unwrap_or(path);
+        let pure_path = pure_path.strip_prefix(&template_path).unwrap_or(pure_path);
+        let pure_path = pure_path.strip_prefix('/').unwrap_or(pure_path);
+        !self.can_render_incremental(pure_path)
+    }
     /// Checks if this template is a template to generate paths beneath it.
     #[cfg(engine)]
     pub fn uses_build_paths(&self) -> bool {
@@ -63,11 +229,45 @@ impl<G: Html> TemplateInner<G> {
     pub fn uses_request_state(&self) -> bool {
         self.get_request_state.is_some()
     }
+    /// Checks if this template can produce its request state as a stream of
+    /// chunks.
+    #[cfg(engine)]
+    pub fn uses_stream_request_state(&self) -> bool {
+        self.stream_request_state.is_some()
+    }
+    /// Checks if this template requires requests for it to be authorized.
+    #[cfg(engine)]
+    pub fn uses_auth(&self) -> bool {
+        self.auth_check.is_some()
+    }
+    /// Checks if the given request is authorized to access the pages of this
+    /// template. This will always be `true` if the template doesn't require
+    /// authorization.
+    #[cfg(engine)]
+    pub fn is_authorized(&self, req: &Request) -> bool {
+        match &self.auth_check {
+            Some(auth_check) => auth_check(req),
+            None => true,
+        }
+    }
+    /// Checks if requests for the pages of this template may use the given
+    /// HTTP method. By default, only `GET` and `HEAD` are allowed.
+    #[cfg(engine)]
+    pub fn method_allowed(&self, method: &str) -> bool {
+        self.allowed_methods
+            .iter()
+            .any(|allowed| allowed.eq_ignore_ascii_case(method))
+    }
     /// Checks if this template needs to do anything at build time.
     #[cfg(engine)]
     pub fn uses_build_state(&self) -> bool {
         self.get_build_state.is_some()
     }
+    /// Checks if this template generates build states for many paths at once.
+    #[cfg(engine)]
+    pub fn uses_build_state_batch(&self) -> bool {
+        self.get_build_states_batch.is_some()
+    }
     /// Checks if this template has custom logic to amalgamate build and
     /// request states if both are generated.
     #[cfg(engine)]
@@ -86,4 +286,937 @@ impl<G: Html> TemplateInner<G> {
             && !self.revalidates()
             && !self.uses_incremental()
     }
+    /// Gets the value of the `Cache-Control` header for this template's pages.
+    /// If one hasn't been set with `.cache_control()`, a default will be
+    /// derived from the template's rendering strategies: pages that are only
+    /// generated at build-time can be cached for a day, pages that
+    /// revalidate must be revalidated on every request, and pages that
+    /// generate request state should never be cached.
+    ///
+    /// Note that the derived default is *not* sent with your pages
+    /// automatically (the Perseus defaults will be used unless you set a value
+    /// explicitly), it's provided for integrating with external caches.
+    #[cfg(engine)]
+    pub fn cache_control_header(&self) -> String {
+        if let Some(cache_control) = &self.cache_control {
+            return cache_control.clone();
+        }
+        match self.render_strategy() {
+            RenderStrategy::Basic | RenderStrategy::Ssg | RenderStrategy::IncrementalSsg => {
+                "public, max-age=86400".to_string()
+            }
+            RenderStrategy::Isr => "public, max-age=0, must-revalidate".to_string(),
+            RenderStrategy::Ssr | RenderStrategy::Hybrid => "no-store".to_string(),
+        }
+    }
+    /// Gets the value of the `Content-Type` header for this template's pages,
+    /// which will be `text/html; charset=utf-8` unless one has been set with
+    /// `.content_type()`.
+    #[cfg(engine)]
+    pub fn content_type_header(&self) -> &str {
+        self.content_type
+            .as_deref()
+            .unwrap_or("text/html; charset=utf-8")
+    }
+    /// Checks if this template's content type is a textual format, ignoring
+    /// any parameters (like the charset).
+    #[cfg(engine)]
+    fn has_textual_content_type(&self) -> bool {
+        let content_type = self.content_type_header();
+        let essence = content_type
+            .split(';')
+            .next()
+            .unwrap_or(content_type)
+            .trim()
+            .to_ascii_lowercase();
+
+        essence.starts_with("text/")
+            || essence.ends_with("+json")
+            || essence.ends_with("+xml")
+            || matches!(
+                essence.as_str(),
+                "application/json" | "application/javascript" | "application/xml"
+            )
+    }
+    /// Gets the name of the file that the page of this template at the given
+    /// path (including the template root) will be exported to. If an
+    /// extension has been set with `.output_extension()`, this will be the
+    /// path with that extension (e.g. `feed.xml`), otherwise it will be an
+    /// `index.html` file in a directory for the page (unless the path is
+    /// already an index page).
+    #[cfg(engine)]
+    pub fn output_filename(&self, path: &str) -> String {
+        let path = path.trim_matches('/');
+        match &self.output_extension {
+            Some(ext) => format!("{}.{}", path, ext),
+            None if path.is_empty() => "index.html".to_string(),
+            // If it's already an index page, we don't want `index/index.html`
+            None if path.ends_with("index") => format!("{}.html", path),
+            // Pages are written into their own folders, which prevents a situation of a
+            // template root page outside the directory for the rest of that template's pages
+            // (see #73)
+            None => format!("{}/index.html", path),
+        }
+    }
+    /// Gets the HTTP status code that this template's pages will be served
+    /// with, which will be *200* unless one has been set with
+    /// `.status_code()`.
+    #[cfg(engine)]
+    pub fn get_status_code(&self) -> u16 {
+        self.status_code
+    }
+    /// Gets the canonical URL of the page of this template at the given path
+    /// (including the template root), with the given state. This will be
+    /// `None` if no canonical URL function has been set.
+    #[cfg(engine)]
+    pub fn canonical_url(&self, path: &str, state: &TemplateState) -> Option<String> {
+        self.canonical_url
+            .as_ref()
+            .map(|canonical_url| canonical_url(path, state))
+    }
+    /// Extracts the parameters from the given path (including the template
+    /// root) using the pattern set with `.route_pattern()`, returning a map
+    /// of parameter names to their values. This will be `None` if no pattern
+    /// has been set, or if the path doesn't match it.
+    #[cfg(engine)]
+    pub fn extract_params(&self, path: &str) -> Option<HashMap<String, String>> {
+        let pattern = self.route_pattern.as_ref()?;
+        let pattern_segments = pattern.trim_matches('/').split('/').collect::<Vec<_>>();
+        let path_segments = path.trim_matches('/').split('/').collect::<Vec<_>>();
+        if pattern_segments.len() != path_segments.len() {
+            return None;
+        }
+
+        let mut params = HashMap::new();
+        for (pattern_segment, path_segment) in pattern_segments.into_iter().zip(path_segments) {
+            match pattern_segment.strip_prefix(':') {
+                Some(name) => {
+                    params.insert(name.to_string(), path_segment.to_string());
+                }
+                None if pattern_segment == path_segment => (),
+                None => return None,
+            }
+        }
+
+        Some(params)
+    }
+    /// Checks whether or not this template should be built, given whether or
+    /// not this is a production build. This will only be `false` for drafts
+    /// (see `.draft()`) in production.
+    #[cfg(engine)]
+    pub fn include_in_build(&self, is_production: bool) -> bool {
+        !(self.draft && is_production)
+    }
+    /// Gets the tags that have been added to this template with `.tag()`.
+    #[cfg(engine)]
+    pub fn get_tags(&self) -> &[String] {
+        &self.tags
+    }
+    /// Checks if this template has the given tag.
+    #[cfg(engine)]
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+    /// Checks that this template is basic (see `.is_basic()`), returning an
+    /// error listing the features that stop it from being so if it isn't.
+    /// This can be useful to ensure that pages you expect to be purely
+    /// static stay that way.
+    #[cfg(engine)]
+    pub fn assert_basic(&self) -> Result<(), BuildError> {
+        let features = [
+            ("uses_build_paths", self.uses_build_paths()),
+            ("uses_build_state", self.uses_build_state()),
+            ("uses_request_state", self.uses_request_state()),
+            ("revalidates", self.revalidates()),
+            ("uses_incremental", self.uses_incremental()),
+        ]
+        .into_iter()
+        .filter(|(_, enabled)| *enabled)
+        .map(|(feature, _)| feature.to_string())
+        .collect::<Vec<_>>();
+
+        if features.is_empty() {
+            Ok(())
+        } else {
+            Err(BuildError::TemplateNotBasic {
+                template_name: self.get_path(),
+                features,
+            })
+        }
+    }
+    /// Checks that the root path of this template is valid, which means it
+    /// must not be empty, have leading or trailing whitespace, contain double
+    /// slashes, or contain `..` segments, and that its strategies are
+    /// configured coherently (see `.validate_config()`). This is
+    /// automatically called when your app is built, and build paths are
+    /// checked in the same way.
+    #[cfg(engine)]
+    pub fn validate(&self) -> Result<(), BuildError> {
+        validate_path(&self.path, false).map_err(|reason| BuildError::InvalidPagePath {
+            template_name: self.path.clone(),
+            path: self.path.clone(),
+            reason: reason.to_string(),
+        })?;
+        for alias in self.aliases.iter() {
+            validate_path(alias, false).map_err(|reason| BuildError::InvalidPagePath {
+                template_name: self.path.clone(),
+                path: alias.clone(),
+                reason: reason.to_string(),
+            })?;
+        }
+        self.validate_config()
+    }
+    /// Checks that the combination of strategies this template uses makes
+    /// sense, returning an error naming the missing feature if not. For
+    /// example, incremental generation requires the *build paths* strategy,
+    /// revalidation requires the *build state* strategy, and state
+    /// amalgamation requires both the *build state* and *request state*
+    /// strategies. This also checks that templates requiring authorization
+    /// generate request state.
+    #[cfg(engine)]
+    pub fn validate_config(&self) -> Result<(), BuildError> {
+        if self.uses_auth() && !self.uses_request_state() {
+            return Err(BuildError::AuthWithoutRequestState {
+                template_name: self.get_path(),
+            });
+        }
+        self.next_revalidation_cron_checked(SystemTime::now())?;
+
+        let required_features = [
+            (
+                self.uses_incremental(),
+                self.uses_build_paths(),
+                "build_paths",
+            ),
+            (self.revalidates(), self.uses_build_state(), "build_state"),
+            (
+                self.can_amalgamate_states(),
+                self.uses_build_state(),
+                "build_state",
+            ),
+            (
+                self.can_amalgamate_states(),
+                self.uses_request_state(),
+                "request_state",
+            ),
+            (
+                self.request_state_falls_back,
+                self.uses_build_state(),
+                "build_state",
+            ),
+            (
+                self.stale_while_revalidate,
+                self.revalidates(),
+                "revalidate",
+            ),
+            (
+                self.uses_revalidate_state(),
+                self.revalidates(),
+                "revalidate",
+            ),
+            (
+                self.has_fallback(),
+                self.uses_incremental(),
+                "incremental_generation",
+            ),
+        ];
+        for (needs_feature, has_feature, feature_name) in required_features {
+            if needs_feature && !has_feature {
+                return Err(BuildError::TemplateFeatureNotEnabled {
+                    template_name: self.get_path(),
+                    feature_name: feature_name.to_string(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+    /// Computes a fingerprint of this template's static configuration (its
+    /// path, aliases, tags, revalidation settings, the strategies it uses,
+    /// and its other non-function settings), which can be compared with a
+    /// previous fingerprint to detect when a template's definition has
+    /// changed (e.g. to decide what to rebuild). Two templates defined in
+    /// the same way will always have the same fingerprint.
+    ///
+    /// Functions can't be hashed, so changing the body of one of your
+    /// functions (e.g. your build state function) will *not* change the
+    /// fingerprint, only adding or removing it will. As with
+    /// `.cache_key()`, fingerprints use a stable hash, so they can be
+    /// persisted and compared across builds.
+    #[cfg(engine)]
+    pub fn config_fingerprint(&self) -> u64 {
+        let mut hasher = StableHasher::new();
+        self.get_path().hash(&mut hasher);
+        self.aliases.hash(&mut hasher);
+        self.tags.hash(&mut hasher);
+        self.enabled_features().hash(&mut hasher);
+        self.revalidate_after.hash(&mut hasher);
+        self.revalidate_cron.hash(&mut hasher);
+        self.stale_while_revalidate.hash(&mut hasher);
+        self.incremental_build_limit.hash(&mut hasher);
+        self.incremental_cache_limit.hash(&mut hasher);
+        self.sort_build_paths.hash(&mut hasher);
+        self.build_cost_hint.hash(&mut hasher);
+        self.request_timeout.hash(&mut hasher);
+        self.allowed_methods.hash(&mut hasher);
+        self.cache_control.hash(&mut hasher);
+        self.content_type.hash(&mut hasher);
+        self.output_extension.hash(&mut hasher);
+        self.status_code.hash(&mut hasher);
+        self.state_codec.hash(&mut hasher);
+        self.compress.hash(&mut hasher);
+        self.route_pattern.hash(&mut hasher);
+        self.draft.hash(&mut hasher);
+        self.can_be_rescheduled.hash(&mut hasher);
+        // Default states are stored as JSON, which has sorted object keys
+        self.default_state
+            .as_ref()
+            .map(|state| state.state.to_string())
+            .hash(&mut hasher);
+        hasher.finish()
+    }
+    /// Computes a deterministic cache key for the given page of this template
+    /// and its state, as a hex string. This can be used for things like
+    /// `ETag` headers: identical paths and states will always produce the
+    /// same key, and changing either will change the key.
+    ///
+    /// Keys will be the same across builds and platforms, but they're *not*
+    /// suitable for any security-sensitive purposes.
+    #[cfg(engine)]
+    pub fn cache_key(&self, path: &str, state: &TemplateState) -> String {
+        let mut hasher = StableHasher::new();
+        self.get_path().hash(&mut hasher);
+        path.hash(&mut hasher);
+        // Object keys are stored in sorted order, so this is deterministic
+        state.state.to_string().hash(&mut hasher);
+        format!("{:016x}", hasher.finish())
+    }
+    /// Lists the names of the rendering features this template uses (e.g.
+    /// `build_state`, `revalidate`), in a fixed order. This is mostly
+    /// useful for logging.
+    #[cfg(engine)]
+    pub fn enabled_features(&self) -> Vec<&'static str> {
+        [
+            ("build_paths", self.uses_build_paths()),
+            ("incremental", self.uses_incremental()),
+            ("build_state", self.uses_build_state()),
+            ("request_state", self.uses_request_state()),
+            ("revalidate", self.revalidates()),
+            ("amalgamate_states", self.can_amalgamate_states()),
+        ]
+        .into_iter()
+        .filter(|(_, enabled)| *enabled)
+        .map(|(feature, _)| feature)
+        .collect()
+    }
+    /// Summarizes the rendering strategies this template uses into a single
+    /// [`RenderStrategy`].
+    #[cfg(engine)]
+    pub fn render_strategy(&self) -> RenderStrategy {
+        if self.is_basic() {
+            RenderStrategy::Basic
+        } else if self.uses_request_state() {
+            if self.uses_build_state()
+                || self.uses_build_paths()
+                || self.revalidates()
+                || self.uses_incremental()
+            {
+                RenderStrategy::Hybrid
+            } else {
+                RenderStrategy::Ssr
+            }
+        } else if self.revalidates() {
+            RenderStrategy::Isr
+        } else if self.uses_incremental() {
+            RenderStrategy::IncrementalSsg
+        } else {
+            RenderStrategy::Ssg
+        }
+    }
+    /// Checks whether or not the server should compress a response for one of
+    /// this template's pages with the given body length (in bytes). With
+    /// [`Compression::Auto`], this will compress any body longer than
+    /// [`Compression::AUTO_THRESHOLD`], as long as this template's content
+    /// type (see `.content_type()`) is textual (e.g. HTML, JSON, or XML),
+    /// since other formats (like images) are usually compressed already.
+    #[cfg(engine)]
+    pub fn should_compress(&self, body_len: usize) -> bool {
+        match self.compress {
+            Compression::Auto => {
+                body_len > Compression::AUTO_THRESHOLD && self.has_textual_content_type()
+            }
+            Compression::Always => true,
+            Compression::Never => false,
+        }
+    }
+    /// Checks whether or not this template's pages are currently enabled,
+    /// using the function set with `.enabled_when()`. If there isn't one,
+    /// this will always be `true`.
+    #[cfg(engine)]
+    pub fn is_enabled(&self) -> bool {
+        self.enabled_when
+            .as_ref()
+            .is_none_or(|enabled_when| enabled_when())
+    }
+    /// Describes this template in a [`ManifestEntry`], for use by
+    /// client-side routers and the like. The locales of the entry will be
+    /// empty, since templates don't know about your app's locales (see
+    /// `PerseusApp::to_manifest()`).
+    #[cfg(engine)]
+    pub fn to_manifest_entry(&self) -> ManifestEntry {
+        ManifestEntry {
+            path: self.get_path(),
+            strategy: self.render_strategy().as_str().to_string(),
+            has_state: self.uses_build_state() || self.uses_request_state(),
+            locales: Vec::new(),
+        }
+    }
+}
+
+#[cfg(all(test, engine))]
+mod tests {
+    use super::*;
+    use crate::errors::*;
+    use crate::state::{BuildPaths, StateGeneratorInfo, TemplateState};
+    use crate::template::core::test_utils::*;
+    use crate::template::{
+        Compression, RenderStrategy, RevalidationKind, RevalidationPolicy, Template,
+    };
+    use sycamore::web::SsrNode;
+
+    #[test]
+    fn render_strategy_summarizes_features() {
+        let ssg = Template::<SsrNode>::build("test")
+            .build_state_fn(
+                |info: StateGeneratorInfo<()>| async move { TestState { title: info.path } },
+            )
+            .build();
+        assert_eq!(ssg.render_strategy(), RenderStrategy::Ssg);
+
+        let isr = Template::<SsrNode>::build("test")
+            .should_revalidate_fn(|_info: StateGeneratorInfo<()>, _req: Request| async { true })
+            .build();
+        assert_eq!(isr.render_strategy(), RenderStrategy::Isr);
+
+        let basic = Template::<SsrNode>::build("test").build();
+        assert_eq!(basic.render_strategy(), RenderStrategy::Basic);
+    }
+
+    #[test]
+    fn path_matches_get_path() {
+        let template = Template::<SsrNode>::build("blog").build();
+        assert_eq!(template.path(), template.get_path());
+        assert!(matches!(
+            template.path(),
+            std::borrow::Cow::Borrowed("blog")
+        ));
+
+        let index = Template::<SsrNode>::build("index").build();
+        assert_eq!(index.path(), "");
+    }
+
+    #[test]
+    fn requires_auth_checks_requests() {
+        let template = Template::<SsrNode>::build("dashboard")
+            .request_state_fn(|info: StateGeneratorInfo<()>, _req: Request| async move {
+                TestState { title: info.path }
+            })
+            .requires_auth(|req| req.headers().contains_key("Authorization"))
+            .build();
+        assert!(template.validate().is_ok());
+
+        let req = Request::builder().uri("/dashboard").body(()).unwrap();
+        assert!(!template.is_authorized(&req));
+        let req = Request::builder()
+            .uri("/dashboard")
+            .header("Authorization", "Bearer token")
+            .body(())
+            .unwrap();
+        assert!(template.is_authorized(&req));
+
+        let static_template = Template::<SsrNode>::build("dashboard")
+            .requires_auth(|_| false)
+            .build();
+        assert!(matches!(
+            static_template.validate(),
+            Err(BuildError::AuthWithoutRequestState { .. })
+        ));
+    }
+
+    #[test]
+    fn cache_key_is_deterministic() {
+        let template = Template::<SsrNode>::build("blog").build();
+        let state = |title: &str| -> TemplateState {
+            TestState {
+                title: title.to_string(),
+            }
+            .into()
+        };
+
+        let key = template.cache_key("post", &state("Hello"));
+        assert_eq!(key, template.cache_key("post", &state("Hello")));
+        assert_ne!(key, template.cache_key("post", &state("Goodbye")));
+        assert_ne!(key, template.cache_key("other", &state("Hello")));
+    }
+
+    #[test]
+    fn assert_basic_lists_features() {
+        let template = Template::<SsrNode>::build("test")
+            .request_state_fn(|info: StateGeneratorInfo<()>, _req: Request| async move {
+                TestState { title: info.path }
+            })
+            .build();
+        let err = template.assert_basic().unwrap_err();
+        assert!(err.to_string().contains("uses_request_state"));
+
+        let basic = Template::<SsrNode>::build("test").build();
+        assert!(basic.assert_basic().is_ok());
+    }
+
+    #[test]
+    fn cache_control_derives_from_strategy() {
+        let basic = Template::<SsrNode>::build("test").build();
+        assert!(basic.cache_control_header().contains("max-age=86400"));
+
+        let ssr = Template::<SsrNode>::build("test")
+            .request_state_fn(|info: StateGeneratorInfo<()>, _req: Request| async move {
+                TestState { title: info.path }
+            })
+            .build();
+        assert_eq!(ssr.cache_control_header(), "no-store");
+
+        let custom = Template::<SsrNode>::build("test")
+            .cache_control("private, max-age=60")
+            .build();
+        assert_eq!(custom.cache_control_header(), "private, max-age=60");
+        let headers = custom
+            .get_headers(TemplateState::empty(), TemplateState::empty(), None)
+            .unwrap();
+        assert_eq!(headers[http::header::CACHE_CONTROL], "private, max-age=60");
+    }
+
+    #[test]
+    fn enabled_features_lists_names() {
+        let template = Template::<SsrNode>::build("test")
+            .build_paths_fn(|| async {
+                BuildPaths {
+                    paths: Vec::new(),
+                    extra: ().into(),
+                }
+            })
+            .incremental_generation()
+            .build();
+        assert_eq!(
+            template.enabled_features(),
+            vec!["build_paths", "incremental"]
+        );
+    }
+
+    #[test]
+    fn content_type_defaults_to_html() {
+        let template = Template::<SsrNode>::build("test").build();
+        assert_eq!(template.content_type_header(), "text/html; charset=utf-8");
+
+        let template = Template::<SsrNode>::build("feed")
+            .content_type("application/rss+xml")
+            .build();
+        assert_eq!(template.content_type_header(), "application/rss+xml");
+    }
+
+    #[test]
+    fn validate_config_rejects_incoherent_strategies() {
+        let build_state =
+            |info: StateGeneratorInfo<()>| async move { TestState { title: info.path } };
+        let request_state = |info: StateGeneratorInfo<()>, _req: Request| async move {
+            TestState { title: info.path }
+        };
+        let amalgamate = |_info: StateGeneratorInfo<()>,
+                          build_state: TestState,
+                          _request_state: TestState| async move { build_state };
+        let missing_feature = |template: Template<SsrNode>| match template.validate_config() {
+            Err(BuildError::TemplateFeatureNotEnabled { feature_name, .. }) => feature_name,
+            other => panic!("expected missing feature, found {:?}", other),
+        };
+
+        let incremental = Template::<SsrNode>::build("test")
+            .incremental_generation()
+            .build();
+        assert_eq!(missing_feature(incremental), "build_paths");
+        let revalidating = Template::<SsrNode>::build("test")
+            .request_state_fn(request_state)
+            .revalidate_after("1h")
+            .build();
+        assert_eq!(missing_feature(revalidating), "build_state");
+        let amalgamating = Template::<SsrNode>::build("test")
+            .build_state_fn(build_state)
+            .amalgamate_states_fn(amalgamate)
+            .build();
+        assert_eq!(missing_feature(amalgamating), "request_state");
+        let fallback = Template::<SsrNode>::build("test")
+            .fallback_fn(|cx| sycamore::view! { cx, p { "Loading..." } })
+            .build();
+        assert_eq!(missing_feature(fallback), "incremental_generation");
+
+        let coherent = Template::<SsrNode>::build("test")
+            .build_state_fn(build_state)
+            .request_state_fn(request_state)
+            .amalgamate_states_fn(amalgamate)
+            .revalidate_after("1h")
+            .build();
+        assert!(coherent.validate_config().is_ok());
+    }
+
+    #[test]
+    fn tags_can_be_added_and_checked() {
+        let template = Template::<SsrNode>::build("post")
+            .tag("blog")
+            .tag("content")
+            .build();
+        assert!(template.has_tag("blog"));
+        assert!(template.has_tag("content"));
+        assert!(!template.has_tag("docs"));
+        assert_eq!(template.get_tags(), ["blog", "content"]);
+    }
+
+    #[test]
+    fn canonical_url_fn_receives_path_and_state() {
+        let template = Template::<SsrNode>::build("post").build();
+        assert_eq!(
+            template.canonical_url("post/hello", &TemplateState::empty()),
+            None
+        );
+
+        let template = Template::<SsrNode>::build("post")
+            .canonical_url_fn(|path, state: Option<&TestState>| match state {
+                Some(state) => format!("https://example.com/{}?title={}", path, state.title),
+                None => format!("https://example.com/{}", path),
+            })
+            .build();
+        assert_eq!(
+            template.canonical_url("post/hello", &TemplateState::empty()),
+            Some("https://example.com/post/hello".to_string())
+        );
+        let state: TemplateState = TestState {
+            title: "hi".to_string(),
+        }
+        .into();
+        assert_eq!(
+            template.canonical_url("post/hello", &state),
+            Some("https://example.com/post/hello?title=hi".to_string())
+        );
+    }
+
+    #[test]
+    fn revalidation_kind_distinguishes_time_and_logic() {
+        let basic = Template::<SsrNode>::build("test").build();
+        assert_eq!(basic.revalidation_kind(), RevalidationKind::None);
+
+        let time = Template::<SsrNode>::build("test")
+            .revalidate_after("1h")
+            .build();
+        assert_eq!(time.revalidation_kind(), RevalidationKind::TimeBased);
+
+        let both = Template::<SsrNode>::build("test")
+            .revalidate_after("1h")
+            .should_revalidate_fn(|_info: StateGeneratorInfo<()>, _req: Request| async { true })
+            .build();
+        assert_eq!(both.revalidation_kind(), RevalidationKind::Both);
+    }
+
+    #[test]
+    fn stale_while_revalidate_allows_stale_serving() {
+        let blocking = Template::<SsrNode>::build("test")
+            .revalidate_after("1h")
+            .build();
+        assert_eq!(blocking.revalidation_policy(), RevalidationPolicy::Blocking);
+        assert!(!blocking.revalidation_policy().allows_stale());
+
+        let stale = Template::<SsrNode>::build("test")
+            .revalidate_after("1h")
+            .stale_while_revalidate(true)
+            .build();
+        assert_eq!(
+            stale.revalidation_policy(),
+            RevalidationPolicy::StaleWhileRevalidate
+        );
+        assert!(stale.revalidation_policy().allows_stale());
+
+        let static_template = Template::<SsrNode>::build("test")
+            .stale_while_revalidate(true)
+            .build();
+        assert_eq!(
+            static_template.revalidation_policy(),
+            RevalidationPolicy::None
+        );
+        assert!(static_template.validate_config().is_err());
+    }
+
+    #[test]
+    fn route_pattern_extracts_params() {
+        let template = Template::<SsrNode>::build("blog")
+            .route_pattern("/blog/:year/:slug")
+            .build();
+        let params = template.extract_params("/blog/2024/hi").unwrap();
+        assert_eq!(params.len(), 2);
+        assert_eq!(params["year"], "2024");
+        assert_eq!(params["slug"], "hi");
+
+        assert!(template.extract_params("blog/2024").is_none());
+        assert!(template.extract_params("docs/2024/hi").is_none());
+        assert!(Template::<SsrNode>::build("blog")
+            .build()
+            .extract_params("blog/2024/hi")
+            .is_none());
+    }
+
+    #[test]
+    fn next_revalidation_adds_interval_to_last_render() {
+        use std::time::{Duration, SystemTime};
+
+        let last_rendered = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let template = Template::<SsrNode>::build("test")
+            .revalidate_after("1h")
+            .build();
+        assert_eq!(
+            template.next_revalidation(last_rendered),
+            Some(last_rendered + Duration::from_secs(3600))
+        );
+
+        let basic = Template::<SsrNode>::build("test").build();
+        assert_eq!(basic.next_revalidation(last_rendered), None);
+    }
+
+    #[test]
+    fn drafts_are_excluded_from_production_builds() {
+        let draft = Template::<SsrNode>::build("post").draft(true).build();
+        assert!(!draft.include_in_build(true));
+        assert!(draft.include_in_build(false));
+
+        let published = Template::<SsrNode>::build("post").build();
+        assert!(published.include_in_build(true));
+        assert!(published.include_in_build(false));
+    }
+
+    #[test]
+    fn to_manifest_entry_describes_template() {
+        let template = Template::<SsrNode>::build("blog")
+            .build_paths_fn(|| async {
+                BuildPaths {
+                    paths: vec!["a".to_string()],
+                    extra: ().into(),
+                }
+            })
+            .build_state_fn(
+                |info: StateGeneratorInfo<()>| async move { TestState { title: info.path } },
+            )
+            .build();
+        let entry = template.to_manifest_entry();
+        assert_eq!(entry.path, "blog");
+        assert_eq!(entry.strategy, "ssg");
+        assert!(entry.has_state);
+        assert!(entry.locales.is_empty());
+
+        let json = serde_json::to_value(&entry).unwrap();
+        assert_eq!(json["strategy"], "ssg");
+    }
+
+    #[test]
+    fn should_compress_respects_compression_setting() {
+        let template = Template::<SsrNode>::build("test").build();
+        let large_html = format!("<p>{}</p>", "a".repeat(4096));
+        assert!(template.should_compress(large_html.len()));
+        assert!(!template.should_compress("<p>Hi</p>".len()));
+
+        let always = Template::<SsrNode>::build("test")
+            .compress(Compression::Always)
+            .build();
+        assert!(always.should_compress(1));
+        let never = Template::<SsrNode>::build("test")
+            .compress(Compression::Never)
+            .build();
+        assert!(!never.should_compress(large_html.len()));
+    }
+
+    #[test]
+    fn should_compress_only_compresses_textual_content_types() {
+        let feed = Template::<SsrNode>::build("feed")
+            .content_type("application/rss+xml")
+            .build();
+        assert!(feed.should_compress(4096));
+        let data = Template::<SsrNode>::build("data")
+            .content_type("Application/JSON; charset=utf-8")
+            .build();
+        assert!(data.should_compress(4096));
+        let image = Template::<SsrNode>::build("image")
+            .content_type("image/png")
+            .build();
+        assert!(!image.should_compress(4096));
+        // Other settings still override this
+        let image = Template::<SsrNode>::build("image")
+            .content_type("image/png")
+            .compress(Compression::Always)
+            .build();
+        assert!(image.should_compress(4096));
+    }
+
+    #[test]
+    fn not_modified_since_compares_header_dates() {
+        use std::time::{Duration, UNIX_EPOCH};
+
+        let template = Template::<SsrNode>::build("test").build();
+        // This is `Sun, 06 Nov 1994 08:49:37 GMT`, plus a fraction of a second
+        let last_rendered = UNIX_EPOCH + Duration::from_millis(784_111_777_500);
+
+        assert!(template.not_modified_since(last_rendered, "Sun, 06 Nov 1994 08:49:37 GMT"));
+        assert!(template.not_modified_since(last_rendered, "Mon, 07 Nov 1994 08:49:37 GMT"));
+        assert!(!template.not_modified_since(last_rendered, "Sat, 05 Nov 1994 08:49:37 GMT"));
+        assert!(!template.not_modified_since(last_rendered, "yesterday"));
+    }
+
+    #[test]
+    fn output_filename_uses_extension() {
+        let feed = Template::<SsrNode>::build("feed")
+            .content_type("application/rss+xml")
+            .output_extension("xml")
+            .build();
+        assert_eq!(feed.output_filename("/feed"), "feed.xml");
+
+        let template = Template::<SsrNode>::build("about").build();
+        assert_eq!(template.output_filename("about"), "about/index.html");
+        assert_eq!(template.output_filename("docs/index"), "docs/index.html");
+        assert_eq!(template.output_filename(""), "index.html");
+    }
+
+    #[test]
+    fn enabled_when_gates_template() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::Arc;
+
+        assert!(Template::<SsrNode>::build("test").build().is_enabled());
+
+        let flag = Arc::new(AtomicBool::new(false));
+        let template_flag = flag.clone();
+        let template = Template::<SsrNode>::build("test")
+            .enabled_when(move || template_flag.load(Ordering::SeqCst))
+            .build();
+        assert!(!template.is_enabled());
+        flag.store(true, Ordering::SeqCst);
+        assert!(template.is_enabled());
+    }
+
+    #[test]
+    fn revalidate_cron_schedules_next_matching_time() {
+        use chrono::TimeZone;
+
+        let build_state =
+            |info: StateGeneratorInfo<()>| async move { TestState { title: info.path } };
+        let template = Template::<SsrNode>::build("test")
+            .build_state_fn(build_state)
+            .revalidate_cron("0 3 * * *")
+            .build();
+        assert!(template.revalidates_with_time());
+        assert!(template.validate_config().is_ok());
+
+        let two_am = chrono::Utc.with_ymd_and_hms(2023, 5, 10, 2, 0, 0).unwrap();
+        let next = template.next_revalidation_cron(two_am.into()).unwrap();
+        assert_eq!(
+            chrono::DateTime::<chrono::Utc>::from(next),
+            chrono::Utc.with_ymd_and_hms(2023, 5, 10, 3, 0, 0).unwrap()
+        );
+        // After 3am, the next revalidation is the following day
+        let four_am = chrono::Utc.with_ymd_and_hms(2023, 5, 10, 4, 0, 0).unwrap();
+        let next = template.next_revalidation_cron(four_am.into()).unwrap();
+        assert_eq!(
+            chrono::DateTime::<chrono::Utc>::from(next),
+            chrono::Utc.with_ymd_and_hms(2023, 5, 11, 3, 0, 0).unwrap()
+        );
+
+        let invalid = Template::<SsrNode>::build("test")
+            .build_state_fn(build_state)
+            .revalidate_cron("0 25 * * *")
+            .build();
+        assert!(matches!(
+            invalid.validate_config(),
+            Err(BuildError::InvalidCronSchedule { .. })
+        ));
+        assert!(invalid.next_revalidation_cron(two_am.into()).is_none());
+
+        // This parses, but there's never a 31st of February
+        let never = Template::<SsrNode>::build("test")
+            .build_state_fn(build_state)
+            .revalidate_cron("0 0 31 2 *")
+            .build();
+        assert!(matches!(
+            never.validate_config(),
+            Err(BuildError::InvalidCronSchedule { .. })
+        ));
+        assert!(matches!(
+            never.next_revalidation_cron_checked(two_am.into()),
+            Err(BuildError::InvalidCronSchedule { .. })
+        ));
+        assert!(never.next_revalidation_cron(two_am.into()).is_none());
+        assert!(matches!(
+            Template::<SsrNode>::build("test")
+                .build()
+                .next_revalidation_cron_checked(two_am.into()),
+            Ok(None)
+        ));
+    }
+
+    #[test]
+    fn aliases_are_included_in_all_paths() {
+        let template = Template::<SsrNode>::build("about")
+            .alias("about-us")
+            .alias("/company/")
+            .build();
+        assert_eq!(template.all_paths(), vec!["about", "about-us", "company"]);
+        assert!(template.is_alias("company"));
+        assert!(!template.is_alias("about"));
+        assert!(template.validate().is_ok());
+
+        let invalid = Template::<SsrNode>::build("about").alias("a//b").build();
+        assert!(matches!(
+            invalid.validate(),
+            Err(BuildError::InvalidPagePath { .. })
+        ));
+    }
+
+    #[test]
+    fn method_allowed_defaults_to_get_and_head() {
+        let template = Template::<SsrNode>::build("test").build();
+        assert!(template.method_allowed("GET"));
+        assert!(template.method_allowed("HEAD"));
+        assert!(!template.method_allowed("POST"));
+
+        let custom = Template::<SsrNode>::build("test")
+            .allowed_methods(["get", "post"])
+            .build();
+        assert!(custom.method_allowed("POST"));
+        assert!(!custom.method_allowed("HEAD"));
+    }
+
+    #[test]
+    fn config_fingerprint_tracks_static_config() {
+        let define = |path: &str| {
+            Template::<SsrNode>::build(path)
+                .build_state_fn(|info: StateGeneratorInfo<()>| async move {
+                    TestState { title: info.path }
+                })
+                .revalidate_after("1h")
+                .tag("blog")
+                .build()
+        };
+        let fingerprint = define("blog").config_fingerprint();
+        assert_eq!(fingerprint, define("blog").config_fingerprint());
+        assert_ne!(fingerprint, define("news").config_fingerprint());
+
+        let retagged = Template::<SsrNode>::build("blog")
+            .build_state_fn(
+                |info: StateGeneratorInfo<()>| async move { TestState { title: info.path } },
+            )
+            .revalidate_after("1h")
+            .tag("news")
+            .build();
+        assert_ne!(fingerprint, retagged.config_fingerprint());
+    }
 }
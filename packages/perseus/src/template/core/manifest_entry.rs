@@ -0,0 +1,21 @@
+use serde::{Deserialize, Serialize};
+
+/// A description of a template's pages, intended for client-side routers and
+/// other tooling that needs to know about an app's pages without running
+/// it. This can be generated for a single template with
+/// `.to_manifest_entry()`, or for a whole app with
+/// `PerseusApp::to_manifest()`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// The root path of the template.
+    pub path: String,
+    /// The rendering strategy of the template, as a lowercase string (e.g.
+    /// `ssg` or `incremental_ssg`).
+    pub strategy: String,
+    /// Whether or not the template's pages carry any state.
+    pub has_state: bool,
+    /// The locales the template's pages are built for. Templates don't know
+    /// about your app's locales, so this will be empty unless the entry was
+    /// generated by `PerseusApp::to_manifest()` for an app using i18n.
+    pub locales: Vec<String>,
+}
@@ -1,24 +1,49 @@
 // This module contains the primary shared logic in Perseus, and is broken up to
 // avoid a 2000-line file.
 
+mod compression;
+mod defaults;
 mod getters;
+#[cfg(engine)]
+mod manifest_entry;
+mod render_strategy;
 mod renderers;
+mod revalidation_kind;
+mod revalidation_policy;
 mod setters;
+mod state_codec;
 mod utils;
 // These are broken out because of state-management closure wrapping
 mod entity;
 mod state_setters;
+#[cfg(all(test, engine))]
+pub(crate) mod test_utils;
 
 use std::ops::Deref;
 
+pub use compression::Compression;
+pub use defaults::TemplateDefaults;
 pub(crate) use entity::{Entity, EntityMap, Forever};
+#[cfg(engine)]
+pub use manifest_entry::ManifestEntry;
+pub use render_strategy::RenderStrategy;
+pub use revalidation_kind::RevalidationKind;
+pub use revalidation_policy::RevalidationPolicy;
+pub use state_codec::StateCodec;
 pub(crate) use utils::*;
 
 #[cfg(engine)]
 use super::fn_types::*;
-use super::TemplateFn;
+use super::{FallbackFn, LayoutFn, TemplateFn};
+#[cfg(engine)]
+use crate::state::TemplateState;
 #[cfg(engine)]
 use crate::utils::ComputedDuration;
+#[cfg(engine)]
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+};
 use sycamore::{prelude::create_scope, view::View, web::Html};
 
 /// A single template in an app. Each template is comprised of a Sycamore view,
@@ -27,6 +52,10 @@ use sycamore::{prelude::create_scope, view::View, web::Html};
 /// template could have a state `struct` that stores a title and some content,
 /// which could then render as many pages as desired.
 ///
+/// Templates are not generic over their state types (state is stored
+/// internally in a type-erased form), so templates with completely different
+/// states can be stored together, in, say, a `Vec<Template<G>>`.
+///
 /// You can read more about the templates system [here](https://framesurge.sh/perseus/en-US/docs/next/core-principles).
 #[derive(Debug)]
 pub struct Template<G: Html> {
@@ -66,14 +95,30 @@ pub struct TemplateInner<G: Html> {
     /// to be prerendered in some way. This should be very similar to the
     /// function that hydrates your template on the client side.
     /// This will be executed inside `sycamore::render_to_string`, and should
-    /// return a `Template<SsrNode>`. This takes an `Option<Props>`
-    /// because otherwise efficient typing is almost impossible for templates
-    /// without any properties (solutions welcome in PRs!).
+    /// return a `View<G>`. State is passed in untyped, and the setters (e.g.
+    /// `.view_with_state()`) handle deserializing it, so templates without
+    /// any state can just use `.view()`, which ignores it entirely.
     // Public to the crate so capsules can shadow these functions for property support
     pub(crate) view: TemplateFn<G>,
+    /// Whether or not `view` has been set by the user, which is used to
+    /// support strict mode.
+    view_set: bool,
+    /// The layouts that the views of this template's pages will be wrapped
+    /// in, from innermost to outermost.
+    layouts: Vec<LayoutFn<G>>,
+    /// A function that renders a placeholder for pages of this template that
+    /// are yet to be incrementally generated, which will be sent to the user
+    /// straight away while the page's state is fetched. This is needed on
+    /// both the engine-side and the browser-side, since the browser will
+    /// hydrate it.
+    fallback_view: Option<FallbackFn<G>>,
+    /// Other paths at which this template's root page will also be served.
+    /// These are needed by the router on both the engine-side and the
+    /// browser-side.
+    aliases: Vec<String>,
     /// A function that will be used to populate the document's `<head>` with
     /// metadata such as the title. This will be passed state in
-    /// the same way as `template`, but will always be rendered to a string,
+    /// the same way as `view`, but will always be rendered to a string,
     /// which will then be interpolated directly into the `<head>`,
     /// so reactivity here will not work!
     #[cfg(engine)]
@@ -86,6 +131,41 @@ pub struct TemplateInner<G: Html> {
     /// create sensible cache control headers.
     #[cfg(engine)]
     pub(crate) set_headers: Option<SetHeadersFn>,
+    /// The value of the `Cache-Control` header to use for this template's
+    /// pages, if the default headers are being used.
+    #[cfg(engine)]
+    cache_control: Option<String>,
+    /// The value of the `Content-Type` header for this template's pages, if
+    /// they aren't HTML.
+    #[cfg(engine)]
+    content_type: Option<String>,
+    /// The file extension to use for this template's pages when exporting,
+    /// if they shouldn't be exported as HTML.
+    #[cfg(engine)]
+    output_extension: Option<String>,
+    /// The HTTP status code to serve this template's pages with.
+    #[cfg(engine)]
+    status_code: u16,
+    /// A function that determines whether or not this template's pages are
+    /// currently enabled (e.g. from a runtime feature flag).
+    #[cfg(engine)]
+    enabled_when: Option<Box<dyn Fn() -> bool + Send + Sync>>,
+    /// A function that will be run on the HTML of each of this template's
+    /// pages after it's been prerendered.
+    #[cfg(engine)]
+    transform_html: Option<TransformHtmlFn>,
+    /// A function that will render an inline error for this template's pages
+    /// if their views fail on the engine-side.
+    #[cfg(engine)]
+    error_view: Option<ErrorViewFn<G>>,
+    /// A function that will render the body of the 404 page for any missing
+    /// pages of this template.
+    #[cfg(engine)]
+    not_found_view: Option<NotFoundFn>,
+    /// A function that will be run once when this template starts being built,
+    /// before any paths or state are generated.
+    #[cfg(engine)]
+    build_start: Option<BuildStartFn>,
     /// A function that generates the information to begin building a template.
     /// This is responsible for generating all the paths that will built for
     /// that template at build-time (which may later be extended with
@@ -94,6 +174,11 @@ pub struct TemplateInner<G: Html> {
     /// functions.
     #[cfg(engine)]
     get_build_paths: Option<GetBuildPathsFn>,
+    /// A function that generates the paths that will be built for this
+    /// template along with metadata about them (e.g. for sitemaps). If this is
+    /// set, `get_build_paths` will be set to use it as well.
+    #[cfg(engine)]
+    get_build_paths_meta: Option<GetBuildPathsMetaFn>,
     /// Defines whether or not any new paths that match this template will be
     /// prerendered and cached in production. This allows you to
     /// have potentially billions of templates and retain a super-fast build
@@ -104,23 +189,107 @@ pub struct TemplateInner<G: Html> {
     /// can use a different template.
     #[cfg(engine)]
     incremental_generation: bool,
+    /// A function that decides whether or not a path that would be generated
+    /// incrementally is allowed, before any generation occurs. If this
+    /// returns `false`, the server will return a 404. If this isn't set,
+    /// all paths will be allowed.
+    #[cfg(engine)]
+    incremental_path_matcher: Option<IncrementalPathMatcherFn>,
+    /// The maximum number of paths from `get_build_paths` that will be
+    /// prerendered at build-time if incremental generation is enabled. Any
+    /// others will be generated on-demand.
+    #[cfg(engine)]
+    incremental_build_limit: Option<usize>,
+    /// Whether or not the paths from `get_build_paths` should be sorted
+    /// lexically, making build artifacts reproducible.
+    #[cfg(engine)]
+    sort_build_paths: bool,
+    /// A hint of the relative cost of building each of this template's pages,
+    /// for use by external build schedulers.
+    #[cfg(engine)]
+    build_cost_hint: Option<u32>,
+    /// The maximum number of paths generated on-demand with incremental
+    /// generation that will stay cached at once.
+    #[cfg(engine)]
+    incremental_cache_limit: Option<usize>,
+    /// The maximum amount of time that request state generation may take
+    /// before it's abandoned.
+    #[cfg(engine)]
+    request_timeout: Option<std::time::Duration>,
+    /// The HTTP methods that requests for this template's pages may use.
+    #[cfg(engine)]
+    allowed_methods: Vec<String>,
+    /// The paths that have been generated on-demand with incremental
+    /// generation, from least to most recently used. This is only tracked if
+    /// there's a limit on how many can stay cached.
+    #[cfg(engine)]
+    incremental_lru: Mutex<VecDeque<String>>,
     /// A function that gets the initial state to use to prerender the template
     /// at build time. This will be passed the path of the template, and
     /// will be run for any sub-paths.
     #[cfg(engine)]
     get_build_state: Option<GetBuildStateFn>,
+    /// A function that gets the initial states for many paths at once, which
+    /// will be preferred over `get_build_state` at build-time (which will
+    /// also be set to use this).
+    #[cfg(engine)]
+    get_build_states_batch: Option<GetBuildStatesBatchFn>,
+    /// A cache of the states generated by `get_build_state`, keyed by locale
+    /// and path, if memoization has been enabled.
+    #[cfg(engine)]
+    build_state_cache: Option<Mutex<HashMap<(String, String), TemplateState>>>,
+    /// The format to use when encoding this template's state for external
+    /// caches.
+    #[cfg(engine)]
+    state_codec: StateCodec,
+    /// Whether or not the server should compress responses for this
+    /// template's pages.
+    #[cfg(engine)]
+    compress: Compression,
+    /// A state to use when rendering this template without any state, which
+    /// `get_build_state` will also be set to produce.
+    #[cfg(engine)]
+    default_state: Option<TemplateState>,
+    /// A function that checks the state generated by `get_build_state`
+    /// upholds any invariants the user requires, before it's cached.
+    #[cfg(engine)]
+    validate_state: Option<ValidateStateFn>,
     /// A function that will run on every request to generate a state for that
     /// request. This allows server-side-rendering. This can be used with
     /// `get_build_state`, though custom amalgamation logic must be provided.
     #[cfg(engine)]
     get_request_state: Option<GetRequestStateFn>,
+    /// A function that will run on every request to generate a stream of
+    /// states for that request, each of which will be rendered as a separate
+    /// chunk of HTML, so that large pages can begin to be sent before all
+    /// their state has been computed.
+    #[cfg(engine)]
+    stream_request_state: Option<StreamRequestStateFn>,
+    /// Whether or not `get_request_state` can tell Perseus to fall back to
+    /// the build state, which requires `get_build_state`.
+    #[cfg(engine)]
+    request_state_falls_back: bool,
     /// A function to be run on every request to check if a template prerendered
     /// at build-time should be prerendered again. If used with
     /// `revalidate_after`, this function will only be run after that time
-    /// period. This function will not be parsed anything specific to the
-    /// request that invoked it.
+    /// period. This function will be passed the request that invoked it, so
+    /// it can make decisions based on things like query parameters.
     #[cfg(engine)]
     should_revalidate: Option<ShouldRevalidateFn>,
+    /// Whether or not `should_revalidate` needs the state the page was last
+    /// generated with, which will only be read from the mutable store if so.
+    #[cfg(engine)]
+    should_revalidate_uses_state: bool,
+    /// A function that checks whether or not a request is authorized to
+    /// access the pages of this template, which will be run before any
+    /// request-time state generation. Unauthorized requests will receive a
+    /// 401 error. This requires `get_request_state`.
+    #[cfg(engine)]
+    auth_check: Option<AuthCheckFn>,
+    /// Middleware that will be run, in order, on each request for this
+    /// template's pages before `get_request_state`.
+    #[cfg(engine)]
+    request_middleware: Vec<RequestMiddlewareFn>,
     /// A length of time after which to prerender the template again. The given
     /// duration will be waited for, and the next request after it will lead
     /// to a revalidation. Note that, if this is used with incremental
@@ -130,12 +299,44 @@ pub struct TemplateInner<G: Html> {
     /// that with `should_revalidate`).
     #[cfg(engine)]
     revalidate_after: Option<ComputedDuration>,
+    /// A cron expression (e.g. `0 3 * * *` for 3am UTC every day) that
+    /// specifies when to prerender the template again. This works like
+    /// `revalidate_after`, except that the next revalidation is scheduled for
+    /// the next time matching the expression, rather than after a fixed
+    /// interval. If both are set, `revalidate_after` takes precedence.
+    #[cfg(engine)]
+    revalidate_cron: Option<String>,
+    /// Whether or not pages that need revalidation may be served stale while
+    /// they're revalidated in the background. This requires revalidation.
+    #[cfg(engine)]
+    stale_while_revalidate: bool,
+    /// A function that updates the state a page was last generated with when
+    /// it revalidates, which will be used instead of running
+    /// `get_build_state` again. This requires revalidation.
+    #[cfg(engine)]
+    revalidate_state: Option<RevalidateStateFn>,
     /// Custom logic to amalgamate potentially different states generated at
     /// build and request time. This is only necessary if your template uses
     /// both `build_state` and `request_state`. If not specified and both are
     /// generated, request state will be prioritized.
     #[cfg(engine)]
     amalgamate_states: Option<AmalgamateStatesFn>,
+    /// A function that generates the canonical URL of each of this template's
+    /// pages.
+    #[cfg(engine)]
+    canonical_url: Option<CanonicalUrlFn>,
+    /// A pattern describing the paths of this template's pages (e.g.
+    /// `blog/:year/:slug`), from which parameters can be extracted.
+    #[cfg(engine)]
+    route_pattern: Option<String>,
+    /// Whether or not this template is a draft, which will exclude it from
+    /// production builds.
+    #[cfg(engine)]
+    draft: bool,
+    /// Arbitrary tags for grouping templates, which can be used by external
+    /// tools (e.g. to rebuild only some groups of pages).
+    #[cfg(engine)]
+    tags: Vec<String>,
     /// Whether or not this template is actually a capsule. This impacts
     /// significant aspects of internal handling.
     ///
@@ -152,10 +353,16 @@ pub struct TemplateInner<G: Html> {
 }
 impl<G: Html> std::fmt::Debug for TemplateInner<G> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("Template")
+        let mut debug = f.debug_struct("Template");
+        debug
             .field("path", &self.path)
-            .field("is_capsule", &self.is_capsule)
-            .finish()
+            .field("is_capsule", &self.is_capsule);
+        // The closures can't be printed, so we summarize which have been set instead
+        #[cfg(engine)]
+        debug
+            .field("features", &self.enabled_features())
+            .field("revalidate_after", &self.revalidate_after);
+        debug.finish_non_exhaustive()
     }
 }
 impl<G: Html> TemplateInner<G> {
@@ -166,25 +373,101 @@ impl<G: Html> TemplateInner<G> {
             path: path.to_string(),
             // Because of the scope disposer return type, this isn't as trivial as an empty function
             view: Box::new(|_, _, _, _| Ok((View::empty(), create_scope(|_| {})))),
+            view_set: false,
+            layouts: Vec::new(),
+            fallback_view: None,
+            aliases: Vec::new(),
             // Unlike `template`, this may not be set at all (especially in very simple apps)
             #[cfg(engine)]
             head: None,
             #[cfg(engine)]
             set_headers: None,
             #[cfg(engine)]
+            cache_control: None,
+            #[cfg(engine)]
+            content_type: None,
+            #[cfg(engine)]
+            output_extension: None,
+            #[cfg(engine)]
+            status_code: 200,
+            #[cfg(engine)]
+            enabled_when: None,
+            #[cfg(engine)]
+            transform_html: None,
+            #[cfg(engine)]
+            error_view: None,
+            #[cfg(engine)]
+            not_found_view: None,
+            #[cfg(engine)]
+            build_start: None,
+            #[cfg(engine)]
             get_build_paths: None,
             #[cfg(engine)]
+            get_build_paths_meta: None,
+            #[cfg(engine)]
             incremental_generation: false,
             #[cfg(engine)]
+            incremental_path_matcher: None,
+            #[cfg(engine)]
+            incremental_build_limit: None,
+            #[cfg(engine)]
+            sort_build_paths: false,
+            #[cfg(engine)]
+            build_cost_hint: None,
+            #[cfg(engine)]
+            incremental_cache_limit: None,
+            #[cfg(engine)]
+            request_timeout: None,
+            #[cfg(engine)]
+            allowed_methods: vec!["GET".to_string(), "HEAD".to_string()],
+            #[cfg(engine)]
+            incremental_lru: Mutex::new(VecDeque::new()),
+            #[cfg(engine)]
             get_build_state: None,
             #[cfg(engine)]
+            build_state_cache: None,
+            #[cfg(engine)]
+            state_codec: StateCodec::Json,
+            #[cfg(engine)]
+            compress: Compression::Auto,
+            #[cfg(engine)]
+            get_build_states_batch: None,
+            #[cfg(engine)]
+            default_state: None,
+            #[cfg(engine)]
+            validate_state: None,
+            #[cfg(engine)]
             get_request_state: None,
             #[cfg(engine)]
+            stream_request_state: None,
+            #[cfg(engine)]
+            request_state_falls_back: false,
+            #[cfg(engine)]
             should_revalidate: None,
             #[cfg(engine)]
+            should_revalidate_uses_state: false,
+            #[cfg(engine)]
+            auth_check: None,
+            #[cfg(engine)]
+            request_middleware: Vec::new(),
+            #[cfg(engine)]
             revalidate_after: None,
             #[cfg(engine)]
+            revalidate_cron: None,
+            #[cfg(engine)]
+            stale_while_revalidate: false,
+            #[cfg(engine)]
+            revalidate_state: None,
+            #[cfg(engine)]
             amalgamate_states: None,
+            #[cfg(engine)]
+            canonical_url: None,
+            #[cfg(engine)]
+            route_pattern: None,
+            #[cfg(engine)]
+            draft: false,
+            #[cfg(engine)]
+            tags: Vec::new(),
             // There is no mechanism to set this to `true`, except through the `Capsule` struct
             is_capsule: false,
             can_be_rescheduled: false,
@@ -201,6 +484,23 @@ impl<G: Html> TemplateInner<G> {
             inner: Entity::from(self),
         }
     }
+    /// Builds a full [`Template`] from this [`TemplateInner`], exactly as
+    /// with `.build()`, but first checks that the template is configured
+    /// coherently (see `.validate()`), returning the problem if it isn't.
+    /// This lets you catch misconfigurations where you define your template,
+    /// rather than when your app is built.
+    #[cfg(engine)]
+    pub fn try_build(self) -> Result<Template<G>, crate::errors::BuildError> {
+        self.validate()?;
+        Ok(self.build())
+    }
+    /// Builds a full [`Template`] from this [`TemplateInner`], exactly as
+    /// with `.build()`. Templates are only validated on the engine-side, so
+    /// this will never fail on the browser-side.
+    #[cfg(any(client, doc))]
+    pub fn try_build(self) -> Result<Template<G>, std::convert::Infallible> {
+        Ok(self.build())
+    }
 }
 
 // The engine needs to know whether or not to use hydration, this is how we pass
@@ -213,3 +513,117 @@ pub(crate) type BrowserNodeType = sycamore::prelude::DomNode;
 /// enabled.
 #[cfg(all(feature = "hydrate", any(client, doc)))]
 pub(crate) type BrowserNodeType = sycamore::prelude::HydrateNode;
+
+#[cfg(all(test, engine))]
+mod tests {
+    use super::*;
+    use crate::errors::*;
+    use crate::state::{StateGeneratorInfo, UnreactiveState};
+    use crate::template::core::test_utils::*;
+    use crate::template::{RenderStrategy, Template};
+    use futures::executor::block_on;
+    use serde::{Deserialize, Serialize};
+    use sycamore::web::SsrNode;
+
+    #[test]
+    fn templates_with_different_states_can_be_collected() {
+        #[derive(Serialize, Deserialize, Clone)]
+        struct CounterState {
+            count: u32,
+        }
+        impl UnreactiveState for CounterState {}
+
+        let templates: Vec<Template<SsrNode>> = vec![
+            Template::build("post")
+                .build_state_fn(|info: StateGeneratorInfo<()>| async move {
+                    TestState { title: info.path }
+                })
+                .view_with_unreactive_state(|cx, state: TestState| {
+                    sycamore::view! { cx, p { (state.title) } }
+                })
+                .build(),
+            Template::build("counter")
+                .build_state_fn(|_info: StateGeneratorInfo<()>| async { CounterState { count: 3 } })
+                .view_with_unreactive_state(|cx, state: CounterState| {
+                    sycamore::view! { cx, p { (state.count) } }
+                })
+                .build(),
+        ];
+
+        let translator = translator();
+        let mut rendered = Vec::new();
+        for template in templates.iter() {
+            assert_eq!(template.render_strategy(), RenderStrategy::Ssg);
+            let state = block_on(template.get_build_state(info("test"))).unwrap();
+            let html = template.render_to_string(state, &translator).unwrap();
+            rendered.push((template.get_path(), html));
+        }
+        assert_eq!(rendered[0].0, "post");
+        assert!(rendered[0].1.contains("test"));
+        assert_eq!(rendered[1].0, "counter");
+        assert!(rendered[1].1.contains('3'));
+    }
+
+    #[test]
+    fn templates_are_generic_over_node_type() {
+        use sycamore::web::{DomNode, HydrateNode};
+
+        // The same definition can be used to build templates for the engine-side and for the
+        // browser-side (with or without hydration)
+        fn get_template<G: Html>() -> Template<G> {
+            Template::build("test")
+                .view_with_unreactive_state(|cx, state: TestState| {
+                    sycamore::view! { cx, p { (state.title) } }
+                })
+                .build()
+        }
+        let _dom: Template<DomNode> = get_template();
+        let _hydrate: Template<HydrateNode> = get_template();
+
+        let translator = translator();
+        let html = get_template::<SsrNode>()
+            .render_to_string(
+                TestState {
+                    title: "Isomorphic".to_string(),
+                }
+                .into(),
+                &translator,
+            )
+            .unwrap();
+        assert!(html.contains("Isomorphic"));
+    }
+
+    #[test]
+    fn debug_shows_path_and_features() {
+        let template = Template::<SsrNode>::build("blog")
+            .build_state_fn(
+                |info: StateGeneratorInfo<()>| async move { TestState { title: info.path } },
+            )
+            .revalidate_after("1h")
+            .build();
+        let debug = format!("{:?}", template);
+        assert!(debug.contains("\"blog\""));
+        assert!(debug.contains("build_state"));
+        assert!(debug.contains("revalidate"));
+        assert!(debug.contains("revalidate_after: Some("));
+    }
+
+    #[test]
+    fn try_build_validates_config() {
+        let incoherent = Template::<SsrNode>::build("test")
+            .incremental_generation()
+            .try_build();
+        assert!(matches!(
+            incoherent,
+            Err(BuildError::TemplateFeatureNotEnabled { .. })
+        ));
+
+        let template = Template::<SsrNode>::build("test")
+            .build_state_fn(
+                |info: StateGeneratorInfo<()>| async move { TestState { title: info.path } },
+            )
+            .try_build()
+            .unwrap();
+        assert_eq!(template.get_path(), "test");
+    }
+}
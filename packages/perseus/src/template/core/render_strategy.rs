@@ -0,0 +1,39 @@
+/// A summary of the rendering strategies a template uses, which can be
+/// computed with `.render_strategy()`. This is mostly useful for reporting,
+/// since Perseus itself works with the individual strategies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderStrategy {
+    /// The template generates no state at all, and will be rendered once at
+    /// build-time.
+    Basic,
+    /// The template generates its pages and/or their state at build-time only
+    /// (static site generation).
+    Ssg,
+    /// The template generates some pages at build-time, and the rest on their
+    /// first request, caching them thereafter (incremental generation).
+    IncrementalSsg,
+    /// The template generates state at build-time, and regenerates it
+    /// either after some time or based on some logic (incremental static
+    /// regeneration). This may also use incremental generation.
+    Isr,
+    /// The template generates its state on every request only (server-side
+    /// rendering).
+    Ssr,
+    /// The template generates state on every request, along with using one or
+    /// more build-time strategies.
+    Hybrid,
+}
+impl RenderStrategy {
+    /// Gets the name of this strategy as a lowercase string, as used in
+    /// manifests.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Basic => "basic",
+            Self::Ssg => "ssg",
+            Self::IncrementalSsg => "incremental_ssg",
+            Self::Isr => "isr",
+            Self::Ssr => "ssr",
+            Self::Hybrid => "hybrid",
+        }
+    }
+}
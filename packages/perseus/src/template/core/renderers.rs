@@ -1,28 +1,41 @@
+#[cfg(engine)]
+use super::utils::normalize_build_path;
 use super::utils::PreloadInfo;
 use crate::errors::*;
 #[cfg(engine)]
 use crate::i18n::Translator;
 use crate::path::PathMaybeWithLocale;
 #[cfg(engine)]
+use crate::path::PathWithoutLocale;
+#[cfg(engine)]
 use crate::reactor::Reactor;
 #[cfg(engine)]
 use crate::reactor::RenderMode;
 use crate::state::TemplateState;
 #[cfg(engine)]
-use crate::state::{BuildPaths, StateGeneratorInfo, UnknownStateType};
+use crate::state::{BuildPathMeta, BuildPaths, ResponseMeta, StateGeneratorInfo, UnknownStateType};
 #[cfg(engine)]
 use crate::template::default_headers;
 use crate::template::TemplateInner;
 #[cfg(engine)]
+use crate::utils::{ssr_fallible, ssr_fallible_to_writer, StableHasher};
+#[cfg(engine)]
 use crate::Request;
 #[cfg(engine)]
-use http::HeaderMap;
+use futures::stream::{BoxStream, StreamExt};
+#[cfg(engine)]
+use http::{
+    header::{HeaderValue, CACHE_CONTROL},
+    HeaderMap,
+};
 #[cfg(any(client, doc))]
 use sycamore::prelude::ScopeDisposer;
 use sycamore::web::Html;
 #[cfg(engine)]
 use sycamore::web::SsrNode;
 use sycamore::{prelude::Scope, view::View};
+#[cfg(engine)]
+use tokio::time::timeout;
 
 impl<G: Html> TemplateInner<G> {
     /// Executes the user-given function that renders the template on the
@@ -43,7 +56,7 @@ impl<G: Html> TemplateInner<G> {
         );
 
         // Only widgets use the preload info
-        (self.view)(
+        let (view, disposer) = (self.view)(
             cx,
             PreloadInfo {
                 locale: String::new(),
@@ -51,7 +64,23 @@ impl<G: Html> TemplateInner<G> {
             },
             state,
             path,
-        )
+        )?;
+        Ok((self.apply_layouts(cx, view), disposer))
+    }
+    /// Executes the user-given function that renders the fallback for pages
+    /// of this template that are yet to be incrementally generated on the
+    /// client-side ONLY, if one has been set.
+    #[cfg(any(client, doc))]
+    pub(crate) fn render_fallback_client<'a>(
+        &self,
+        cx: Scope<'a>,
+    ) -> Option<(View<G>, ScopeDisposer<'a>)> {
+        let fallback_view = self.fallback_view.as_ref()?;
+        let mut view = View::empty();
+        let disposer = sycamore::reactive::create_child_scope(cx, |child_cx| {
+            view = (fallback_view)(child_cx);
+        });
+        Some((self.apply_layouts(cx, view), disposer))
     }
     /// Executes the user-given function that renders the template on the
     /// server-side ONLY. This automatically initializes an isolated global
@@ -65,6 +94,23 @@ impl<G: Html> TemplateInner<G> {
         mode: RenderMode<SsrNode>,
         cx: Scope,
         translator: &Translator,
+    ) -> Result<View<G>, ClientError> {
+        let view =
+            self.render_body_for_template_server(path, state, global_state, mode, cx, translator)?;
+        Ok(self.apply_layouts(cx, view))
+    }
+    /// Renders the view of this template on the server-side, exactly as with
+    /// `.render_for_template_server()`, but without wrapping it in this
+    /// template's layouts.
+    #[cfg(engine)]
+    fn render_body_for_template_server(
+        &self,
+        path: PathMaybeWithLocale,
+        state: TemplateState,
+        global_state: TemplateState,
+        mode: RenderMode<SsrNode>,
+        cx: Scope,
+        translator: &Translator,
     ) -> Result<View<G>, ClientError> {
         assert!(
             !self.is_capsule,
@@ -77,10 +123,27 @@ impl<G: Html> TemplateInner<G> {
         Reactor::engine(global_state, mode, Some(translator)).add_self_to_cx(cx);
         // This is used for widget preloading, which doesn't occur on the engine-side
         let preload_info = PreloadInfo {};
+        let state = match &self.default_state {
+            Some(default_state) if state.is_empty() => default_state.clone(),
+            _ => state,
+        };
         // We don't care about the scope disposer, since this scope is unique anyway
-        let (view, _) = (self.view)(cx, preload_info, state, path)?;
+        let view = match (self.view)(cx, preload_info, state, path) {
+            Ok((view, _)) => view,
+            Err(err) => match &self.error_view {
+                Some(error_view) => error_view(cx, &err),
+                None => return Err(err),
+            },
+        };
         Ok(view)
     }
+    /// Wraps the given view in all this template's layouts, from innermost to
+    /// outermost.
+    fn apply_layouts(&self, cx: Scope, view: View<G>) -> View<G> {
+        self.layouts
+            .iter()
+            .fold(view, |view, layout| layout(cx, view))
+    }
     /// Executes the user-given function that renders the document `<head>`,
     /// returning a string to be interpolated manually. Reactivity in this
     /// function will not take effect due to this string rendering. Note that
@@ -119,11 +182,69 @@ impl<G: Html> TemplateInner<G> {
 
         Ok(prerendered)
     }
+    /// Renders the body of the 404 page for the given missing path of this
+    /// template, if a function to do so has been set with
+    /// `.not_found_fn()`. This is only the body, which the server will
+    /// interpolate into the app's HTML shell.
+    #[cfg(engine)]
+    pub fn render_not_found(&self, path: &str, translator: &Translator) -> Option<String> {
+        use sycamore::{
+            prelude::create_scope_immediate, utils::hydrate::with_no_hydration_context,
+        };
+
+        let not_found_view = self.not_found_view.as_ref()?;
+        let mut view = View::empty();
+        create_scope_immediate(|cx| {
+            // Translations can be used in the view, just like on any other page (this is the
+            // body of an error page, so widgets can't be)
+            Reactor::<G>::engine(TemplateState::empty(), RenderMode::Error, Some(translator))
+                .add_self_to_cx(cx);
+
+            view = with_no_hydration_context(|| (not_found_view)(cx, path));
+        });
+
+        Some(sycamore::render_to_string(|_| view))
+    }
+    /// Runs the user's HTML transformation function on the given prerendered
+    /// HTML for the page at the given path, if one has been set.
+    #[cfg(engine)]
+    pub(crate) fn transform_html(&self, html: String, path: &str) -> Result<String, ClientError> {
+        match &self.transform_html {
+            Some(transform_html) => {
+                transform_html(html).map_err(|err| ClientError::TemplateRenderFailed {
+                    path: path.to_string(),
+                    source: err,
+                })
+            }
+            None => Ok(html),
+        }
+    }
+    /// Runs the user's build start function, if one has been set. This is
+    /// called once before anything else when this template is built.
+    #[cfg(engine)]
+    pub fn run_build_start(&self) -> Result<(), ServerError> {
+        if let Some(build_start) = &self.build_start {
+            build_start().map_err(|err| ServerError::RenderFnFailed {
+                fn_name: "build_start".to_string(),
+                template_name: self.get_path(),
+                blame: ErrorBlame::Server(None),
+                source: err,
+            })?;
+        }
+        Ok(())
+    }
     /// Gets the list of templates that should be prerendered for at build-time.
     #[cfg(engine)]
     pub(crate) async fn get_build_paths(&self) -> Result<BuildPaths, ServerError> {
         if let Some(get_build_paths) = &self.get_build_paths {
-            get_build_paths.call().await
+            let mut build_paths = get_build_paths.call().await?;
+            for path in build_paths.paths.iter_mut() {
+                *path = normalize_build_path(path);
+            }
+            if self.sort_build_paths {
+                build_paths.paths.sort();
+            }
+            Ok(build_paths)
         } else {
             Err(BuildError::TemplateFeatureNotEnabled {
                 template_name: self.path.clone(),
@@ -132,6 +253,209 @@ impl<G: Html> TemplateInner<G> {
             .into())
         }
     }
+    /// Gets the paths of this template that should be prerendered for at
+    /// build-time, exactly as with `.get_build_paths()`, but returns a
+    /// [`BuildError::DuplicateBuildPath`] if any path is returned more than
+    /// once, which would otherwise lead to it being generated twice.
+    ///
+    /// Paths are normalized before they're checked, so `/a` and `a/` count as
+    /// duplicates, and the error and the returned paths will use the
+    /// normalized forms (e.g. `a`, not `/a`).
+    #[cfg(engine)]
+    pub async fn get_build_paths_checked(&self) -> Result<Vec<String>, ServerError> {
+        let BuildPaths { paths, .. } = self.get_build_paths().await?;
+        let mut seen = std::collections::HashSet::new();
+        for path in paths.iter() {
+            if !seen.insert(path) {
+                return Err(BuildError::DuplicateBuildPath {
+                    template_name: self.get_path(),
+                    path: path.to_string(),
+                }
+                .into());
+            }
+        }
+
+        Ok(paths)
+    }
+    /// Generates the build states of the given incrementally-generated paths
+    /// of this template ahead of time, returning each path with its state.
+    /// This can be used to prerender known popular paths when your app is
+    /// deployed, rather than on their first request. The paths should be
+    /// relative to the template root, and each must be accepted by this
+    /// template's incremental path matcher, otherwise a
+    /// [`ServeError::PageNotFound`] will be returned, just as it would be
+    /// at request-time.
+    ///
+    /// This will run your build paths function to get the extra state for
+    /// the template, so it requires *incremental generation*.
+    #[cfg(engine)]
+    pub async fn warm_paths(
+        &self,
+        paths: Vec<String>,
+        locale: &str,
+    ) -> Result<Vec<(String, TemplateState)>, ServerError> {
+        if !self.uses_incremental() {
+            return Err(BuildError::TemplateFeatureNotEnabled {
+                template_name: self.get_path(),
+                feature_name: "incremental_generation".to_string(),
+            }
+            .into());
+        }
+        // Check every path before we do any work
+        if let Some(path) = paths.iter().find(|path| !self.can_render_incremental(path)) {
+            return Err(ServeError::PageNotFound {
+                path: format!("{}/{}", self.get_path(), path),
+            }
+            .into());
+        }
+
+        let BuildPaths { extra, .. } = self.get_build_paths().await?;
+        let mut states = Vec::new();
+        for path in paths {
+            let state = self
+                .get_build_state(StateGeneratorInfo {
+                    path: path.clone(),
+                    locale: locale.to_string(),
+                    extra: extra.clone(),
+                })
+                .await?;
+            states.push((path, state));
+        }
+
+        Ok(states)
+    }
+    /// Estimates the total cost of building this template, by multiplying
+    /// the hint set with `.build_cost_hint()` (or 1, if there isn't one) by
+    /// the number of pages that will be built. If this template uses the
+    /// *build paths* strategy, this will run your build paths function to
+    /// find out how many pages there are, otherwise there's just the one.
+    #[cfg(engine)]
+    pub async fn estimated_build_cost(&self) -> Result<u32, ServerError> {
+        let num_pages = if self.uses_build_paths() {
+            let paths = self.get_build_paths().await?.paths.len();
+            // Anything past this is already far beyond a meaningful hint
+            u32::try_from(paths).unwrap_or(u32::MAX)
+        } else {
+            1
+        };
+
+        Ok(self.build_cost_hint.unwrap_or(1).saturating_mul(num_pages))
+    }
+    /// Serializes the given state for this template into the exact format
+    /// Perseus uses internally when caching it, which can be used to store
+    /// state in external caches. This can't fail, since the state has
+    /// already been serialized into a [`serde_json::Value`].
+    #[cfg(engine)]
+    pub fn serialize_state(&self, state: &TemplateState) -> String {
+        state.state.to_string()
+    }
+    /// Deserializes state for this template from the format produced by
+    /// `.serialize_state()`.
+    #[cfg(engine)]
+    pub fn deserialize_state(&self, state: &str) -> Result<TemplateState, ServerError> {
+        TemplateState::from_str(state).map_err(|err| {
+            ClientError::InvariantError(ClientInvariantError::InvalidState { source: err }).into()
+        })
+    }
+    /// Encodes the given state for this template using the codec set with
+    /// `.state_codec()` (JSON by default), which can be used to store state
+    /// compactly in external caches.
+    #[cfg(engine)]
+    pub fn encode_state(&self, state: &TemplateState) -> Vec<u8> {
+        self.state_codec.encode(&state.state)
+    }
+    /// Decodes state for this template from the bytes produced by
+    /// `.encode_state()`. This must use the same codec as the state was
+    /// encoded with.
+    #[cfg(engine)]
+    pub fn decode_state(&self, bytes: &[u8]) -> Result<TemplateState, ServerError> {
+        self.state_codec
+            .decode(bytes)
+            .map(TemplateState::from_value)
+            .map_err(|reason| ServerError::InvalidEncodedState {
+                template_name: self.get_path(),
+                reason,
+            })
+    }
+    /// Gets the paths generated by this template's build paths function that
+    /// should actually be prerendered at build-time, which will be all of
+    /// them unless an incremental build limit has been set.
+    #[cfg(engine)]
+    pub async fn build_paths_to_prerender(&self) -> Result<Vec<String>, ServerError> {
+        let BuildPaths { mut paths, .. } = self.get_build_paths().await?;
+        self.apply_incremental_build_limit(&mut paths);
+        Ok(paths)
+    }
+    /// Gets all the concrete paths this template is responsible for, which
+    /// will be its root path, plus all the paths generated by its build paths
+    /// function joined under it (if it has one). These will have no leading
+    /// or trailing slashes, and will be deduplicated (in the order they were
+    /// generated), making them suitable for registering routes.
+    ///
+    /// Note that any paths this template might generate incrementally will not
+    /// be included, since they can't be known in advance.
+    #[cfg(engine)]
+    pub async fn owned_paths(&self) -> Result<Vec<String>, ServerError> {
+        let mut owned_paths = vec![self.get_path().trim_matches('/').to_string()];
+        if self.uses_build_paths() {
+            let BuildPaths { paths, .. } = self.get_build_paths().await?;
+            for path in paths {
+                let full_path = self.full_build_path(&path);
+                if !owned_paths.contains(&full_path) {
+                    owned_paths.push(full_path);
+                }
+            }
+        }
+
+        Ok(owned_paths)
+    }
+    /// Checks whether or not the page at the given path (including the
+    /// template root) will be prerendered at build-time, as opposed to being
+    /// generated incrementally. For templates using the *build paths*
+    /// strategy, this checks against the paths that will actually be
+    /// prerendered (accounting for any incremental build limit); otherwise,
+    /// only the template root will be prerendered.
+    #[cfg(engine)]
+    pub async fn is_prerendered(&self, path: &str) -> Result<bool, ServerError> {
+        let path = path.trim_matches('/');
+        if self.uses_build_paths() {
+            let paths = self.build_paths_to_prerender().await?;
+            Ok(paths
+                .iter()
+                .any(|build_path| self.full_build_path(build_path) == path))
+        } else {
+            Ok(self.get_path().trim_matches('/') == path)
+        }
+    }
+    /// Joins the given build path under the root of this template, with no
+    /// leading or trailing slashes.
+    #[cfg(engine)]
+    fn full_build_path(&self, path: &str) -> String {
+        let full_path = format!("{}/{}", self.get_path(), path.trim_start_matches('/'));
+        full_path.trim_matches('/').to_string()
+    }
+    /// Truncates the given build paths to the incremental build limit, if one
+    /// applies to this template.
+    #[cfg(engine)]
+    pub(crate) fn apply_incremental_build_limit(&self, paths: &mut Vec<String>) {
+        if let Some(limit) = self.incremental_build_limit {
+            if self.uses_incremental() {
+                paths.truncate(limit);
+            }
+        }
+    }
+    /// Gets the paths this template will build, along with their metadata.
+    /// If the user didn't provide a function to generate metadata, the paths
+    /// will be generated with none.
+    #[cfg(engine)]
+    pub async fn get_build_paths_meta(&self) -> Result<Vec<BuildPathMeta>, ServerError> {
+        if let Some(get_build_paths_meta) = &self.get_build_paths_meta {
+            get_build_paths_meta.call().await
+        } else {
+            let BuildPaths { paths, .. } = self.get_build_paths().await?;
+            Ok(paths.into_iter().map(BuildPathMeta::new).collect())
+        }
+    }
     /// Gets the initial state for a template. This needs to be passed the full
     /// path of the template, which may be one of those generated by
     /// `.get_build_paths()`. This also needs the locale being rendered to so
@@ -143,7 +467,27 @@ impl<G: Html> TemplateInner<G> {
         info: StateGeneratorInfo<UnknownStateType>,
     ) -> Result<TemplateState, ServerError> {
         if let Some(get_build_state) = &self.get_build_state {
-            get_build_state.call(info).await
+            let cache = self
+                .build_state_cache
+                .as_ref()
+                .filter(|_| !self.revalidates());
+            let cache_key = (info.locale.clone(), info.path.clone());
+            if let Some(cache) = cache {
+                if let Some(state) = cache.lock().unwrap().get(&cache_key) {
+                    return Ok(state.clone());
+                }
+            }
+
+            let path = info.path.clone();
+            let state = get_build_state
+                .call(info)
+                .await
+                .map_err(|err| err.in_page(self.get_path(), path.clone()))?;
+            let state = self.validate_build_state(path, state)?;
+            if let Some(cache) = cache {
+                cache.lock().unwrap().insert(cache_key, state.clone());
+            }
+            Ok(state)
         } else {
             Err(BuildError::TemplateFeatureNotEnabled {
                 template_name: self.path.clone(),
@@ -152,6 +496,145 @@ impl<G: Html> TemplateInner<G> {
             .into())
         }
     }
+    /// Clears any states memoized by `.memoize_build_state()`, so they'll be
+    /// regenerated the next time they're needed.
+    #[cfg(engine)]
+    pub fn clear_build_state_cache(&self) {
+        if let Some(cache) = &self.build_state_cache {
+            cache.lock().unwrap().clear();
+        }
+    }
+    /// Records the given path, which has been generated on-demand with
+    /// incremental generation, as the most recently used, evicting the least
+    /// recently used paths if there are now too many cached. This returns
+    /// the paths that were evicted, whose artifacts should be removed from
+    /// the mutable store (this will always be empty if there's no limit on
+    /// the cache). The path just recorded is never evicted, since it's about to
+    /// be served, so a limit of zero will keep only the most recent page.
+    #[cfg(engine)]
+    pub(crate) fn record_incremental_path(&self, path: &str) -> Vec<String> {
+        if self.incremental_cache_limit.is_none() {
+            return Vec::new();
+        }
+
+        let mut lru = self.incremental_lru.lock().unwrap();
+        if let Some(idx) = lru.iter().position(|cached| cached == path) {
+            lru.remove(idx);
+        }
+        lru.push_back(path.to_string());
+        let mut evicted = Vec::new();
+        // The path being served is at the back, so we stop before reaching it
+        while lru.len() > 1 && self.should_evict(lru.len()) {
+            evicted.extend(lru.pop_front());
+        }
+
+        evicted
+    }
+    /// Gets the initial states for many paths of a template at once, using the
+    /// user's batch function if they provided one, and falling back to
+    /// generating each state individually otherwise. The results will be in
+    /// the same order as the given information, and errors are per-path.
+    #[cfg(engine)]
+    pub async fn get_build_states(
+        &self,
+        infos: Vec<StateGeneratorInfo<UnknownStateType>>,
+    ) -> Result<Vec<Result<TemplateState, ServerError>>, ServerError> {
+        if let Some(get_build_states_batch) = &self.get_build_states_batch {
+            let paths = infos
+                .iter()
+                .map(|info| info.path.clone())
+                .collect::<Vec<_>>();
+            let results = get_build_states_batch.call(infos).await;
+            if results.len() != paths.len() {
+                return Err(BuildError::BatchLengthMismatch {
+                    template_name: self.get_path(),
+                    expected: paths.len(),
+                    found: results.len(),
+                }
+                .into());
+            }
+            Ok(paths
+                .into_iter()
+                .zip(results)
+                .map(|(path, res)| {
+                    res.map_err(|err| err.in_page(self.get_path(), path.clone()))
+                        .and_then(|state| self.validate_build_state(path, state))
+                })
+                .collect())
+        } else {
+            let mut results = Vec::new();
+            for info in infos {
+                results.push(self.get_build_state(info).await);
+            }
+            Ok(results)
+        }
+    }
+    /// Generates the build state for every path this template would build (in
+    /// the given locale), returning each path with its state, in the order
+    /// the paths were generated. This is useful for things like content
+    /// previews. Templates that don't use the *build paths* strategy will
+    /// just have their root path (an empty string) previewed.
+    ///
+    /// If state generation fails for any path, this will stop and return a
+    /// [`ServerError::PreviewFailed`] naming that path. Any paths skipped by
+    /// the build state function won't be included.
+    #[cfg(engine)]
+    pub async fn build_path_previews(
+        &self,
+        locale: &str,
+    ) -> Result<Vec<(String, TemplateState)>, ServerError> {
+        let (paths, extra) = if self.uses_build_paths() {
+            let BuildPaths { paths, extra } = self.get_build_paths().await?;
+            (paths, extra)
+        } else {
+            (vec![String::new()], TemplateState::empty())
+        };
+        let infos = paths
+            .iter()
+            .map(|path| StateGeneratorInfo {
+                path: path.clone(),
+                locale: locale.to_string(),
+                extra: extra.clone(),
+            })
+            .collect();
+        let states = self.get_build_states(infos).await?;
+
+        paths
+            .into_iter()
+            .zip(states)
+            .filter(|(_, state)| {
+                !matches!(
+                    state,
+                    Err(ServerError::BuildError(BuildError::PathSkipped { .. }))
+                )
+            })
+            .map(|(path, state)| match state {
+                Ok(state) => Ok((path, state)),
+                Err(err) => Err(ServerError::PreviewFailed {
+                    template_name: self.get_path(),
+                    path,
+                    source: Box::new(err),
+                }),
+            })
+            .collect()
+    }
+    /// Runs the user's state validation function on the given build state for
+    /// the given path, if they provided one.
+    #[cfg(engine)]
+    fn validate_build_state(
+        &self,
+        path: String,
+        state: TemplateState,
+    ) -> Result<TemplateState, ServerError> {
+        if let Some(validate_state) = &self.validate_state {
+            validate_state(&state).map_err(|err| ServerError::StateValidationFailed {
+                template_name: self.get_path(),
+                path,
+                source: err,
+            })?;
+        }
+        Ok(state)
+    }
     /// Gets the request-time state for a template. This is equivalent to SSR,
     /// and will not be performed at build-time. Unlike `.get_build_paths()`
     /// though, this will be passed information about the request that triggered
@@ -159,13 +642,42 @@ impl<G: Html> TemplateInner<G> {
     /// client, so the user must specify an [`ErrorBlame`]. This is also passed
     /// the locale being rendered to.
     #[cfg(engine)]
-    pub(crate) async fn get_request_state(
+    pub async fn get_request_state(
         &self,
         info: StateGeneratorInfo<UnknownStateType>,
         req: Request,
     ) -> Result<TemplateState, ServerError> {
+        self.get_request_state_with_meta(info, req)
+            .await
+            .map(|(state, _)| state)
+    }
+    /// Gets the request-time state for a template, along with any metadata
+    /// about the response that the request state function generated (see
+    /// `.request_state_with_meta_fn()`). If the function doesn't generate any,
+    /// this will be empty.
+    #[cfg(engine)]
+    pub async fn get_request_state_with_meta(
+        &self,
+        info: StateGeneratorInfo<UnknownStateType>,
+        req: Request,
+    ) -> Result<(TemplateState, ResponseMeta), ServerError> {
         if let Some(get_request_state) = &self.get_request_state {
-            get_request_state.call(info, req).await
+            let path = info.path.clone();
+            let req = self
+                .run_request_middleware(req)
+                .map_err(|err| err.in_page(self.get_path(), path.clone()))?;
+            let res = match self.request_timeout {
+                Some(duration) => timeout(duration, get_request_state.call(info, req))
+                    .await
+                    .unwrap_or_else(|_| {
+                        Err(ServerError::RequestStateTimeout {
+                            template_name: self.get_path(),
+                            timeout: duration,
+                        })
+                    }),
+                None => get_request_state.call(info, req).await,
+            };
+            res.map_err(|err| err.in_page(self.get_path(), path))
         } else {
             Err(BuildError::TemplateFeatureNotEnabled {
                 template_name: self.path.clone(),
@@ -174,6 +686,20 @@ impl<G: Html> TemplateInner<G> {
             .into())
         }
     }
+    /// Runs this template's request middleware on the given request, in
+    /// order, returning the modified request, or the first error.
+    #[cfg(engine)]
+    fn run_request_middleware(&self, mut req: Request) -> Result<Request, ServerError> {
+        for middleware in &self.request_middleware {
+            middleware(&mut req).map_err(|err| ServerError::RenderFnFailed {
+                fn_name: "request_middleware".to_string(),
+                template_name: self.get_path(),
+                blame: err.blame,
+                source: err.error,
+            })?;
+        }
+        Ok(req)
+    }
     /// Amalgamates given request and build states. Errors here can be caused by
     /// either the server or the client, so the user must specify
     /// an [`ErrorBlame`].
@@ -189,9 +715,21 @@ impl<G: Html> TemplateInner<G> {
         request_state: TemplateState,
     ) -> Result<TemplateState, ServerError> {
         if let Some(amalgamate_states) = &self.amalgamate_states {
+            // There's nothing to amalgamate unless both states can be generated
+            if !self.uses_build_state() || !self.uses_request_state() {
+                return Err(BuildError::FeatureOrderViolation {
+                    template_name: self.path.clone(),
+                    path: info.path.clone(),
+                    feature_name: "amalgamate_states".to_string(),
+                    reason: "states can only be amalgamated if both build and request state are generated".to_string(),
+                }
+                .into());
+            }
+            let path = info.path.clone();
             amalgamate_states
                 .call(info, build_state, request_state)
                 .await
+                .map_err(|err| err.in_page(self.get_path(), path))
         } else {
             Err(BuildError::TemplateFeatureNotEnabled {
                 template_name: self.path.clone(),
@@ -201,18 +739,24 @@ impl<G: Html> TemplateInner<G> {
         }
     }
     /// Checks, by the user's custom logic, if this template should revalidate.
-    /// This function isn't presently parsed anything, but has
-    /// network access etc., and can really do whatever it likes. Errors here
-    /// can be caused by either the server or the client, so the
+    /// This is passed the request, and the state the page was last generated
+    /// with (which will be empty if the template doesn't use build state),
+    /// and has network access etc., so it can really do whatever it likes.
+    /// Errors here can be caused by either the server or the client, so the
     /// user must specify an [`ErrorBlame`].
     #[cfg(engine)]
     pub(crate) async fn should_revalidate(
         &self,
         info: StateGeneratorInfo<UnknownStateType>,
         req: Request,
+        cached_state: TemplateState,
     ) -> Result<bool, ServerError> {
         if let Some(should_revalidate) = &self.should_revalidate {
-            should_revalidate.call(info, req).await
+            let path = info.path.clone();
+            should_revalidate
+                .call(info, req, cached_state)
+                .await
+                .map_err(|err| err.in_page(self.get_path(), path))
         } else {
             Err(BuildError::TemplateFeatureNotEnabled {
                 template_name: self.path.clone(),
@@ -221,6 +765,30 @@ impl<G: Html> TemplateInner<G> {
             .into())
         }
     }
+    /// Updates the given state a page of this template was last generated
+    /// with, by the user's custom logic, when the page revalidates. Errors
+    /// here can be caused by either the server or the client, so the user
+    /// must specify an [`ErrorBlame`].
+    #[cfg(engine)]
+    pub(crate) async fn revalidate_state(
+        &self,
+        info: StateGeneratorInfo<UnknownStateType>,
+        old_state: TemplateState,
+    ) -> Result<TemplateState, ServerError> {
+        if let Some(revalidate_state) = &self.revalidate_state {
+            let path = info.path.clone();
+            revalidate_state
+                .call(info, old_state)
+                .await
+                .map_err(|err| err.in_page(self.get_path(), path))
+        } else {
+            Err(BuildError::TemplateFeatureNotEnabled {
+                template_name: self.path.clone(),
+                feature_name: "revalidate_state".to_string(),
+            }
+            .into())
+        }
+    }
     /// Gets the template's headers for the given state. These will be inserted
     /// into any successful HTTP responses for this template, and they have
     /// the power to override existing headers, including `Content-Type`.
@@ -247,10 +815,2100 @@ impl<G: Html> TemplateInner<G> {
             if let Some(header_fn) = &self.set_headers {
                 res = (header_fn)(cx, state);
             } else {
-                res = Ok(default_headers());
+                let mut headers = default_headers();
+                if let Some(cache_control) = &self.cache_control {
+                    match HeaderValue::from_str(cache_control) {
+                        Ok(val) => {
+                            headers.insert(CACHE_CONTROL, val);
+                        }
+                        Err(err) => {
+                            res = Err(ServerError::InvalidCacheControlHeader {
+                                template_name: self.get_path(),
+                                source: err,
+                            });
+                            return;
+                        }
+                    }
+                }
+                res = Ok(headers);
             }
         });
 
         res
     }
 }
+#[cfg(engine)]
+impl TemplateInner<SsrNode> {
+    /// Renders this template to an HTML string with the given state, using
+    /// the given translator, without any global state. This is mainly
+    /// intended for snapshot testing your templates, and it won't render any
+    /// widgets (they'll produce errors instead, since they need the rest of
+    /// your app).
+    pub fn render_to_string(
+        &self,
+        state: TemplateState,
+        translator: &Translator,
+    ) -> Result<String, ClientError> {
+        self.render_to_string_at(state, &self.get_path(), translator)
+    }
+    /// Renders the fallback for this template's pages that are yet to be
+    /// incrementally generated, if one has been set with `.fallback_fn()`.
+    /// This will be wrapped in the template's layouts, and it can be
+    /// hydrated, since the browser will render exactly the same thing.
+    pub fn render_fallback(
+        &self,
+        global_state: TemplateState,
+        translator: &Translator,
+    ) -> Option<String> {
+        let fallback_view = self.fallback_view.as_ref()?;
+        let html = ssr_fallible(|cx| {
+            // There's no state, and fallbacks can't use widgets
+            Reactor::<SsrNode>::engine(global_state, RenderMode::Error, Some(translator))
+                .add_self_to_cx(cx);
+            let view = (fallback_view)(cx);
+            Ok::<_, std::convert::Infallible>(self.apply_layouts(cx, view))
+        });
+
+        // We know this can't fail
+        html.ok()
+    }
+    /// Renders this template to an HTML string exactly as with
+    /// `.render_to_string()`, but with borrowed state, so the same state can
+    /// be used for several renders (e.g. of the body and the head, with
+    /// `.render_head_to_string_ref()`). If no state is given, the template
+    /// will be rendered with empty state.
+    ///
+    /// Note that your view functions take their state by value, so the
+    /// untyped state will still be cloned internally for each render.
+    pub fn render_to_string_ref(
+        &self,
+        state: Option<&TemplateState>,
+        translator: &Translator,
+    ) -> Result<String, ClientError> {
+        self.render_to_string(
+            state.cloned().unwrap_or_else(TemplateState::empty),
+            translator,
+        )
+    }
+    /// Renders only the document `<head>` of this template to an HTML string
+    /// exactly as with `.render_head_to_string()`, but with borrowed state
+    /// (see `.render_to_string_ref()`).
+    pub fn render_head_to_string_ref(
+        &self,
+        state: Option<&TemplateState>,
+        translator: &Translator,
+    ) -> Result<String, ServerError> {
+        self.render_head_to_string(
+            state.cloned().unwrap_or_else(TemplateState::empty),
+            translator,
+        )
+    }
+    /// Renders a best-effort loading skeleton for this template's pages, based
+    /// on the shape of the given state type, which can be shown while the
+    /// real state is being fetched. This serializes the default value of the
+    /// state, and renders a placeholder `<div>` for each of its top-level
+    /// fields (with the field name in a `data-field` attribute), which you
+    /// can style however you like (e.g. as gray boxes).
+    ///
+    /// If the state doesn't serialize to an object (e.g. if it's a
+    /// tuple struct), the skeleton will have no placeholders.
+    pub fn render_skeleton<S: serde::Serialize + Default>(&self) -> Result<String, ClientError> {
+        let state = serde_json::to_value(S::default()).map_err(|err| {
+            ClientError::InvariantError(ClientInvariantError::InvalidState { source: err })
+        })?;
+        let fields = match state {
+            serde_json::Value::Object(map) => map.keys().cloned().collect(),
+            _ => Vec::new(),
+        };
+
+        let mut html = String::from(r#"<div class="perseus-skeleton">"#);
+        for field in fields {
+            // Field names will usually be Rust identifiers, but serde can rename them to anything
+            let field = field
+                .replace('&', "&amp;")
+                .replace('"', "&quot;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;");
+            html.push_str(&format!(
+                r#"<div class="perseus-skeleton-field" data-field="{}"></div>"#,
+                field
+            ));
+        }
+        html.push_str("</div>");
+
+        Ok(html)
+    }
+    /// Renders only the document `<head>` of this template to an HTML string
+    /// with the given state, exactly as with `.render_to_string()` (without
+    /// any global state). This is useful for partial updates after
+    /// client-side navigations. If no head function has been set, this will
+    /// be empty.
+    pub fn render_head_to_string(
+        &self,
+        state: TemplateState,
+        translator: &Translator,
+    ) -> Result<String, ServerError> {
+        self.render_head_str(state, TemplateState::empty(), translator)
+    }
+    /// Renders this template to an HTML string exactly as with
+    /// `.render_to_string()`, but with the state given as a JSON string
+    /// (e.g. from a test fixture). If the JSON is malformed, this will return
+    /// a [`ClientInvariantError::InvalidState`], and any render errors will
+    /// be returned as usual.
+    ///
+    /// Note that the JSON isn't checked against this template's state type
+    /// until the view is rendered.
+    pub fn render_from_json(
+        &self,
+        json: &str,
+        translator: &Translator,
+    ) -> Result<String, ClientError> {
+        let state = TemplateState::from_str(json).map_err(|err| {
+            ClientError::InvariantError(ClientInvariantError::InvalidState { source: err })
+        })?;
+        self.render_to_string(state, translator)
+    }
+    /// Renders this template to an HTML string exactly as with
+    /// `.render_to_string()`, but also returns how long the render took,
+    /// which is useful for finding slow templates.
+    pub fn render_to_string_timed(
+        &self,
+        state: TemplateState,
+        translator: &Translator,
+    ) -> Result<(String, std::time::Duration), ClientError> {
+        let start = std::time::Instant::now();
+        let html = self.render_to_string(state, translator)?;
+        Ok((html, start.elapsed()))
+    }
+    /// Renders this template exactly as with `.render_to_string()`, and then
+    /// hashes the resulting HTML to a short hex digest, which is useful for
+    /// cache busting assets derived from a page's content. If no state is
+    /// given, the template will be rendered with empty state.
+    ///
+    /// This uses 64-bit FNV-1a, so the digest for the same content will be
+    /// the same across builds and platforms, but it is *not* suitable for
+    /// any security-sensitive purposes.
+    pub fn content_digest(
+        &self,
+        state: Option<TemplateState>,
+        translator: &Translator,
+    ) -> Result<String, ClientError> {
+        use std::hash::Hasher;
+
+        let html = self.render_to_string(state.unwrap_or_else(TemplateState::empty), translator)?;
+        let mut hasher = StableHasher::new();
+        hasher.write(html.as_bytes());
+        Ok(format!("{:016x}", hasher.finish()))
+    }
+    /// Renders the page of this template at the given path (which should
+    /// include the template root, e.g. `docs/intro` for the `docs`
+    /// template), exactly as with `.render_to_string()`. This is useful for
+    /// templates that render differently depending on their path.
+    pub fn render_to_string_at(
+        &self,
+        state: TemplateState,
+        path: &str,
+        translator: &Translator,
+    ) -> Result<String, ClientError> {
+        let path = PathMaybeWithLocale::new(
+            &PathWithoutLocale(path.to_string()),
+            &translator.get_locale(),
+        );
+        let html = ssr_fallible(|cx| {
+            self.render_for_template_server(
+                path.clone(),
+                state,
+                TemplateState::empty(),
+                RenderMode::Head,
+                cx,
+                translator,
+            )
+        })?;
+        self.transform_html(html, &path)
+    }
+    /// Generates this template's request state as a stream of chunks (see
+    /// `.stream_request_fn()`), rendering each one to a string of HTML as it
+    /// arrives. The chunks will be yielded in the order the state function
+    /// produces them, and a chunk that fails to generate or render will be
+    /// yielded as an error, without ending the stream.
+    ///
+    /// Each chunk is a standalone fragment of the page's body: it won't be
+    /// wrapped in this template's layouts or run through its HTML
+    /// transformation function (since those apply to whole pages), and it
+    /// won't have any hydration keys, since chunks can't be hydrated
+    /// individually.
+    ///
+    /// This will run this template's request middleware first, and return an
+    /// error immediately if that fails, or if this template doesn't stream
+    /// its request state.
+    pub fn render_request_stream<'a>(
+        &'a self,
+        info: StateGeneratorInfo<UnknownStateType>,
+        req: Request,
+        translator: &'a Translator,
+    ) -> Result<BoxStream<'a, Result<String, ServerError>>, ServerError> {
+        let stream_request_state = match &self.stream_request_state {
+            Some(stream_request_state) => stream_request_state,
+            None => {
+                return Err(BuildError::TemplateFeatureNotEnabled {
+                    template_name: self.path.clone(),
+                    feature_name: "stream_request_state".to_string(),
+                }
+                .into())
+            }
+        };
+        let path = info.path.clone();
+        let req = self
+            .run_request_middleware(req)
+            .map_err(|err| err.in_page(self.get_path(), path.clone()))?;
+
+        let chunks = stream_request_state(info, req).map(move |state| {
+            let state = state.map_err(|err| err.in_page(self.get_path(), path.clone()))?;
+            let html = self.render_stream_chunk(state, &path, translator)?;
+            Ok(html)
+        });
+        Ok(chunks.boxed())
+    }
+    /// Renders a single chunk of streamed request state for the page at the
+    /// given path, as a standalone fragment (see `.render_request_stream()`).
+    fn render_stream_chunk(
+        &self,
+        state: TemplateState,
+        path: &str,
+        translator: &Translator,
+    ) -> Result<String, ClientError> {
+        use sycamore::{
+            prelude::create_scope_immediate, utils::hydrate::with_no_hydration_context,
+        };
+
+        let path = PathMaybeWithLocale::new(
+            &PathWithoutLocale(path.to_string()),
+            &translator.get_locale(),
+        );
+        let mut view = Ok(View::empty());
+        create_scope_immediate(|cx| {
+            view = with_no_hydration_context(|| {
+                self.render_body_for_template_server(
+                    path,
+                    state,
+                    TemplateState::empty(),
+                    RenderMode::Head,
+                    cx,
+                    translator,
+                )
+            });
+        });
+        let view = view?;
+
+        Ok(sycamore::render_to_string(|_| view))
+    }
+    /// Renders this template to the given writer, exactly as with
+    /// `.render_to_string()`, except the rendered HTML will be written out
+    /// node by node, rather than being collected into one string first. This
+    /// is useful for very large pages.
+    ///
+    /// If this template has an HTML transformation function, it needs the
+    /// whole page at once, so the HTML will be collected and transformed
+    /// before being written out in one go.
+    pub fn render_to_writer(
+        &self,
+        state: TemplateState,
+        translator: &Translator,
+        w: &mut dyn std::io::Write,
+    ) -> Result<(), ServerError> {
+        if self.transform_html.is_some() {
+            let html = self.render_to_string(state, translator)?;
+            return w
+                .write_all(html.as_bytes())
+                .map_err(|err| ServerError::WriteFailed { source: err });
+        }
+
+        let path = PathMaybeWithLocale::new(
+            &PathWithoutLocale(self.get_path()),
+            &translator.get_locale(),
+        );
+        ssr_fallible_to_writer(
+            |cx| {
+                self.render_for_template_server(
+                    path,
+                    state,
+                    TemplateState::empty(),
+                    RenderMode::Head,
+                    cx,
+                    translator,
+                )
+            },
+            w,
+        )
+    }
+}
+
+#[cfg(all(test, engine))]
+mod tests {
+    use super::*;
+    use crate::state::{
+        BuildPathMeta, BuildPaths, RenderOutcome, RequestStateOutcome, ResponseMeta,
+        StateGeneratorInfo, TemplateState, UnreactiveState,
+    };
+    use crate::template::core::test_utils::*;
+    use crate::template::{StateCodec, Template};
+    use futures::executor::block_on;
+    use serde::{Deserialize, Serialize};
+    use sycamore::prelude::create_scope_immediate;
+    use sycamore::web::SsrNode;
+
+    #[test]
+    fn fallible_view_error_propagates() {
+        let template = Template::<SsrNode>::build("test")
+            .view_fallible(|_cx| Err::<View<SsrNode>, _>("missing field"))
+            .build();
+        let translator = translator();
+        let path = PathMaybeWithLocale::new(&PathWithoutLocale("test".to_string()), "xx-XX");
+
+        let mut res = None;
+        create_scope_immediate(|cx| {
+            res = Some(
+                template
+                    .render_for_template_server(
+                        path,
+                        TemplateState::empty(),
+                        TemplateState::empty(),
+                        RenderMode::Head,
+                        cx,
+                        &translator,
+                    )
+                    .map(|_| ()),
+            );
+        });
+        assert!(matches!(
+            res.unwrap(),
+            Err(ClientError::TemplateRenderFailed { path, .. }) if path == "test"
+        ));
+    }
+
+    #[test]
+    fn fallible_build_state_error_propagates() {
+        let template = Template::<SsrNode>::build("test")
+            .build_state_fn(|info: StateGeneratorInfo<()>| async move {
+                if info.path == "missing" {
+                    Err(BlamedError::from(std::io::Error::new(
+                        std::io::ErrorKind::NotFound,
+                        "fixture missing",
+                    )))
+                } else {
+                    Ok(TestState { title: info.path })
+                }
+            })
+            .build();
+
+        let res = block_on(template.get_build_state(info("missing")));
+        let source = match res {
+            Err(ServerError::InPage { path, source, .. }) if path == "missing" => source,
+            other => panic!("expected error in page, found {:?}", other),
+        };
+        assert!(matches!(
+            *source,
+            ServerError::RenderFnFailed { fn_name, .. } if fn_name == "build_state"
+        ));
+        let state = block_on(template.get_build_state(info("present"))).unwrap();
+        assert_eq!(
+            state.change_type::<TestState>().into_concrete().unwrap(),
+            TestState {
+                title: "present".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn request_state_receives_request() {
+        let template = Template::<SsrNode>::build("test")
+            .request_state_fn(|info: StateGeneratorInfo<()>, req: Request| async move {
+                let preview = req.uri().query() == Some("preview=true");
+                let auth = req
+                    .headers()
+                    .get("Authorization")
+                    .and_then(|val| val.to_str().ok())
+                    .unwrap_or_default();
+                Ok::<_, BlamedError<std::io::Error>>(TestState {
+                    title: format!("{}:{}:{}", info.path, auth, preview),
+                })
+            })
+            .build();
+
+        let req = Request::builder()
+            .method("GET")
+            .uri("/test?preview=true")
+            .header("Authorization", "Bearer token")
+            .body(())
+            .unwrap();
+        let state = block_on(template.get_request_state(info("test"), req)).unwrap();
+        assert_eq!(
+            state.change_type::<TestState>().into_concrete().unwrap(),
+            TestState {
+                title: "test:Bearer token:true".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn amalgamate_states_merges_build_and_request_state() {
+        let template = Template::<SsrNode>::build("test")
+            .build_state_fn(|info: StateGeneratorInfo<()>| async move {
+                TestState {
+                    title: format!("cached {}", info.path),
+                }
+            })
+            .request_state_fn(|_info: StateGeneratorInfo<()>, _req: Request| async move {
+                TestState {
+                    title: "override".to_string(),
+                }
+            })
+            .amalgamate_states_fn(
+                |_info: StateGeneratorInfo<()>,
+                 build_state: TestState,
+                 request_state: TestState| async move {
+                    TestState {
+                        title: format!("{} ({})", build_state.title, request_state.title),
+                    }
+                },
+            )
+            .build();
+
+        let build_state = block_on(template.get_build_state(info("test"))).unwrap();
+        let req = Request::builder().uri("/test").body(()).unwrap();
+        let request_state = block_on(template.get_request_state(info("test"), req)).unwrap();
+        let state =
+            block_on(template.amalgamate_states(info("test"), build_state, request_state)).unwrap();
+        assert_eq!(
+            state.change_type::<TestState>().into_concrete().unwrap(),
+            TestState {
+                title: "cached test (override)".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn amalgamate_states_without_fn_errors() {
+        let template = Template::<SsrNode>::build("test").build();
+        let res = block_on(template.amalgamate_states(
+            info("test"),
+            TemplateState::empty(),
+            TemplateState::empty(),
+        ));
+        assert!(matches!(
+            res,
+            Err(ServerError::BuildError(BuildError::TemplateFeatureNotEnabled { feature_name, .. })) if feature_name == "amalgamate_states"
+        ));
+    }
+
+    #[test]
+    fn basic_template_renders_without_state() {
+        let template = Template::<SsrNode>::build("test")
+            .view(|cx| sycamore::view! { cx, p { "Hello!" } })
+            .build();
+        assert!(template.is_basic());
+
+        let translator = translator();
+        let path = PathMaybeWithLocale::new(&PathWithoutLocale("test".to_string()), "xx-XX");
+        let mut html = String::new();
+        create_scope_immediate(|cx| {
+            let view = template
+                .render_for_template_server(
+                    path,
+                    TemplateState::empty(),
+                    TemplateState::empty(),
+                    RenderMode::Head,
+                    cx,
+                    &translator,
+                )
+                .unwrap();
+            html = sycamore::render_to_string(|_| view);
+        });
+        assert!(html.contains("Hello!"));
+    }
+
+    #[test]
+    fn build_state_fn_can_await() {
+        let template = Template::<SsrNode>::build("test")
+            .build_state_fn(|info: StateGeneratorInfo<()>| async move {
+                let title = futures::future::ready(info.path).await;
+                TestState { title }
+            })
+            .build();
+
+        let state = block_on(template.get_build_state(info("fetched"))).unwrap();
+        assert_eq!(
+            state.change_type::<TestState>().into_concrete().unwrap(),
+            TestState {
+                title: "fetched".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn build_paths_extra_reaches_build_state() {
+        use std::collections::HashMap;
+
+        let template = Template::<SsrNode>::build("test")
+            .build_paths_fn(|| async {
+                let mut states = HashMap::new();
+                states.insert("a".to_string(), "Post A".to_string());
+                states.insert("b".to_string(), "Post B".to_string());
+                BuildPaths {
+                    paths: vec!["a".to_string(), "b".to_string()],
+                    extra: states.into(),
+                }
+            })
+            .build_state_fn(
+                |info: StateGeneratorInfo<HashMap<String, String>>| async move {
+                    TestState {
+                        title: info.get_extra()[&info.path].clone(),
+                    }
+                },
+            )
+            .build();
+
+        let BuildPaths { paths, extra } = block_on(template.get_build_paths()).unwrap();
+        for path in paths {
+            let info = StateGeneratorInfo {
+                path: path.clone(),
+                locale: "xx-XX".to_string(),
+                extra: extra.clone(),
+            };
+            let state = block_on(template.get_build_state(info)).unwrap();
+            assert_eq!(
+                state
+                    .change_type::<TestState>()
+                    .into_concrete()
+                    .unwrap()
+                    .title,
+                format!("Post {}", path.to_uppercase())
+            );
+        }
+    }
+
+    #[test]
+    fn should_revalidate_receives_request() {
+        let template = Template::<SsrNode>::build("test")
+            .should_revalidate_fn(|_info: StateGeneratorInfo<()>, req: Request| async move {
+                req.uri().query() == Some("flush=1")
+            })
+            .build();
+        // There's no need to read the cached state for this
+        assert!(!template.revalidates_with_cached_state());
+
+        let req = Request::builder().uri("/test?flush=1").body(()).unwrap();
+        assert!(
+            block_on(template.should_revalidate(info("test"), req, TemplateState::empty()))
+                .unwrap()
+        );
+        let req = Request::builder().uri("/test").body(()).unwrap();
+        assert!(
+            !block_on(template.should_revalidate(info("test"), req, TemplateState::empty()))
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn head_renders_with_state() {
+        let template = Template::<SsrNode>::build("test")
+            .head_with_state(|cx, state: TestState| {
+                sycamore::view! { cx, title { (state.title) } }
+            })
+            .build();
+        let translator = translator();
+        let state: TemplateState = TestState {
+            title: "My Post".to_string(),
+        }
+        .into();
+
+        let head = template
+            .render_head_str(state, TemplateState::empty(), &translator)
+            .unwrap();
+        assert!(head.contains("<title>My Post</title>"));
+
+        let empty = Template::<SsrNode>::build("test").build();
+        let head = empty
+            .render_head_str(TemplateState::empty(), TemplateState::empty(), &translator)
+            .unwrap();
+        assert!(!head.contains("<title>"));
+    }
+
+    #[test]
+    fn build_paths_fn_can_await() {
+        let template = Template::<SsrNode>::build("test")
+            .build_paths_fn(|| async {
+                let mut paths = Vec::new();
+                for page in 1..=3 {
+                    paths.push(futures::future::ready(format!("page-{}", page)).await);
+                }
+                BuildPaths {
+                    paths,
+                    extra: ().into(),
+                }
+            })
+            .build();
+
+        let BuildPaths { paths, .. } = block_on(template.get_build_paths()).unwrap();
+        assert_eq!(paths, vec!["page-1", "page-2", "page-3"]);
+    }
+
+    #[test]
+    fn incremental_path_matcher_rejects_paths() {
+        let template = Template::<SsrNode>::build("blog")
+            .incremental_generation()
+            .incremental_path_matcher_fn(|path| path.chars().all(|c| c.is_ascii_digit()))
+            .build();
+        assert!(!template.can_render_incremental("abc"));
+        assert!(template.can_render_incremental("123"));
+
+        let unrestricted = Template::<SsrNode>::build("blog")
+            .incremental_generation()
+            .build();
+        assert!(unrestricted.can_render_incremental("abc"));
+    }
+
+    #[test]
+    fn build_state_fn_can_be_shared() {
+        use std::sync::Arc;
+
+        async fn shared_state(info: StateGeneratorInfo<()>) -> TestState {
+            TestState { title: info.path }
+        }
+        let prefix = Arc::new("post: ".to_string());
+        let prefixed_state = move |info: StateGeneratorInfo<()>| {
+            let prefix = prefix.clone();
+            async move {
+                TestState {
+                    title: format!("{}{}", prefix, info.path),
+                }
+            }
+        };
+
+        let first = Template::<SsrNode>::build("first")
+            .build_state_fn(shared_state)
+            .build();
+        let second = Template::<SsrNode>::build("second")
+            .build_state_fn(shared_state)
+            .build();
+        let first_state = block_on(first.get_build_state(info("test"))).unwrap();
+        let second_state = block_on(second.get_build_state(info("test"))).unwrap();
+        assert_eq!(first_state.state, second_state.state);
+
+        let first = Template::<SsrNode>::build("first")
+            .build_state_fn(prefixed_state.clone())
+            .build();
+        let second = Template::<SsrNode>::build("second")
+            .build_state_fn(prefixed_state)
+            .build();
+        let first_state = block_on(first.get_build_state(info("test"))).unwrap();
+        let second_state = block_on(second.get_build_state(info("test"))).unwrap();
+        assert_eq!(first_state.state, second_state.state);
+    }
+
+    #[test]
+    fn should_revalidate_with_state_compares_cached_state() {
+        #[derive(Serialize, Deserialize, Clone)]
+        struct VersionedState {
+            version: u32,
+        }
+        impl UnreactiveState for VersionedState {}
+
+        let template = Template::<SsrNode>::build("test")
+            .build_state_fn(|_info: StateGeneratorInfo<()>| async { VersionedState { version: 2 } })
+            .should_revalidate_with_state_fn(
+                |_info: StateGeneratorInfo<()>, _req: Request, cached: VersionedState| async move {
+                    // Pretend the upstream version is 2
+                    cached.version < 2
+                },
+            )
+            .build();
+        assert!(template.revalidates_with_cached_state());
+
+        let req = Request::builder().uri("/test").body(()).unwrap();
+        let cached: TemplateState = VersionedState { version: 1 }.into();
+        assert!(block_on(template.should_revalidate(info("test"), req, cached)).unwrap());
+        let req = Request::builder().uri("/test").body(()).unwrap();
+        let cached: TemplateState = VersionedState { version: 2 }.into();
+        assert!(!block_on(template.should_revalidate(info("test"), req, cached)).unwrap());
+    }
+
+    #[test]
+    fn render_to_string_renders_view() {
+        let template = Template::<SsrNode>::build("test")
+            .view(|cx| sycamore::view! { cx, p { "Hello" } })
+            .build();
+        let translator = translator();
+        let html = template
+            .render_to_string(TemplateState::empty(), &translator)
+            .unwrap();
+        assert!(html.contains("Hello"));
+    }
+
+    #[test]
+    fn build_paths_meta_round_trips() {
+        let template = Template::<SsrNode>::build("blog")
+            .build_paths_meta_fn(|| async {
+                vec![
+                    BuildPathMeta {
+                        path: "first".to_string(),
+                        last_modified: Some("2023-01-01".to_string()),
+                        priority: Some(0.8),
+                    },
+                    BuildPathMeta::new("second"),
+                ]
+            })
+            .build();
+        let meta = block_on(template.get_build_paths_meta()).unwrap();
+        assert_eq!(meta[0].last_modified.as_deref(), Some("2023-01-01"));
+        assert_eq!(meta[0].priority, Some(0.8));
+        assert_eq!(meta[1], BuildPathMeta::new("second"));
+        let BuildPaths { paths, .. } = block_on(template.get_build_paths()).unwrap();
+        assert_eq!(paths, vec!["first", "second"]);
+
+        let plain = Template::<SsrNode>::build("blog")
+            .build_paths_fn(|| async {
+                BuildPaths {
+                    paths: vec!["first".to_string()],
+                    extra: ().into(),
+                }
+            })
+            .build();
+        let meta = block_on(plain.get_build_paths_meta()).unwrap();
+        assert_eq!(meta, vec![BuildPathMeta::new("first")]);
+    }
+
+    #[test]
+    fn validate_state_fn_rejects_invalid_state() {
+        let template = Template::<SsrNode>::build("test")
+            .build_state_fn(|info: StateGeneratorInfo<()>| async move {
+                TestState {
+                    title: if info.path == "untitled" {
+                        String::new()
+                    } else {
+                        info.path
+                    },
+                }
+            })
+            .validate_state_fn(|state: &TestState| {
+                if state.title.is_empty() {
+                    Err("title must not be empty")
+                } else {
+                    Ok(())
+                }
+            })
+            .build();
+
+        assert!(block_on(template.get_build_state(info("titled"))).is_ok());
+        let res = block_on(template.get_build_state(info("untitled")));
+        assert!(matches!(
+            res,
+            Err(ServerError::StateValidationFailed { path, .. }) if path == "untitled"
+        ));
+    }
+
+    #[test]
+    fn render_to_writer_matches_string() {
+        let template = Template::<SsrNode>::build("test")
+            .view(|cx| {
+                sycamore::view! { cx,
+                    h1 { "Products" }
+                    ul { li { "First" } li { "Second" } }
+                }
+            })
+            .build();
+        let translator = translator();
+
+        let mut buf = Vec::new();
+        template
+            .render_to_writer(TemplateState::empty(), &translator, &mut buf)
+            .unwrap();
+        let html = template
+            .render_to_string(TemplateState::empty(), &translator)
+            .unwrap();
+        assert_eq!(buf, html.into_bytes());
+    }
+
+    #[test]
+    fn render_to_writer_applies_transform_html() {
+        let template = Template::<SsrNode>::build("test")
+            .view(|cx| sycamore::view! { cx, p { "Hello" } })
+            .transform_html_fn(|html| Ok::<_, std::io::Error>(html.to_uppercase()))
+            .build();
+        let translator = translator();
+
+        let mut buf = Vec::new();
+        template
+            .render_to_writer(TemplateState::empty(), &translator, &mut buf)
+            .unwrap();
+        let html = String::from_utf8(buf).unwrap();
+        assert!(html.contains("HELLO"));
+        assert_eq!(
+            html,
+            template
+                .render_to_string(TemplateState::empty(), &translator)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn build_state_fn_receives_locale() {
+        let template = Template::<SsrNode>::build("test")
+            .build_state_fn(|info: StateGeneratorInfo<()>| async move {
+                TestState {
+                    title: format!("{}:{}", info.locale, info.path),
+                }
+            })
+            .build();
+
+        let info = StateGeneratorInfo {
+            path: "about".to_string(),
+            locale: "fr".to_string(),
+            extra: TemplateState::empty(),
+        };
+        let state = block_on(template.get_build_state(info)).unwrap();
+        assert_eq!(
+            state.change_type::<TestState>().into_concrete().unwrap(),
+            TestState {
+                title: "fr:about".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn amalgamate_states_without_request_state_errors() {
+        let template = Template::<SsrNode>::build("test")
+            .build_state_fn(
+                |info: StateGeneratorInfo<()>| async move { TestState { title: info.path } },
+            )
+            .amalgamate_states_fn(
+                |_info: StateGeneratorInfo<()>,
+                 build_state: TestState,
+                 _request_state: TestState| async move { build_state },
+            )
+            .build();
+        let res = block_on(template.amalgamate_states(
+            info("test/page"),
+            TemplateState::empty(),
+            TemplateState::empty(),
+        ));
+        assert!(matches!(
+            res,
+            Err(ServerError::BuildError(BuildError::FeatureOrderViolation { feature_name, path, .. }))
+                if feature_name == "amalgamate_states" && path == "test/page"
+        ));
+    }
+
+    #[test]
+    fn incremental_build_limit_truncates_prerendered_paths() {
+        let template = Template::<SsrNode>::build("test")
+            .build_paths_fn(|| async {
+                BuildPaths {
+                    paths: (0..5).map(|i| i.to_string()).collect(),
+                    extra: ().into(),
+                }
+            })
+            .incremental_generation()
+            .incremental_build_limit(2)
+            .build();
+        let paths = block_on(template.build_paths_to_prerender()).unwrap();
+        assert_eq!(paths, vec!["0".to_string(), "1".to_string()]);
+    }
+
+    #[test]
+    fn serialized_state_round_trips() {
+        let template = Template::<SsrNode>::build("test").build();
+        let state: TemplateState = TestState {
+            title: "Hello!".to_string(),
+        }
+        .into();
+        let serialized = template.serialize_state(&state);
+        let deserialized = template.deserialize_state(&serialized).unwrap();
+        assert_eq!(
+            deserialized
+                .change_type::<TestState>()
+                .into_concrete()
+                .unwrap(),
+            TestState {
+                title: "Hello!".to_string()
+            }
+        );
+        assert!(template.deserialize_state("{invalid").is_err());
+    }
+
+    #[test]
+    fn build_start_fn_runs_and_propagates_errors() {
+        use std::sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        };
+
+        let runs = Arc::new(AtomicUsize::new(0));
+        let runs_clone = runs.clone();
+        let template = Template::<SsrNode>::build("test")
+            .build_start_fn(move || {
+                runs_clone.fetch_add(1, Ordering::SeqCst);
+                Ok::<(), std::io::Error>(())
+            })
+            .build();
+        template.run_build_start().unwrap();
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+
+        let template = Template::<SsrNode>::build("test")
+            .build_start_fn(|| Err(std::io::Error::other("index missing")))
+            .build();
+        assert!(matches!(
+            template.run_build_start(),
+            Err(ServerError::RenderFnFailed { fn_name, .. }) if fn_name == "build_start"
+        ));
+    }
+
+    #[test]
+    fn paginate_fn_fans_out_into_pages() {
+        #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+        struct ArchivePage {
+            items: Vec<usize>,
+        }
+        impl UnreactiveState for ArchivePage {}
+
+        let template = Template::<SsrNode>::build("archive")
+            .paginate_fn(|| async {
+                let items = (0..25).collect::<Vec<_>>();
+                items
+                    .chunks(10)
+                    .enumerate()
+                    .map(|(i, chunk)| {
+                        (
+                            (i + 1).to_string(),
+                            ArchivePage {
+                                items: chunk.to_vec(),
+                            },
+                        )
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .build();
+        assert!(template.uses_build_paths() && template.uses_build_state());
+
+        let BuildPaths { paths, extra } = block_on(template.get_build_paths()).unwrap();
+        assert_eq!(paths, vec!["1", "2", "3"]);
+        let state = block_on(template.get_build_state(StateGeneratorInfo {
+            path: "3".to_string(),
+            locale: "xx-XX".to_string(),
+            extra: extra.clone(),
+        }))
+        .unwrap();
+        assert_eq!(
+            state.change_type::<ArchivePage>().into_concrete().unwrap(),
+            ArchivePage {
+                items: (20..25).collect()
+            }
+        );
+
+        let res = block_on(template.get_build_state(StateGeneratorInfo {
+            path: "4".to_string(),
+            locale: "xx-XX".to_string(),
+            extra,
+        }));
+        assert!(matches!(
+            res,
+            Err(ServerError::ServeError(ServeError::PageNotFound { .. }))
+        ));
+    }
+
+    #[test]
+    fn conditional_request_state_can_use_build_state() {
+        use crate::template::states::States;
+
+        let template = Template::<SsrNode>::build("test")
+            .build_state_fn(|_info: StateGeneratorInfo<()>| async move {
+                TestState {
+                    title: "cached".to_string(),
+                }
+            })
+            .conditional_request_state_fn(
+                |_info: StateGeneratorInfo<()>, req: Request| async move {
+                    if req.headers().contains_key("authorization") {
+                        RequestStateOutcome::State(TestState {
+                            title: "personalized".to_string(),
+                        })
+                    } else {
+                        RequestStateOutcome::UseBuildState
+                    }
+                },
+            )
+            .build();
+        assert!(template.validate().is_ok());
+
+        let get_final_state = |req: Request| {
+            let states = States {
+                build_state: block_on(template.get_build_state(info("test"))).unwrap(),
+                request_state: block_on(template.get_request_state(info("test"), req)).unwrap(),
+            };
+            let state = if states.both_defined() {
+                states.request_state
+            } else {
+                states.get_defined().unwrap()
+            };
+            state
+                .change_type::<TestState>()
+                .into_concrete()
+                .unwrap()
+                .title
+        };
+        let anonymous = Request::builder().uri("/test").body(()).unwrap();
+        assert_eq!(get_final_state(anonymous), "cached");
+        let authorized = Request::builder()
+            .uri("/test")
+            .header("authorization", "Bearer token")
+            .body(())
+            .unwrap();
+        assert_eq!(get_final_state(authorized), "personalized");
+
+        let without_build_state = Template::<SsrNode>::build("test")
+            .conditional_request_state_fn(
+                |_info: StateGeneratorInfo<()>, _req: Request| async move {
+                    RequestStateOutcome::<TestState>::UseBuildState
+                },
+            )
+            .build();
+        assert!(matches!(
+            without_build_state.validate(),
+            Err(BuildError::TemplateFeatureNotEnabled { feature_name, .. }) if feature_name == "build_state"
+        ));
+    }
+
+    #[test]
+    fn build_paths_iter_fn_halts_on_error() {
+        use std::sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        };
+
+        let template = Template::<SsrNode>::build("product")
+            .build_paths_iter_fn(|| (0..3).map(|i| Ok::<_, std::io::Error>(i.to_string())))
+            .build();
+        let BuildPaths { paths, .. } = block_on(template.get_build_paths()).unwrap();
+        assert_eq!(paths, vec!["0", "1", "2"]);
+
+        let pulled = Arc::new(AtomicUsize::new(0));
+        let pulled_clone = pulled.clone();
+        let template = Template::<SsrNode>::build("product")
+            .build_paths_iter_fn(move || {
+                let pulled = pulled_clone.clone();
+                (0..5).map(move |i| {
+                    pulled.fetch_add(1, Ordering::SeqCst);
+                    if i == 1 {
+                        Err(std::io::Error::other("cursor closed"))
+                    } else {
+                        Ok(i.to_string())
+                    }
+                })
+            })
+            .build();
+        assert!(matches!(
+            block_on(template.get_build_paths()),
+            Err(ServerError::RenderFnFailed { fn_name, .. }) if fn_name == "build_paths"
+        ));
+        assert_eq!(pulled.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn transform_html_fn_applies_to_rendered_output() {
+        let template = Template::<SsrNode>::build("test")
+            .view(|cx| sycamore::view! { cx, p { "hello" } })
+            .transform_html_fn(|html| Ok::<_, std::io::Error>(html.to_uppercase()))
+            .build();
+        let translator = translator();
+        let html = template
+            .render_to_string(TemplateState::empty(), &translator)
+            .unwrap();
+        assert!(html.contains("HELLO"));
+        assert!(!html.contains("hello"));
+    }
+
+    #[test]
+    fn default_state_is_used_without_state() {
+        let template = Template::<SsrNode>::build("test")
+            .default_state(TestState {
+                title: "built at noon".to_string(),
+            })
+            .view_with_unreactive_state(|cx, state: TestState| {
+                sycamore::view! { cx, p { (state.title) } }
+            })
+            .build();
+        assert!(template.uses_build_state());
+
+        let translator = translator();
+        let html = template
+            .render_to_string(TemplateState::empty(), &translator)
+            .unwrap();
+        assert!(html.contains("built at noon"));
+        let state = block_on(template.get_build_state(info("test"))).unwrap();
+        assert_eq!(
+            state
+                .change_type::<TestState>()
+                .into_concrete()
+                .unwrap()
+                .title,
+            "built at noon"
+        );
+    }
+
+    #[test]
+    fn render_to_string_timed_returns_html_and_duration() {
+        let template = Template::<SsrNode>::build("test")
+            .view(|cx| sycamore::view! { cx, p { "Hello!" } })
+            .build();
+        let translator = translator();
+        let (html, duration) = template
+            .render_to_string_timed(TemplateState::empty(), &translator)
+            .unwrap();
+        assert!(html.contains("Hello!"));
+        assert!(duration >= std::time::Duration::ZERO);
+    }
+
+    #[test]
+    fn build_state_batch_fn_returns_states_in_order() {
+        let template = Template::<SsrNode>::build("test")
+            .build_state_batch_fn(|infos: Vec<StateGeneratorInfo<()>>| async move {
+                infos
+                    .into_iter()
+                    .map(|info| {
+                        if info.path == "bad" {
+                            Err(BlamedError {
+                                error: std::io::Error::other("not in the CMS"),
+                                blame: ErrorBlame::Server(None),
+                            })
+                        } else {
+                            Ok(TestState { title: info.path })
+                        }
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .build();
+        assert!(template.uses_build_state() && template.uses_build_state_batch());
+
+        let results =
+            block_on(template.get_build_states(vec![info("a"), info("bad"), info("c")])).unwrap();
+        assert_eq!(results.len(), 3);
+        let mut results = results.into_iter();
+        let title = |res: Option<Result<TemplateState, ServerError>>| {
+            res.unwrap().map(|state| {
+                state
+                    .change_type::<TestState>()
+                    .into_concrete()
+                    .unwrap()
+                    .title
+            })
+        };
+        assert_eq!(title(results.next()).unwrap(), "a");
+        assert!(title(results.next()).is_err());
+        assert_eq!(title(results.next()).unwrap(), "c");
+
+        // Individual generation should go through the batch function too
+        let state = block_on(template.get_build_state(info("b"))).unwrap();
+        assert_eq!(
+            state
+                .change_type::<TestState>()
+                .into_concrete()
+                .unwrap()
+                .title,
+            "b"
+        );
+    }
+
+    #[test]
+    fn request_state_with_meta_returns_headers() {
+        let template = Template::<SsrNode>::build("test")
+            .request_state_with_meta_fn(|info: StateGeneratorInfo<()>, _req: Request| async move {
+                let meta = ResponseMeta::new().header(
+                    http::header::SET_COOKIE,
+                    http::HeaderValue::from_static("session=abc"),
+                );
+                (TestState { title: info.path }, meta)
+            })
+            .build();
+
+        let req = request();
+        let (state, meta) =
+            block_on(template.get_request_state_with_meta(info("test"), req)).unwrap();
+        assert_eq!(
+            state.change_type::<TestState>().into_concrete().unwrap(),
+            TestState {
+                title: "test".to_string()
+            }
+        );
+        assert_eq!(
+            meta.headers.get(http::header::SET_COOKIE).unwrap(),
+            "session=abc"
+        );
+        assert_eq!(meta.status, None);
+    }
+
+    #[test]
+    fn owned_paths_joins_build_paths_under_root() {
+        let template = Template::<SsrNode>::build("blog")
+            .build_paths_fn(|| async {
+                BuildPaths {
+                    paths: vec!["/a".to_string(), "/b".to_string(), "a".to_string()],
+                    extra: ().into(),
+                }
+            })
+            .build();
+        assert_eq!(
+            block_on(template.owned_paths()).unwrap(),
+            vec![
+                "blog".to_string(),
+                "blog/a".to_string(),
+                "blog/b".to_string()
+            ]
+        );
+
+        let basic = Template::<SsrNode>::build("about").build();
+        assert_eq!(
+            block_on(basic.owned_paths()).unwrap(),
+            vec!["about".to_string()]
+        );
+    }
+
+    #[test]
+    fn error_view_renders_inline_on_view_failure() {
+        let template = Template::<SsrNode>::build("test")
+            .view_fallible(|_cx| Err::<View<SsrNode>, _>("missing field"))
+            .error_view(|cx, _err| sycamore::view! { cx, p { "oops" } })
+            .build();
+        let translator = translator();
+        let html = template
+            .render_to_string(TemplateState::empty(), &translator)
+            .unwrap();
+        assert!(html.contains("oops"));
+    }
+
+    #[test]
+    fn memoized_build_state_runs_once_per_path() {
+        use std::sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        };
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let template = Template::<SsrNode>::build("test")
+            .build_state_fn(move |info: StateGeneratorInfo<()>| {
+                let counter = counter.clone();
+                async move {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    TestState { title: info.path }
+                }
+            })
+            .memoize_build_state()
+            .build();
+
+        let first = block_on(template.get_build_state(info("test"))).unwrap();
+        let second = block_on(template.get_build_state(info("test"))).unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(first.state, second.state);
+
+        template.clear_build_state_cache();
+        block_on(template.get_build_state(info("test"))).unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn render_from_json_renders_and_rejects_malformed_json() {
+        let template = Template::<SsrNode>::build("test")
+            .view_with_unreactive_state(|cx, state: TestState| {
+                sycamore::view! { cx, p { (state.title) } }
+            })
+            .build();
+        let translator = translator();
+
+        let html = template
+            .render_from_json(r#"{"title":"From a fixture"}"#, &translator)
+            .unwrap();
+        assert!(html.contains("From a fixture"));
+
+        let err = template
+            .render_from_json(r#"{"title":"#, &translator)
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            ClientError::InvariantError(ClientInvariantError::InvalidState { .. })
+        ));
+    }
+
+    #[test]
+    fn request_middleware_runs_before_request_state() {
+        use crate::errors::err_to_status_code;
+
+        let template = Template::<SsrNode>::build("test")
+            .request_middleware(|req: &mut Request| {
+                req.headers_mut()
+                    .insert("X-Locale", http::HeaderValue::from_static("en-US"));
+                Ok::<_, BlamedError<std::io::Error>>(())
+            })
+            .request_state_fn(|_info: StateGeneratorInfo<()>, req: Request| async move {
+                TestState {
+                    title: req.headers()["X-Locale"].to_str().unwrap().to_string(),
+                }
+            })
+            .build();
+
+        let req = request();
+        let state = block_on(template.get_request_state(info("test"), req)).unwrap();
+        assert_eq!(
+            state.change_type::<TestState>().into_concrete().unwrap(),
+            TestState {
+                title: "en-US".to_string()
+            }
+        );
+
+        let rejecting = Template::<SsrNode>::build("test")
+            .request_middleware(|_req: &mut Request| {
+                Err(BlamedError {
+                    error: std::io::Error::other("rejected"),
+                    blame: ErrorBlame::Client(Some(403)),
+                })
+            })
+            .request_state_fn(|_info: StateGeneratorInfo<()>, _req: Request| async move {
+                TestState {
+                    title: String::new(),
+                }
+            })
+            .build();
+        let req = request();
+        let err = block_on(rejecting.get_request_state(info("test"), req)).unwrap_err();
+        assert_eq!(err_to_status_code(&err), 403);
+        let ServerError::InPage { source, .. } = err else {
+            panic!("expected error in page, found {:?}", err);
+        };
+        assert!(matches!(
+            *source,
+            ServerError::RenderFnFailed {
+                blame: ErrorBlame::Client(Some(403)),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn is_prerendered_checks_build_paths() {
+        let template = Template::<SsrNode>::build("blog")
+            .build_paths_fn(|| async {
+                BuildPaths {
+                    paths: vec!["a".to_string(), "b".to_string()],
+                    extra: ().into(),
+                }
+            })
+            .incremental_generation()
+            .build();
+        assert!(block_on(template.is_prerendered("blog/a")).unwrap());
+        assert!(block_on(template.is_prerendered("/blog/b/")).unwrap());
+        assert!(!block_on(template.is_prerendered("blog/c")).unwrap());
+        assert!(!block_on(template.is_prerendered("blog")).unwrap());
+
+        let basic = Template::<SsrNode>::build("about").build();
+        assert!(block_on(basic.is_prerendered("about")).unwrap());
+    }
+
+    #[test]
+    fn encoded_state_round_trips_with_template_codec() {
+        let state: TemplateState = TestState {
+            title: "Encoded".to_string(),
+        }
+        .into();
+        let template = Template::<SsrNode>::build("test")
+            .state_codec(StateCodec::Binary)
+            .build();
+        let encoded = template.encode_state(&state);
+        let decoded = template.decode_state(&encoded).unwrap();
+        assert_eq!(
+            decoded.change_type::<TestState>().into_concrete().unwrap(),
+            TestState {
+                title: "Encoded".to_string()
+            }
+        );
+        assert!(matches!(
+            template.decode_state(&[42]),
+            Err(ServerError::InvalidEncodedState { .. })
+        ));
+    }
+
+    #[test]
+    fn build_path_previews_align_states_with_paths() {
+        let template = Template::<SsrNode>::build("blog")
+            .build_paths_fn(|| async {
+                BuildPaths {
+                    paths: vec!["a".to_string(), "b".to_string()],
+                    extra: ().into(),
+                }
+            })
+            .build_state_fn(|info: StateGeneratorInfo<()>| async move {
+                TestState {
+                    title: format!("{} ({})", info.path, info.locale),
+                }
+            })
+            .build();
+        let previews = block_on(template.build_path_previews("en-US")).unwrap();
+        let previews = previews
+            .into_iter()
+            .map(|(path, state)| {
+                (
+                    path,
+                    state
+                        .change_type::<TestState>()
+                        .into_concrete()
+                        .unwrap()
+                        .title,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            previews,
+            vec![
+                ("a".to_string(), "a (en-US)".to_string()),
+                ("b".to_string(), "b (en-US)".to_string()),
+            ]
+        );
+
+        let broken = Template::<SsrNode>::build("blog")
+            .build_paths_fn(|| async {
+                BuildPaths {
+                    paths: vec!["a".to_string(), "broken".to_string()],
+                    extra: ().into(),
+                }
+            })
+            .build_state_fn(|info: StateGeneratorInfo<()>| async move {
+                if info.path == "broken" {
+                    return Err(BlamedError {
+                        error: std::io::Error::other("broken"),
+                        blame: ErrorBlame::Server(None),
+                    });
+                }
+                Ok(TestState { title: info.path })
+            })
+            .build();
+        let err = block_on(broken.build_path_previews("en-US")).unwrap_err();
+        assert!(matches!(err, ServerError::PreviewFailed { path, .. } if path == "broken"));
+    }
+
+    #[test]
+    fn redirecting_request_state_can_redirect() {
+        let template = Template::<SsrNode>::build("test")
+            .redirecting_request_state_fn(
+                |_info: StateGeneratorInfo<()>, _req: Request| async move {
+                    RenderOutcome::<TestState>::Redirect {
+                        to: "/login".to_string(),
+                        permanent: false,
+                    }
+                },
+            )
+            .build();
+        let req = request();
+        let (state, meta) =
+            block_on(template.get_request_state_with_meta(info("test"), req)).unwrap();
+        assert!(state.is_empty());
+        assert!(meta.is_redirect());
+        assert_eq!(meta.status, Some(http::StatusCode::FOUND));
+        assert_eq!(meta.headers.get(http::header::LOCATION).unwrap(), "/login");
+    }
+
+    #[test]
+    fn content_digest_is_deterministic() {
+        let template = Template::<SsrNode>::build("test")
+            .view_with_unreactive_state(|cx, state: TestState| {
+                sycamore::view! { cx, p { (state.title) } }
+            })
+            .build();
+        let translator = translator();
+        let state = |title: &str| {
+            Some(
+                TestState {
+                    title: title.to_string(),
+                }
+                .into(),
+            )
+        };
+
+        let digest = template
+            .content_digest(state("Hello"), &translator)
+            .unwrap();
+        assert_eq!(digest.len(), 16);
+        assert_eq!(
+            digest,
+            template
+                .content_digest(state("Hello"), &translator)
+                .unwrap()
+        );
+        assert_ne!(
+            digest,
+            template
+                .content_digest(state("Goodbye"), &translator)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn request_state_errors_map_to_status_codes() {
+        use crate::errors::err_to_status_code;
+
+        let not_found = Template::<SsrNode>::build("test")
+            .request_state_fn(|_info: StateGeneratorInfo<()>, _req: Request| async move {
+                Err::<TestState, _>(BlamedError::not_found(std::io::Error::other(
+                    "no such post",
+                )))
+            })
+            .build();
+        let req = request();
+        let err = block_on(not_found.get_request_state(info("test"), req)).unwrap_err();
+        assert_eq!(err_to_status_code(&err), 404);
+
+        let generic = Template::<SsrNode>::build("test")
+            .request_state_fn(|_info: StateGeneratorInfo<()>, _req: Request| async move {
+                Err::<TestState, BlamedError<_>>(std::io::Error::other("oops").into())
+            })
+            .build();
+        let req = request();
+        let err = block_on(generic.get_request_state(info("test"), req)).unwrap_err();
+        assert_eq!(err_to_status_code(&err), 500);
+    }
+
+    #[test]
+    fn status_code_is_reported_while_rendering() {
+        let default = Template::<SsrNode>::build("test").build();
+        assert_eq!(default.get_status_code(), 200);
+
+        let template = Template::<SsrNode>::build("old-post")
+            .status_code(410)
+            .view(|cx| sycamore::view! { cx, p { "This post has been removed." } })
+            .build();
+        let translator = translator();
+        assert_eq!(template.get_status_code(), 410);
+        let html = template
+            .render_to_string(TemplateState::empty(), &translator)
+            .unwrap();
+        assert!(html.contains("This post has been removed."));
+    }
+
+    #[test]
+    fn sort_build_paths_sorts_paths() {
+        let template = Template::<SsrNode>::build("test")
+            .build_paths_fn(|| async {
+                BuildPaths {
+                    paths: vec!["c".to_string(), "a".to_string(), "b".to_string()],
+                    extra: ().into(),
+                }
+            })
+            .sort_build_paths(true)
+            .build();
+        let build_paths = block_on(template.get_build_paths()).unwrap();
+        assert_eq!(build_paths.paths, vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn incremental_cache_limit_evicts_least_recently_used() {
+        let template = Template::<SsrNode>::build("test")
+            .incremental_cache_limit(2)
+            .build();
+        assert!(!template.should_evict(2));
+        assert!(template.should_evict(3));
+        assert!(!Template::<SsrNode>::build("test")
+            .build()
+            .should_evict(1000));
+
+        assert!(template.record_incremental_path("a").is_empty());
+        assert!(template.record_incremental_path("b").is_empty());
+        // This makes `b` the least recently used
+        assert!(template.record_incremental_path("a").is_empty());
+        assert_eq!(template.record_incremental_path("c"), vec!["b"]);
+        assert_eq!(template.record_incremental_path("b"), vec!["a"]);
+        assert!(template.record_incremental_path("c").is_empty());
+        assert!(Template::<SsrNode>::build("test")
+            .build()
+            .record_incremental_path("a")
+            .is_empty());
+
+        // The path being served should never be evicted, even with no room
+        let template = Template::<SsrNode>::build("test")
+            .incremental_cache_limit(0)
+            .build();
+        assert!(template.record_incremental_path("a").is_empty());
+        assert_eq!(template.record_incremental_path("b"), vec!["a"]);
+    }
+
+    #[test]
+    fn request_timeout_abandons_slow_request_state() {
+        use crate::errors::err_to_status_code;
+        use std::time::Duration;
+
+        let template = Template::<SsrNode>::build("test")
+            .request_state_fn(|_info: StateGeneratorInfo<()>, _req: Request| async move {
+                futures::future::pending::<TestState>().await
+            })
+            .request_timeout(Duration::from_millis(10))
+            .build();
+        // The timeout uses Tokio's timer
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+        let err = rt
+            .block_on(template.get_request_state(info("test"), request()))
+            .unwrap_err();
+        assert!(matches!(err, ServerError::RequestStateTimeout { .. }));
+        assert_eq!(err_to_status_code(&err), 504);
+    }
+
+    #[test]
+    fn render_head_to_string_excludes_body() {
+        let template = Template::<SsrNode>::build("test")
+            .view_with_unreactive_state(|cx, state: TestState| {
+                sycamore::view! { cx, p { "Body: " (state.title) } }
+            })
+            .head_with_state(|cx, state: TestState| {
+                sycamore::view! { cx, title { (state.title) } }
+            })
+            .build();
+        let translator = translator();
+        let state: TemplateState = TestState {
+            title: "My Post".to_string(),
+        }
+        .into();
+
+        let head = template.render_head_to_string(state, &translator).unwrap();
+        assert!(head.contains("<title>My Post</title>"));
+        assert!(!head.contains("Body:"));
+    }
+
+    #[test]
+    fn amalgamate_mixed_states_converts_build_summary() {
+        #[derive(Serialize, Deserialize, Clone)]
+        struct Summary {
+            count: usize,
+        }
+        impl UnreactiveState for Summary {}
+        #[derive(Serialize, Deserialize, Clone)]
+        struct Details {
+            names: Vec<String>,
+        }
+        impl UnreactiveState for Details {}
+
+        let template = Template::<SsrNode>::build("test")
+            .build_state_fn(|_info: StateGeneratorInfo<()>| async move { Summary { count: 3 } })
+            .request_state_fn(|_info: StateGeneratorInfo<()>, _req: Request| async move {
+                Details {
+                    names: vec!["a".to_string(), "b".to_string()],
+                }
+            })
+            .amalgamate_mixed_states_fn(
+                |_info: StateGeneratorInfo<()>, build: Summary, request: Details| async move {
+                    TestState {
+                        title: format!("{} of {}", request.names.join(", "), build.count),
+                    }
+                },
+            )
+            .build();
+
+        let build_state = block_on(template.get_build_state(info("test"))).unwrap();
+        let req = request();
+        let request_state = block_on(template.get_request_state(info("test"), req)).unwrap();
+        let state =
+            block_on(template.amalgamate_states(info("test"), build_state, request_state)).unwrap();
+        assert_eq!(
+            state.change_type::<TestState>().into_concrete().unwrap(),
+            TestState {
+                title: "a, b of 3".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn get_build_paths_checked_rejects_duplicates() {
+        let template = Template::<SsrNode>::build("test")
+            .build_paths_fn(|| async {
+                BuildPaths {
+                    paths: vec!["/a".to_string(), "/a".to_string()],
+                    extra: ().into(),
+                }
+            })
+            .build();
+        // Build paths are normalized before they're checked, so these are reported without
+        // their leading slashes
+        let err = block_on(template.get_build_paths_checked()).unwrap_err();
+        assert!(matches!(
+            err,
+            ServerError::BuildError(BuildError::DuplicateBuildPath { path, .. }) if path == "a"
+        ));
+        let differently_slashed = Template::<SsrNode>::build("test")
+            .build_paths_fn(|| async {
+                BuildPaths {
+                    paths: vec!["/a".to_string(), "a/".to_string()],
+                    extra: ().into(),
+                }
+            })
+            .build();
+        assert!(block_on(differently_slashed.get_build_paths_checked()).is_err());
+
+        let unique = Template::<SsrNode>::build("test")
+            .build_paths_fn(|| async {
+                BuildPaths {
+                    paths: vec!["/a".to_string(), "/b".to_string()],
+                    extra: ().into(),
+                }
+            })
+            .build();
+        assert_eq!(
+            block_on(unique.get_build_paths_checked()).unwrap(),
+            vec!["a", "b"]
+        );
+    }
+
+    #[test]
+    fn render_skeleton_has_placeholder_per_field() {
+        #[derive(Serialize, Default)]
+        struct PostState {
+            title: String,
+            author: String,
+            views: u32,
+        }
+
+        let template = Template::<SsrNode>::build("test").build();
+        let skeleton = template.render_skeleton::<PostState>().unwrap();
+        assert_eq!(skeleton.matches("perseus-skeleton-field").count(), 3);
+        for field in ["title", "author", "views"] {
+            assert!(skeleton.contains(&format!("data-field=\"{}\"", field)));
+        }
+    }
+
+    #[test]
+    fn revalidate_state_updates_old_state() {
+        #[derive(Serialize, Deserialize, Clone)]
+        struct PostState {
+            title: String,
+            comments: u32,
+        }
+        impl UnreactiveState for PostState {}
+
+        let template = Template::<SsrNode>::build("test")
+            .build_state_fn(|_info: StateGeneratorInfo<()>| async {
+                PostState {
+                    title: "Rebuilt".to_string(),
+                    comments: 0,
+                }
+            })
+            .revalidate_after("1h")
+            .revalidate_state_fn(|_info: StateGeneratorInfo<()>, old: PostState| async move {
+                // Only the comment count needs refreshing
+                PostState {
+                    comments: old.comments + 1,
+                    ..old
+                }
+            })
+            .build();
+        assert!(template.uses_revalidate_state());
+        assert!(template.validate_config().is_ok());
+
+        let old: TemplateState = PostState {
+            title: "Original".to_string(),
+            comments: 4,
+        }
+        .into();
+        let new = block_on(template.revalidate_state(info("test"), old)).unwrap();
+        let new = new.change_type::<PostState>().into_concrete().unwrap();
+        assert_eq!(new.title, "Original");
+        assert_eq!(new.comments, 5);
+
+        let without_revalidation = Template::<SsrNode>::build("test")
+            .build_state_fn(|_info: StateGeneratorInfo<()>| async {
+                PostState {
+                    title: "Rebuilt".to_string(),
+                    comments: 0,
+                }
+            })
+            .revalidate_state_fn(|_info: StateGeneratorInfo<()>, old: PostState| async move { old })
+            .build();
+        assert!(without_revalidation.validate_config().is_err());
+    }
+
+    #[test]
+    fn estimated_build_cost_multiplies_hint_by_paths() {
+        let template = Template::<SsrNode>::build("test")
+            .build_paths_fn(|| async {
+                BuildPaths {
+                    paths: (0..5).map(|i| i.to_string()).collect(),
+                    extra: ().into(),
+                }
+            })
+            .build_cost_hint(10)
+            .build();
+        assert_eq!(block_on(template.estimated_build_cost()).unwrap(), 50);
+
+        let unhinted = Template::<SsrNode>::build("test").build();
+        assert_eq!(block_on(unhinted.estimated_build_cost()).unwrap(), 1);
+    }
+
+    #[test]
+    fn warm_paths_generates_accepted_paths() {
+        let template = Template::<SsrNode>::build("blog")
+            .build_paths_fn(|| async {
+                BuildPaths {
+                    paths: Vec::new(),
+                    extra: ().into(),
+                }
+            })
+            .incremental_generation()
+            .incremental_path_matcher_fn(|path| path.chars().all(|c| c.is_ascii_digit()))
+            .build_state_fn(
+                |info: StateGeneratorInfo<()>| async move { TestState { title: info.path } },
+            )
+            .build();
+
+        let warmed =
+            block_on(template.warm_paths(vec!["1".to_string(), "2".to_string()], "xx-XX")).unwrap();
+        let titles: Vec<_> = warmed
+            .into_iter()
+            .map(|(path, state)| {
+                let state = state.change_type::<TestState>().into_concrete().unwrap();
+                (path, state.title)
+            })
+            .collect();
+        assert_eq!(
+            titles,
+            vec![
+                ("1".to_string(), "1".to_string()),
+                ("2".to_string(), "2".to_string())
+            ]
+        );
+
+        let rejected = block_on(template.warm_paths(vec!["abc".to_string()], "xx-XX"));
+        assert!(matches!(
+            rejected,
+            Err(ServerError::ServeError(ServeError::PageNotFound { .. }))
+        ));
+    }
+
+    #[test]
+    fn state_fn_errors_are_attached_to_pages() {
+        use crate::errors::err_to_status_code;
+
+        let template = Template::<SsrNode>::build("blog")
+            .build_state_fn(|_info: StateGeneratorInfo<()>| async move {
+                Err::<TestState, _>(BlamedError::from(std::io::Error::other("raw")))
+            })
+            .build();
+        let err = block_on(template.get_build_state(info("first-post"))).unwrap_err();
+        assert!(matches!(
+            &err,
+            ServerError::InPage { template_name, path, .. }
+                if template_name == "blog" && path == "first-post"
+        ));
+        assert!(err.to_string().contains("first-post"));
+        assert_eq!(err_to_status_code(&err), 500);
+    }
+
+    #[test]
+    fn skipped_build_state_paths_are_excluded() {
+        use crate::state::BuildStateOutcome;
+
+        let template = Template::<SsrNode>::build("blog")
+            .build_paths_fn(|| async {
+                BuildPaths {
+                    paths: vec!["a".to_string(), "draft".to_string(), "b".to_string()],
+                    extra: ().into(),
+                }
+            })
+            .skippable_build_state_fn(|info: StateGeneratorInfo<()>| async move {
+                if info.path == "draft" {
+                    BuildStateOutcome::Skip
+                } else {
+                    BuildStateOutcome::State(TestState { title: info.path })
+                }
+            })
+            .build();
+
+        let previews = block_on(template.build_path_previews("xx-XX")).unwrap();
+        let paths: Vec<_> = previews.into_iter().map(|(path, _)| path).collect();
+        assert_eq!(paths, vec!["a", "b"]);
+
+        let err = block_on(template.get_build_state(info("draft"))).unwrap_err();
+        assert!(matches!(
+            err,
+            ServerError::BuildError(BuildError::PathSkipped { ref path, .. }) if path == "draft"
+        ));
+    }
+
+    #[test]
+    fn render_to_string_ref_reuses_borrowed_state() {
+        let template = Template::<SsrNode>::build("test")
+            .view_with_unreactive_state(|cx, state: TestState| {
+                sycamore::view! { cx, p { (state.title) } }
+            })
+            .head_with_state(|cx, state: TestState| {
+                sycamore::view! { cx, title { (state.title) } }
+            })
+            .build();
+        let translator = translator();
+        let state: TemplateState = TestState {
+            title: "Hello".to_string(),
+        }
+        .into();
+
+        let body = template
+            .render_to_string_ref(Some(&state), &translator)
+            .unwrap();
+        let head = template
+            .render_head_to_string_ref(Some(&state), &translator)
+            .unwrap();
+        assert!(body.contains("Hello"));
+        assert!(head.contains("<title>Hello</title>"));
+        // The state is still ours to use
+        assert_eq!(
+            template
+                .render_to_string_ref(Some(&state), &translator)
+                .unwrap(),
+            body
+        );
+    }
+
+    #[test]
+    fn build_paths_are_normalized() {
+        let template = Template::<SsrNode>::build("test")
+            .build_paths_fn(|| async {
+                BuildPaths {
+                    paths: vec![
+                        "about/".to_string(),
+                        "/blog//post".to_string(),
+                        "/".to_string(),
+                    ],
+                    extra: ().into(),
+                }
+            })
+            .build();
+        let build_paths = block_on(template.get_build_paths()).unwrap();
+        assert_eq!(build_paths.paths, vec!["about", "blog/post", ""]);
+    }
+
+    #[test]
+    fn render_not_found_uses_custom_view() {
+        let template = Template::<SsrNode>::build("blog")
+            .incremental_generation()
+            .not_found_fn(|cx, path| {
+                let path = path.to_string();
+                sycamore::view! { cx, p { "No post at " (path) } }
+            })
+            .build();
+        let translator = translator();
+        let html = template
+            .render_not_found("blog/missing", &translator)
+            .unwrap();
+        assert!(html.contains("No post at blog/missing"));
+
+        let default = Template::<SsrNode>::build("blog").build();
+        assert!(default
+            .render_not_found("blog/missing", &translator)
+            .is_none());
+    }
+
+    #[test]
+    fn render_fallback_uses_fallback_view_and_layouts() {
+        let template = Template::<SsrNode>::build("blog")
+            .build_paths_fn(|| async {
+                BuildPaths {
+                    paths: Vec::new(),
+                    extra: ().into(),
+                }
+            })
+            .incremental_generation()
+            .fallback_fn(|cx| sycamore::view! { cx, p { "Loading post..." } })
+            .layout_fn(|cx, view| sycamore::view! { cx, main { (view) } })
+            .build();
+        assert!(template.validate_config().is_ok());
+        let translator = translator();
+        let html = template
+            .render_fallback(TemplateState::empty(), &translator)
+            .unwrap();
+        assert!(html.contains("Loading post..."));
+        assert!(html.contains("<main"));
+
+        let default = Template::<SsrNode>::build("blog").build();
+        assert!(default
+            .render_fallback(TemplateState::empty(), &translator)
+            .is_none());
+    }
+
+    #[test]
+    fn render_request_stream_yields_chunks_in_order() {
+        use futures::stream::{self, StreamExt};
+
+        let template = Template::<SsrNode>::build("listing")
+            .view_with_unreactive_state(|cx, state: TestState| {
+                sycamore::view! { cx, p { (state.title) } }
+            })
+            .stream_request_fn(|_info: StateGeneratorInfo<()>, _req| {
+                stream::iter(["one", "two", "three"].map(|title| TestState {
+                    title: title.to_string(),
+                }))
+            })
+            .build();
+        assert!(template.uses_stream_request_state());
+
+        let translator = translator();
+        let req = request();
+        let chunks: Vec<String> = block_on(
+            template
+                .render_request_stream(info("listing"), req, &translator)
+                .unwrap()
+                .map(|chunk| chunk.unwrap())
+                .collect(),
+        );
+        assert_eq!(chunks.len(), 3);
+        assert!(chunks[0].contains("one"));
+        assert!(chunks[1].contains("two"));
+        assert!(chunks[2].contains("three"));
+    }
+
+    #[test]
+    fn render_request_stream_chunks_are_standalone_fragments() {
+        use futures::stream::{self, StreamExt};
+
+        let template = Template::<SsrNode>::build("listing")
+            .view_with_unreactive_state(|cx, state: TestState| {
+                sycamore::view! { cx, p { (state.title) } }
+            })
+            .layout_fn(|cx, page| sycamore::view! { cx, main { (page) } })
+            .transform_html_fn(|html| Ok::<_, std::io::Error>(html.to_uppercase()))
+            .stream_request_fn(|_info: StateGeneratorInfo<()>, _req| {
+                stream::iter(["one", "two"].map(|title| TestState {
+                    title: title.to_string(),
+                }))
+            })
+            .build();
+
+        let translator = translator();
+        let req = request();
+        let chunks: Vec<String> = block_on(
+            template
+                .render_request_stream(info("listing"), req, &translator)
+                .unwrap()
+                .map(|chunk| chunk.unwrap())
+                .collect(),
+        );
+        for (chunk, title) in chunks.iter().zip(["one", "two"]) {
+            // No layouts, no transformation, and no hydration keys
+            assert!(chunk.contains(title));
+            assert!(!chunk.contains("<main"));
+            assert!(!chunk.contains("data-hk"));
+        }
+    }
+
+    #[test]
+    fn render_request_stream_requires_stream_fn() {
+        let template = Template::<SsrNode>::build("listing").build();
+        let translator = translator();
+        let req = request();
+        assert!(template
+            .render_request_stream(info("listing"), req, &translator)
+            .is_err());
+    }
+}
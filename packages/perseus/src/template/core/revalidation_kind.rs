@@ -0,0 +1,15 @@
+/// The kind of revalidation a template uses, which can be computed with
+/// `.revalidation_kind()`. This is mostly useful for reporting, since
+/// `.revalidates()` alone doesn't distinguish between the two forms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RevalidationKind {
+    /// The template doesn't revalidate.
+    None,
+    /// The template revalidates after some time has elapsed.
+    TimeBased,
+    /// The template revalidates based on some custom logic.
+    LogicBased,
+    /// The template revalidates after some time has elapsed, and also based
+    /// on some custom logic.
+    Both,
+}
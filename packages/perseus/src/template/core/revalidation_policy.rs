@@ -0,0 +1,19 @@
+/// How a template's pages should be served when they need to be revalidated,
+/// which can be computed with `.revalidation_policy()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RevalidationPolicy {
+    /// The template doesn't revalidate, so its pages are never stale.
+    None,
+    /// Pages that need revalidation will be revalidated before they're
+    /// served, so requests will never receive stale pages.
+    Blocking,
+    /// Pages that need revalidation may be served stale while they're
+    /// revalidated in the background.
+    StaleWhileRevalidate,
+}
+impl RevalidationPolicy {
+    /// Checks if this policy allows stale pages to be served.
+    pub fn allows_stale(&self) -> bool {
+        matches!(self, Self::StaleWhileRevalidate)
+    }
+}
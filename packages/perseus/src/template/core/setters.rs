@@ -1,4 +1,4 @@
-use super::TemplateInner;
+use super::{Compression, StateCodec, TemplateDefaults, TemplateInner};
 use crate::utils::PerseusDuration;
 use sycamore::web::Html;
 
@@ -6,15 +6,27 @@ use sycamore::web::Html;
 #[cfg(engine)]
 use super::super::fn_types::*;
 #[cfg(engine)]
-use crate::state::{BuildPaths, MakeRx};
+use crate::errors::{
+    BlamedError, BuildError, ClientError, ClientInvariantError, ErrorBlame, ServeError, ServerError,
+};
+#[cfg(engine)]
+use crate::state::{
+    BuildPathMeta, BuildPaths, BuildStateOutcome, MakeRx, RenderOutcome, RequestStateOutcome,
+    ResponseMeta,
+};
 #[cfg(engine)]
 use crate::state::{StateGeneratorInfo, TemplateState, UnknownStateType};
 #[cfg(engine)]
+use crate::Request;
+#[cfg(engine)]
 use http::HeaderMap;
 #[cfg(engine)]
 use serde::{de::DeserializeOwned, Serialize};
 #[cfg(engine)]
-use sycamore::{prelude::Scope, view::View, web::SsrNode};
+use std::{collections::HashMap, sync::Mutex};
+#[cfg(engine)]
+use sycamore::web::SsrNode;
+use sycamore::{prelude::Scope, view::View};
 
 impl<G: Html> TemplateInner<G> {
     // The server-only ones have a different version for Wasm that takes in an empty
@@ -67,97 +79,1131 @@ impl<G: Html> TemplateInner<G> {
         }));
         self
     }
-    /// Sets the function to set headers. This will override Perseus' inbuilt
-    /// header defaults. This should only be used when your header-setting
-    /// does not need state.
+    /// Sets the function to set headers. This will override Perseus' inbuilt
+    /// header defaults. This should only be used when your header-setting
+    /// does not need state.
+    #[cfg(any(client, doc))]
+    pub fn set_headers(self, _val: impl Fn() + 'static) -> Self {
+        self
+    }
+    /// Sets the `Cache-Control` header to send with this template's pages.
+    /// This will be ignored if you set your own headers with
+    /// `.set_headers()` or `.set_headers_with_state()`, in which case you
+    /// should set this header yourself (perhaps with the value from
+    /// `.cache_control_header()`).
+    #[cfg(engine)]
+    pub fn cache_control(mut self, val: impl Into<String>) -> Self {
+        self.cache_control = Some(val.into());
+        self
+    }
+    /// Sets the `Cache-Control` header to send with this template's pages.
+    /// This will be ignored if you set your own headers with
+    /// `.set_headers()` or `.set_headers_with_state()`, in which case you
+    /// should set this header yourself (perhaps with the value from
+    /// `.cache_control_header()`).
+    #[cfg(any(client, doc))]
+    pub fn cache_control(self, _val: impl Into<String>) -> Self {
+        self
+    }
+    /// Sets the `Content-Type` of this template's pages, for templates that
+    /// don't render HTML (e.g. RSS feeds). Perseus won't change how your
+    /// pages are rendered based on this, but server integrations can use it
+    /// to set the response header.
+    #[cfg(engine)]
+    pub fn content_type(mut self, val: impl Into<String>) -> Self {
+        self.content_type = Some(val.into());
+        self
+    }
+    /// Sets the `Content-Type` of this template's pages, for templates that
+    /// don't render HTML (e.g. RSS feeds).
+    #[cfg(any(client, doc))]
+    pub fn content_type(self, _val: impl Into<String>) -> Self {
+        self
+    }
+    /// Sets the file extension (e.g. `xml`) that this template's pages will be
+    /// exported with, rather than being exported as `index.html` files in
+    /// their own directories. This is useful for pages like RSS feeds, and
+    /// it will usually be used alongside `.content_type()`.
+    #[cfg(engine)]
+    pub fn output_extension(mut self, val: impl Into<String>) -> Self {
+        let val = val.into();
+        self.output_extension = Some(val.trim_start_matches('.').to_string());
+        self
+    }
+    /// Sets the file extension (e.g. `xml`) that this template's pages will be
+    /// exported with.
+    #[cfg(any(client, doc))]
+    pub fn output_extension(self, _val: impl Into<String>) -> Self {
+        self
+    }
+    /// Adds another path at which this template's root page will also be
+    /// served (e.g. `about-us` for an `about` template). This can be called
+    /// multiple times to add several aliases. Aliases render exactly the
+    /// same page as the template's own path, and they're registered with
+    /// the router on both the engine-side and the browser-side (so this
+    /// has no browser-side dummy).
+    ///
+    /// Note that aliases are full paths, not relative to the template, and
+    /// they will take precedence over any other page with the same path.
+    pub fn alias(mut self, val: impl Into<String>) -> Self {
+        let val = val.into();
+        self.aliases.push(val.trim_matches('/').to_string());
+        self
+    }
+    /// Sets the HTTP status code that this template's pages will be served
+    /// with, which is useful for pages that should still render content,
+    /// but that are intentionally gone (*410*) or placeholders for missing
+    /// content (*404*). By default, this is *200*. This only applies to
+    /// initial loads, and any status set in the page's [`ResponseMeta`]
+    /// will take precedence.
+    #[cfg(engine)]
+    pub fn status_code(mut self, val: u16) -> Self {
+        self.status_code = val;
+        self
+    }
+    /// Sets the HTTP status code that this template's pages will be served
+    /// with.
+    #[cfg(any(client, doc))]
+    pub fn status_code(self, _val: u16) -> Self {
+        self
+    }
+    /// Adds the given tag to this template. Tags have no effect on Perseus
+    /// itself, but they can be used to group templates for external tools
+    /// (e.g. to only rebuild pages in the `blog` group when blog content
+    /// changes). This can be called multiple times to add multiple tags.
+    #[cfg(engine)]
+    pub fn tag(mut self, val: impl Into<String>) -> Self {
+        self.tags.push(val.into());
+        self
+    }
+    /// Adds the given tag to this template.
+    #[cfg(any(client, doc))]
+    pub fn tag(self, _val: impl Into<String>) -> Self {
+        self
+    }
+    /// Sets a function that will generate the canonical URL of each of this
+    /// template's pages (e.g. for `<link rel="canonical">` in the document
+    /// `<head>`), given the path of the page (including the template root)
+    /// and its state. The state will be `None` if the page has none, or if
+    /// it isn't of the type you declare.
+    #[cfg(engine)]
+    pub fn canonical_url_fn<S>(
+        mut self,
+        val: impl Fn(&str, Option<&S>) -> String + Send + Sync + 'static,
+    ) -> Self
+    where
+        S: Serialize + DeserializeOwned + MakeRx + 'static,
+    {
+        self.canonical_url = Some(Box::new(move |path, template_state: &TemplateState| {
+            let state = if template_state.is_empty() {
+                None
+            } else {
+                template_state
+                    .clone()
+                    .change_type::<S>()
+                    .into_concrete()
+                    .ok()
+            };
+            val(path, state.as_ref())
+        }));
+        self
+    }
+    /// Sets a function that will generate the canonical URL of each of this
+    /// template's pages.
+    #[cfg(any(client, doc))]
+    pub fn canonical_url_fn(self, _val: impl Fn() + 'static) -> Self {
+        self
+    }
+    /// Sets a pattern describing the paths of this template's pages, from
+    /// which parameters can be extracted with `.extract_params()`. Segments
+    /// starting with `:` are parameters, and all others must match exactly,
+    /// so `blog/:year/:slug` would match `blog/2024/hi`, giving the
+    /// parameters `year` and `slug`. This is useful for getting structured
+    /// information from your build paths in functions like
+    /// `.build_state_fn()`, instead of splitting them manually.
+    ///
+    /// Leading and trailing slashes are ignored, in both the pattern and the
+    /// paths matched against it.
+    #[cfg(engine)]
+    pub fn route_pattern(mut self, val: &str) -> Self {
+        self.route_pattern = Some(val.to_string());
+        self
+    }
+    /// Sets a pattern describing the paths of this template's pages, from
+    /// which parameters can be extracted with `.extract_params()`.
+    #[cfg(any(client, doc))]
+    pub fn route_pattern(self, _val: &str) -> Self {
+        self
+    }
+    /// Sets whether or not this template is a draft. Drafts will be built and
+    /// served as usual in development, but they'll be left out of production
+    /// builds entirely (so requests for their pages will receive 404 errors),
+    /// which is useful for content you're writing ahead of publication.
+    ///
+    /// This has no effect on capsules.
+    #[cfg(engine)]
+    pub fn draft(mut self, val: bool) -> Self {
+        self.draft = val;
+        self
+    }
+    /// Sets whether or not this template is a draft. Drafts will be built and
+    /// served as usual in development, but they'll be left out of production
+    /// builds entirely.
+    #[cfg(any(client, doc))]
+    pub fn draft(self, _val: bool) -> Self {
+        self
+    }
+    /// Sets a function that will decide whether or not this template's pages
+    /// are enabled, which is useful for putting pages behind runtime feature
+    /// flags. Requests for the pages of disabled templates will receive 404
+    /// errors. This is checked on every request, and disabled templates are
+    /// still built, so they can be enabled at any time without a rebuild.
+    /// When exporting, this will be checked once, and disabled templates
+    /// will be left out of the export.
+    ///
+    /// This has no effect on capsules.
+    #[cfg(engine)]
+    pub fn enabled_when(mut self, val: impl Fn() -> bool + Send + Sync + 'static) -> Self {
+        self.enabled_when = Some(Box::new(val));
+        self
+    }
+    /// Sets a function that will decide whether or not this template's pages
+    /// are enabled, which is useful for putting pages behind runtime feature
+    /// flags.
+    #[cfg(any(client, doc))]
+    pub fn enabled_when(self, _val: impl Fn() + 'static) -> Self {
+        self
+    }
+
+    /// Enables the *build paths* strategy with the given function. This
+    /// function is asynchronous, so you can `.await` things like paginated
+    /// API requests inside it.
+    ///
+    /// If the data for each path comes from the same place as the paths
+    /// themselves (e.g. a CMS), you can put it in the `extra` field of the
+    /// [`BuildPaths`] you return, and it will be available to your build
+    /// state function through `info.get_extra()`, avoiding a second request
+    /// for each path.
+    ///
+    /// The paths you return should not include a locale: if your app uses
+    /// i18n, each one will be rendered once for every locale your app
+    /// supports (e.g. `about` will become `en-US/about` and `fr-FR/about`),
+    /// and your state generation functions will be told which locale they're
+    /// generating for through `info.locale`.
+    ///
+    /// Leading, trailing, and duplicate slashes in the paths you return will
+    /// be stripped, so `/about/` and `about` are the same path.
+    #[cfg(engine)]
+    pub fn build_paths_fn<V: Into<GeneratorResult<BuildPaths>>>(
+        mut self,
+        val: impl GetBuildPathsUserFnType<V> + Clone + Send + Sync + 'static,
+    ) -> Self {
+        let template_name = self.get_path();
+        self.get_build_paths = Some(Box::new(move || {
+            let val = val.clone();
+            let template_name = template_name.clone();
+            async move {
+                val.call()
+                    .await
+                    .into()
+                    .into_server_result("build_paths", template_name)
+            }
+        }));
+        self
+    }
+    /// Enables the *build paths* strategy with the given function.
+    #[cfg(any(client, doc))]
+    pub fn build_paths_fn(self, _val: impl Fn() + 'static) -> Self {
+        self
+    }
+    /// Enables the *build paths* strategy with the given function, which
+    /// produces an iterator of paths, rather than a `Vec`. This is useful
+    /// when your paths come from a source that's naturally iterated, like a
+    /// database cursor. The iterator will be consumed lazily, and the first
+    /// error it yields will stop any more paths from being pulled from it,
+    /// failing the build.
+    ///
+    /// Note that this function is synchronous, and it can't produce any extra
+    /// state. All the paths will still be held in memory once collected, since
+    /// Perseus needs them for routing.
+    #[cfg(engine)]
+    pub fn build_paths_iter_fn<I, E>(mut self, val: impl Fn() -> I + Send + Sync + 'static) -> Self
+    where
+        I: Iterator<Item = Result<String, E>>,
+        E: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        let template_name = self.get_path();
+        self.get_build_paths = Some(Box::new(move || {
+            // We consume the iterator outside the future, so it doesn't need to be `Send`
+            let res = val()
+                .collect::<Result<Vec<String>, E>>()
+                .map(|paths| BuildPaths {
+                    paths,
+                    extra: TemplateState::empty(),
+                })
+                .map_err(|err| ServerError::RenderFnFailed {
+                    fn_name: "build_paths".to_string(),
+                    template_name: template_name.clone(),
+                    blame: ErrorBlame::Server(None),
+                    source: err.into(),
+                });
+            async move { res }
+        }));
+        self
+    }
+    /// Enables the *build paths* strategy with the given function, which
+    /// produces an iterator of paths, rather than a `Vec`.
+    #[cfg(any(client, doc))]
+    pub fn build_paths_iter_fn(self, _val: impl Fn() + 'static) -> Self {
+        self
+    }
+    /// Enables the *build paths* strategy with the given function, which
+    /// generates metadata about each path as well, such as when it was last
+    /// modified. These can be accessed later, which is useful for generating
+    /// sitemaps.
+    ///
+    /// This should be used instead of `.build_paths_fn()`, and it will not
+    /// produce any extra state.
+    #[cfg(engine)]
+    pub fn build_paths_meta_fn<V: Into<GeneratorResult<Vec<BuildPathMeta>>>>(
+        mut self,
+        val: impl GetBuildPathsMetaUserFnType<V> + Clone + Send + Sync + 'static,
+    ) -> Self {
+        let template_name = self.get_path();
+        let meta_fn = {
+            let val = val.clone();
+            let template_name = template_name.clone();
+            move || {
+                let val = val.clone();
+                let template_name = template_name.clone();
+                async move {
+                    val.call()
+                        .await
+                        .into()
+                        .into_server_result("build_paths", template_name)
+                }
+            }
+        };
+        self.get_build_paths_meta = Some(Box::new(meta_fn.clone()));
+        self.get_build_paths = Some(Box::new(move || {
+            let meta_fn = meta_fn.clone();
+            async move {
+                let meta = meta_fn().await?;
+                Ok(BuildPaths {
+                    paths: meta.into_iter().map(|meta| meta.path).collect(),
+                    extra: TemplateState::empty(),
+                })
+            }
+        }));
+        self
+    }
+    /// Enables the *build paths* strategy with the given function, which
+    /// generates metadata about each path as well, such as when it was last
+    /// modified. These can be accessed later, which is useful for generating
+    /// sitemaps.
+    ///
+    /// This should be used instead of `.build_paths_fn()`, and it will not
+    /// produce any extra state.
+    #[cfg(any(client, doc))]
+    pub fn build_paths_meta_fn(self, _val: impl Fn() + 'static) -> Self {
+        self
+    }
+
+    /// Enables the *incremental generation* strategy.
+    ///
+    /// Pages that weren't generated at build-time will be generated on their
+    /// first request, before the response is sent (and cached thereafter),
+    /// unless a fallback has been set with `.fallback_fn()`. If that
+    /// generation fails, your error views will be rendered instead.
+    #[cfg(engine)]
+    pub fn incremental_generation(mut self) -> Self {
+        self.incremental_generation = true;
+        self
+    }
+    /// Enables the *incremental generation* strategy.
+    #[cfg(any(client, doc))]
+    pub fn incremental_generation(self) -> Self {
+        self
+    }
+    /// Sets a placeholder view (e.g. a loading skeleton) for pages of this
+    /// template that are yet to be incrementally generated. Rather than
+    /// making the user wait for the page to be generated on its first
+    /// request, the server will send this straight away, and the app shell
+    /// will then fetch the real page (generating it on the server) and
+    /// render it in place of the fallback. Once a page has been generated,
+    /// its fallback won't be shown again.
+    ///
+    /// Fallbacks can't access any state, and they can't use widgets, but
+    /// they can use translations. This requires the *incremental generation*
+    /// strategy, and it's needed on both the engine-side and the
+    /// browser-side (so this has no browser-side dummy).
+    pub fn fallback_fn(mut self, val: impl Fn(Scope) -> View<G> + Send + Sync + 'static) -> Self {
+        self.fallback_view = Some(Box::new(val));
+        self
+    }
+    /// Restricts the paths that the *incremental generation* strategy will
+    /// generate to those for which the given function returns `true`. Any
+    /// other paths will lead to a 404, without any state generation being
+    /// run. This is strongly recommended if your incremental paths follow a
+    /// known pattern, as it stops arbitrary requests from causing arbitrary
+    /// amounts of work (and storage) on your server.
+    ///
+    /// The function will be given the path to be generated, relative to the
+    /// template root (e.g. `abc` for `/post/abc`).
+    #[cfg(engine)]
+    pub fn incremental_path_matcher_fn(
+        mut self,
+        val: impl Fn(&str) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.incremental_path_matcher = Some(Box::new(val));
+        self
+    }
+    /// Restricts the paths that the *incremental generation* strategy will
+    /// generate to those for which the given function returns `true`. Any
+    /// other paths will lead to a 404, without any state generation being
+    /// run. This is strongly recommended if your incremental paths follow a
+    /// known pattern, as it stops arbitrary requests from causing arbitrary
+    /// amounts of work (and storage) on your server.
+    ///
+    /// The function will be given the path to be generated, relative to the
+    /// template root (e.g. `abc` for `/post/abc`).
+    #[cfg(any(client, doc))]
+    pub fn incremental_path_matcher_fn(self, _val: impl Fn(&str) -> bool + 'static) -> Self {
+        self
+    }
+    /// Limits the number of paths generated by the *build paths* strategy
+    /// that will actually be prerendered at build-time to the given number.
+    /// The rest will be left to the *incremental generation* strategy, and
+    /// generated on their first request, which can be used to keep build
+    /// times down for templates with very large numbers of pages.
+    ///
+    /// This has no effect unless incremental generation is enabled, since
+    /// the remaining paths couldn't be generated otherwise. Note that those
+    /// paths must also be allowed by the incremental path matcher, if you've
+    /// set one.
+    #[cfg(engine)]
+    pub fn incremental_build_limit(mut self, val: usize) -> Self {
+        self.incremental_build_limit = Some(val);
+        self
+    }
+    /// Limits the number of paths generated by the *build paths* strategy
+    /// that will actually be prerendered at build-time to the given number.
+    #[cfg(any(client, doc))]
+    pub fn incremental_build_limit(self, _val: usize) -> Self {
+        self
+    }
+    /// Sets whether or not the paths generated by the *build paths* strategy
+    /// should be sorted lexically, which makes the order in which they're
+    /// built (and any build artifacts that depend on it) the same from run
+    /// to run, even if your function returns them in an arbitrary order. By
+    /// default, the order from your function is kept.
+    #[cfg(engine)]
+    pub fn sort_build_paths(mut self, val: bool) -> Self {
+        self.sort_build_paths = val;
+        self
+    }
+    /// Sets whether or not the paths generated by the *build paths* strategy
+    /// should be sorted lexically.
+    #[cfg(any(client, doc))]
+    pub fn sort_build_paths(self, _val: bool) -> Self {
+        self
+    }
+    /// Sets a hint of the relative cost of building a single page of this
+    /// template, in whatever units you like. Perseus doesn't use this
+    /// itself, but it's reported by `.estimated_build_cost()` so that
+    /// external build schedulers can balance work between workers. By
+    /// default, every page has a cost of 1.
+    #[cfg(engine)]
+    pub fn build_cost_hint(mut self, val: u32) -> Self {
+        self.build_cost_hint = Some(val);
+        self
+    }
+    /// Sets a hint of the relative cost of building a single page of this
+    /// template, in whatever units you like.
+    #[cfg(any(client, doc))]
+    pub fn build_cost_hint(self, _val: u32) -> Self {
+        self
+    }
+    /// Limits the number of paths generated on-demand with incremental
+    /// generation that will stay cached at once. Once more than this many
+    /// have been generated, the least recently used will be evicted and
+    /// deleted from the mutable store, and it will be generated again the
+    /// next time it's requested. The page currently being served is never
+    /// evicted, so a limit of `0` will keep only the most recently requested
+    /// page. Note that usage is tracked in memory, so,
+    /// after the server restarts, pages already in the mutable store will
+    /// only start being tracked again (and hence be eligible for eviction)
+    /// once they're next requested.
+    #[cfg(engine)]
+    pub fn incremental_cache_limit(mut self, val: usize) -> Self {
+        self.incremental_cache_limit = Some(val);
+        self
+    }
+    /// Limits the number of paths generated on-demand with incremental
+    /// generation that will stay cached at once.
+    #[cfg(any(client, doc))]
+    pub fn incremental_cache_limit(self, _val: usize) -> Self {
+        self
+    }
+
+    /// Enables the *build state* strategy with the given function. This
+    /// function is asynchronous, so you can `.await` things like network
+    /// requests inside it.
+    ///
+    /// Your function may return either your state directly, or a
+    /// `Result<S, BlamedError<E>>`, in which case any errors will be
+    /// propagated as a
+    /// [`ServerError::RenderFnFailed`](crate::errors::ServerError::RenderFnFailed)
+    /// that names the template, rather than requiring you to `.unwrap()`
+    /// inside your function (which would terminate the whole build with a
+    /// panic). Since `?` will convert any error into a
+    /// [`BlamedError`](crate::errors::BlamedError) blaming the server, this is
+    /// usually as simple as returning `Ok(state)`.
+    ///
+    /// The same function can be shared between several templates by defining
+    /// it as a named `async fn` and passing it to each one. If your logic
+    /// needs some shared data (e.g. a configuration loaded once), capture it
+    /// in an `Arc` and clone that into the future your closure returns: the
+    /// closure will live as long as the template does (i.e. for the life of
+    /// your app), and the future must be `'static`, so it can't borrow
+    /// anything.
+    #[cfg(engine)]
+    pub fn build_state_fn<S, B, V>(
+        mut self,
+        val: impl GetBuildStateUserFnType<S, B, V> + Clone + Send + Sync + 'static,
+    ) -> Self
+    where
+        S: Serialize + DeserializeOwned + MakeRx,
+        B: Serialize + DeserializeOwned + Send + Sync + 'static,
+        V: Into<BlamedGeneratorResult<S>>,
+    {
+        let template_name = self.get_path();
+        self.get_build_state = Some(Box::new(
+            move |info: StateGeneratorInfo<UnknownStateType>| {
+                let val = val.clone();
+                let template_name = template_name.clone();
+                async move {
+                    let user_info = info.change_type::<B>();
+                    let user_state = val
+                        .call(user_info)
+                        .await
+                        .into()
+                        .into_server_result("build_state", template_name)?;
+                    let template_state: TemplateState = user_state.into();
+                    Ok(template_state)
+                }
+            },
+        ));
+        self
+    }
+    /// Enables the *build state* strategy with the given function.
+    #[cfg(any(client, doc))]
+    pub fn build_state_fn(self, _val: impl Fn() + 'static) -> Self {
+        self
+    }
+    /// Enables the *build state* strategy with the given function, which can
+    /// decide, for each path, whether to generate state for it, or to skip
+    /// it entirely (by returning [`BuildStateOutcome::Skip`]). This is
+    /// useful when you only discover that a path shouldn't exist once you
+    /// start generating it (e.g. an unpublished item).
+    ///
+    /// Skipped paths will be dropped from the build entirely, and skipped
+    /// paths generated at request-time with incremental generation will
+    /// lead to a 404. This should be used instead of `.build_state_fn()`.
+    #[cfg(engine)]
+    pub fn skippable_build_state_fn<S, B, V>(
+        mut self,
+        val: impl GetSkippableBuildStateUserFnType<S, B, V> + Clone + Send + Sync + 'static,
+    ) -> Self
+    where
+        S: Serialize + DeserializeOwned + MakeRx,
+        B: Serialize + DeserializeOwned + Send + Sync + 'static,
+        V: Into<BlamedGeneratorResult<BuildStateOutcome<S>>>,
+    {
+        let template_name = self.get_path();
+        self.get_build_state = Some(Box::new(
+            move |info: StateGeneratorInfo<UnknownStateType>| {
+                let val = val.clone();
+                let template_name = template_name.clone();
+                async move {
+                    let path = info.path.clone();
+                    let user_info = info.change_type::<B>();
+                    let outcome = val
+                        .call(user_info)
+                        .await
+                        .into()
+                        .into_server_result("build_state", template_name.clone())?;
+                    match outcome {
+                        BuildStateOutcome::State(state) => {
+                            let template_state: TemplateState = state.into();
+                            Ok(template_state)
+                        }
+                        BuildStateOutcome::Skip => Err(BuildError::PathSkipped {
+                            template_name,
+                            path,
+                        }
+                        .into()),
+                    }
+                }
+            },
+        ));
+        self
+    }
+    /// Enables the *build state* strategy with the given function, which can
+    /// decide, for each path, whether to generate state for it, or to skip
+    /// it entirely.
+    #[cfg(any(client, doc))]
+    pub fn skippable_build_state_fn(self, _val: impl Fn() + 'static) -> Self {
+        self
+    }
+
+    /// Enables the *build paths* and *build state* strategies together with
+    /// the given function, which should generate every path this template
+    /// will build along with its state. This is useful for things like
+    /// pagination, where the number of pages is only known once all the
+    /// data has been loaded, and where the state of each page is computed
+    /// alongside it.
+    ///
+    /// This should be used instead of both `.build_paths_fn()` and
+    /// `.build_state_fn()`, and the states will be stored as this template's
+    /// extra build state. Any paths not produced by this function (e.g. with
+    /// incremental generation) will lead to a 404.
+    #[cfg(engine)]
+    pub fn paginate_fn<S, V>(
+        mut self,
+        val: impl PaginateUserFnType<S, V> + Clone + Send + Sync + 'static,
+    ) -> Self
+    where
+        S: Serialize + DeserializeOwned + MakeRx,
+        V: Into<GeneratorResult<Vec<(String, S)>>>,
+    {
+        let template_name = self.get_path();
+        self.get_build_paths = Some(Box::new(move || {
+            let val = val.clone();
+            let template_name = template_name.clone();
+            async move {
+                let pages = val
+                    .call()
+                    .await
+                    .into()
+                    .into_server_result("paginate", template_name)?;
+                let mut paths = Vec::new();
+                let mut states = serde_json::Map::new();
+                for (path, state) in pages {
+                    let state: TemplateState = state.into();
+                    paths.push(path.clone());
+                    states.insert(path, state.state);
+                }
+                Ok(BuildPaths {
+                    paths,
+                    extra: TemplateState::from_value(serde_json::Value::Object(states)),
+                })
+            }
+        }));
+        self.get_build_state = Some(Box::new(
+            move |info: StateGeneratorInfo<UnknownStateType>| async move {
+                match info.extra.state.get(&info.path) {
+                    Some(state) => Ok(TemplateState::from_value(state.clone())),
+                    None => Err(ServeError::PageNotFound { path: info.path }.into()),
+                }
+            },
+        ));
+        self
+    }
+    /// Enables the *build paths* and *build state* strategies together with
+    /// the given function, which should generate every path this template
+    /// will build along with its state.
+    #[cfg(any(client, doc))]
+    pub fn paginate_fn(self, _val: impl Fn() + 'static) -> Self {
+        self
+    }
+
+    /// Sets a function that will transform the HTML of each of this template's
+    /// pages after it's been prerendered, whether at build-time or
+    /// request-time, which is useful for things like adding nonces to inline
+    /// scripts. Any errors will be propagated as a
+    /// [`ClientError::TemplateRenderFailed`].
+    ///
+    /// Be careful not to change the structure of the HTML, or hydration will
+    /// fail on the client-side! Note also that this is not applied to the
+    /// document `<head>`, or when rendering directly to a writer.
+    #[cfg(engine)]
+    pub fn transform_html_fn<E>(
+        mut self,
+        val: impl Fn(String) -> Result<String, E> + Send + Sync + 'static,
+    ) -> Self
+    where
+        E: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        self.transform_html = Some(Box::new(move |html| val(html).map_err(|err| err.into())));
+        self
+    }
+    /// Sets a function that will transform the HTML of each of this template's
+    /// pages after it's been prerendered.
+    #[cfg(any(client, doc))]
+    pub fn transform_html_fn(self, _val: impl Fn() + 'static) -> Self {
+        self
+    }
+    /// Sets a function that will render an inline error for this template's
+    /// pages if their views return errors on the engine-side, rather than
+    /// failing the whole build (or request). The view you return will be
+    /// used as the content of the page, so it's best kept simple.
+    ///
+    /// Note that this only applies to prerendering: if the page's view
+    /// fails again on the client-side, the usual error views will be used.
+    #[cfg(engine)]
+    pub fn error_view(
+        mut self,
+        val: impl Fn(Scope, &ClientError) -> View<G> + Send + Sync + 'static,
+    ) -> Self {
+        self.error_view = Some(Box::new(val));
+        self
+    }
+    /// Sets a function that will render an inline error for this template's
+    /// pages if their views return errors on the engine-side, rather than
+    /// failing the whole build (or request).
+    #[cfg(any(client, doc))]
+    pub fn error_view(self, _val: impl Fn() + 'static) -> Self {
+        self
+    }
+    /// Sets a function that will render the body of the 404 page sent for
+    /// paths of this template that its incremental path matcher rejects,
+    /// rather than the body from the app's error views. This will be given
+    /// the path that couldn't be found, and what it renders will be
+    /// interpolated into the app's HTML shell, with the head from the error
+    /// views. Other errors (including `BlamedError::not_found()` from your
+    /// state functions) will still use the error views.
+    ///
+    /// Note that this only applies to the HTML the server sends for initial
+    /// loads: once Perseus has loaded in the browser, and for any
+    /// client-side navigation, the usual error views will be used.
+    #[cfg(engine)]
+    pub fn not_found_fn(
+        mut self,
+        val: impl Fn(Scope, &str) -> View<SsrNode> + Send + Sync + 'static,
+    ) -> Self {
+        self.not_found_view = Some(Box::new(val));
+        self
+    }
+    /// Sets a function that will render the body of the 404 page sent for
+    /// paths of this template that its incremental path matcher rejects.
+    #[cfg(any(client, doc))]
+    pub fn not_found_fn(self, _val: impl Fn() + 'static) -> Self {
+        self
+    }
+
+    /// Sets a default state for this template, which is useful for pages that
+    /// are mostly static, but that need a little state (e.g. a build
+    /// timestamp). This is equivalent to enabling the *build state* strategy
+    /// with a function that always returns the given state, and it will
+    /// also be used if this template is rendered directly without any state
+    /// (e.g. with `.render_to_string()`).
+    #[cfg(engine)]
+    pub fn default_state<S>(mut self, val: S) -> Self
+    where
+        S: Serialize + DeserializeOwned + MakeRx,
+    {
+        let template_state: TemplateState = val.into();
+        self.default_state = Some(template_state.clone());
+        self.get_build_state = Some(Box::new(
+            move |_info: StateGeneratorInfo<UnknownStateType>| {
+                let template_state = template_state.clone();
+                async move { Ok(template_state) }
+            },
+        ));
+        self
+    }
+    /// Sets a default state for this template, which is useful for pages that
+    /// are mostly static, but that need a little state.
+    #[cfg(any(client, doc))]
+    pub fn default_state<S>(self, _val: S) -> Self {
+        self
+    }
+
+    /// Sets a function that will be run once when this template starts being
+    /// built, before its build paths or any build state are generated. This
+    /// is useful for expensive setup shared by all the template's pages,
+    /// like warming a cache, that would otherwise be repeated for every
+    /// path. Any errors will stop the build.
+    ///
+    /// Note that this is *not* run before incremental generation, since that
+    /// happens at request-time.
+    #[cfg(engine)]
+    pub fn build_start_fn<E>(
+        mut self,
+        val: impl Fn() -> Result<(), E> + Send + Sync + 'static,
+    ) -> Self
+    where
+        E: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        self.build_start = Some(Box::new(move || val().map_err(|err| err.into())));
+        self
+    }
+    /// Sets a function that will be run once when this template starts being
+    /// built, before its build paths or any build state are generated.
+    #[cfg(any(client, doc))]
+    pub fn build_start_fn(self, _val: impl Fn() + 'static) -> Self {
+        self
+    }
+
+    /// Enables the *build state* strategy with the given function, which
+    /// generates the states for many paths at once, rather than one at a
+    /// time. This is useful if your state comes from a source that supports
+    /// bulk queries, like a CMS, since it avoids one round trip per path.
+    ///
+    /// At build-time, this will be given the information for every path
+    /// generated by the *build paths* strategy for each locale, and it must
+    /// return one result for each of them, in the same order. Errors are
+    /// reported per-path. At request-time (e.g. for incremental generation or
+    /// revalidation), this will be given one path at a time.
+    ///
+    /// This should be used instead of `.build_state_fn()`.
+    #[cfg(engine)]
+    pub fn build_state_batch_fn<S, B, V>(
+        mut self,
+        val: impl GetBuildStatesBatchUserFnType<S, B, V> + Clone + Send + Sync + 'static,
+    ) -> Self
+    where
+        S: Serialize + DeserializeOwned + MakeRx,
+        B: Serialize + DeserializeOwned + Send + Sync + 'static,
+        V: Into<BlamedGeneratorResult<S>>,
+    {
+        let template_name = self.get_path();
+        let batch_fn = move |infos: Vec<StateGeneratorInfo<UnknownStateType>>| {
+            let val = val.clone();
+            let template_name = template_name.clone();
+            async move {
+                let user_infos = infos
+                    .into_iter()
+                    .map(|info| info.change_type::<B>())
+                    .collect();
+                val.call(user_infos)
+                    .await
+                    .into_iter()
+                    .map(|res| {
+                        let user_state = res
+                            .into()
+                            .into_server_result("build_state", template_name.clone())?;
+                        let template_state: TemplateState = user_state.into();
+                        Ok(template_state)
+                    })
+                    .collect::<Vec<_>>()
+            }
+        };
+        self.get_build_states_batch = Some(Box::new(batch_fn.clone()));
+        let template_name = self.get_path();
+        self.get_build_state = Some(Box::new(
+            move |info: StateGeneratorInfo<UnknownStateType>| {
+                let batch_fn = batch_fn.clone();
+                let template_name = template_name.clone();
+                async move {
+                    match batch_fn(vec![info]).await.pop() {
+                        Some(res) => res,
+                        None => Err(BuildError::BatchLengthMismatch {
+                            template_name,
+                            expected: 1,
+                            found: 0,
+                        }
+                        .into()),
+                    }
+                }
+            },
+        ));
+        self
+    }
+    /// Enables the *build state* strategy with the given function, which
+    /// generates the states for many paths at once, rather than one at a
+    /// time.
+    #[cfg(any(client, doc))]
+    pub fn build_state_batch_fn(self, _val: impl Fn() + 'static) -> Self {
+        self
+    }
+
+    /// Sets a function that will check the state generated by the *build
+    /// state* strategy before it's cached, allowing you to enforce
+    /// invariants (e.g. that a title isn't empty). Any errors will stop the
+    /// build (or the request, for incrementally generated and revalidated
+    /// pages), and will be reported with the path of the offending page.
+    #[cfg(engine)]
+    pub fn validate_state_fn<S, E>(
+        mut self,
+        val: impl Fn(&S) -> Result<(), E> + Send + Sync + 'static,
+    ) -> Self
+    where
+        S: Serialize + DeserializeOwned + MakeRx + 'static,
+        E: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        self.validate_state = Some(Box::new(move |template_state: &TemplateState| {
+            // If the state isn't of the type the user expected, that's a validation failure
+            // too
+            let state = template_state.clone().change_type::<S>().into_concrete()?;
+            val(&state).map_err(|err| err.into())
+        }));
+        self
+    }
+    /// Sets a function that will check the state generated by the *build
+    /// state* strategy before it's cached, allowing you to enforce
+    /// invariants (e.g. that a title isn't empty). Any errors will stop the
+    /// build (or the request, for incrementally generated and revalidated
+    /// pages), and will be reported with the path of the offending page.
+    #[cfg(any(client, doc))]
+    pub fn validate_state_fn(self, _val: impl Fn() + 'static) -> Self {
+        self
+    }
+    /// Memoizes the states generated by the *build state* strategy, so that
+    /// generating the state for the same path (in the same locale) again
+    /// will return the cached state, rather than re-running your function.
+    /// This is useful in development, when you're rebuilding often and your
+    /// build state function does expensive work. The cache can be cleared
+    /// with `.clear_build_state_cache()`.
+    ///
+    /// Since the point of revalidation is to regenerate state, this has no
+    /// effect on templates that use the *revalidation* strategy.
+    #[cfg(engine)]
+    pub fn memoize_build_state(mut self) -> Self {
+        self.build_state_cache = Some(Mutex::new(HashMap::new()));
+        self
+    }
+    /// Memoizes the states generated by the *build state* strategy, so that
+    /// generating the state for the same path (in the same locale) again
+    /// will return the cached state, rather than re-running your function.
+    #[cfg(any(client, doc))]
+    pub fn memoize_build_state(self) -> Self {
+        self
+    }
+    /// Sets the format that will be used to encode this template's state with
+    /// `.encode_state()`, which is useful for storing large states compactly
+    /// in external caches. By default, this is [`StateCodec::Json`].
+    #[cfg(engine)]
+    pub fn state_codec(mut self, val: StateCodec) -> Self {
+        self.state_codec = val;
+        self
+    }
+    /// Sets the format that will be used to encode this template's state with
+    /// `.encode_state()`.
+    #[cfg(any(client, doc))]
+    pub fn state_codec(self, _val: StateCodec) -> Self {
+        self
+    }
+    /// Sets whether or not the server should compress responses for this
+    /// template's pages. By default, this is [`Compression::Auto`], which
+    /// will only compress responses large enough to benefit from it.
+    #[cfg(engine)]
+    pub fn compress(mut self, val: Compression) -> Self {
+        self.compress = val;
+        self
+    }
+    /// Sets whether or not the server should compress responses for this
+    /// template's pages.
+    #[cfg(any(client, doc))]
+    pub fn compress(self, _val: Compression) -> Self {
+        self
+    }
+
+    /// Enables the *request state* strategy with the given function.
+    ///
+    /// Your function will be given the full [`Request`](crate::Request) that
+    /// led to this page being rendered, including its method, URI (and hence
+    /// query string), and headers, so you can make decisions based on things
+    /// like an `Authorization` header or a `?preview=true` parameter.
+    #[cfg(engine)]
+    pub fn request_state_fn<S, B, V>(
+        mut self,
+        val: impl GetRequestStateUserFnType<S, B, V> + Clone + Send + Sync + 'static,
+    ) -> Self
+    where
+        S: Serialize + DeserializeOwned + MakeRx,
+        B: Serialize + DeserializeOwned + Send + Sync + 'static,
+        V: Into<BlamedGeneratorResult<S>>,
+    {
+        let template_name = self.get_path();
+        self.get_request_state = Some(Box::new(
+            move |info: StateGeneratorInfo<UnknownStateType>, req| {
+                let val = val.clone();
+                let template_name = template_name.clone();
+                async move {
+                    let user_info = info.change_type::<B>();
+                    let user_state = val
+                        .call(user_info, req)
+                        .await
+                        .into()
+                        .into_server_result("request_state", template_name)?;
+                    let template_state: TemplateState = user_state.into();
+                    Ok((template_state, ResponseMeta::default()))
+                }
+            },
+        ));
+        self
+    }
+    /// Enables the *request state* strategy with the given function.
     #[cfg(any(client, doc))]
-    pub fn set_headers(self, _val: impl Fn() + 'static) -> Self {
+    pub fn request_state_fn(self, _val: impl Fn() + 'static) -> Self {
         self
     }
-
-    /// Enables the *build paths* strategy with the given function.
+    /// Enables the *request state* strategy with the given function, which
+    /// should return [`ResponseMeta`] alongside the state, allowing it to
+    /// set headers (e.g. `Set-Cookie`) and override the status code of the
+    /// response. This should be used instead of `.request_state_fn()`.
     #[cfg(engine)]
-    pub fn build_paths_fn<V: Into<GeneratorResult<BuildPaths>>>(
+    pub fn request_state_with_meta_fn<S, B, V>(
         mut self,
-        val: impl GetBuildPathsUserFnType<V> + Clone + Send + Sync + 'static,
-    ) -> Self {
+        val: impl GetRequestStateWithMetaUserFnType<S, B, V> + Clone + Send + Sync + 'static,
+    ) -> Self
+    where
+        S: Serialize + DeserializeOwned + MakeRx,
+        B: Serialize + DeserializeOwned + Send + Sync + 'static,
+        V: Into<BlamedGeneratorResult<(S, ResponseMeta)>>,
+    {
         let template_name = self.get_path();
-        self.get_build_paths = Some(Box::new(move || {
-            let val = val.clone();
-            let template_name = template_name.clone();
-            async move {
-                val.call()
-                    .await
-                    .into()
-                    .into_server_result("build_paths", template_name)
-            }
-        }));
+        self.get_request_state = Some(Box::new(
+            move |info: StateGeneratorInfo<UnknownStateType>, req| {
+                let val = val.clone();
+                let template_name = template_name.clone();
+                async move {
+                    let user_info = info.change_type::<B>();
+                    let (user_state, meta) = val
+                        .call(user_info, req)
+                        .await
+                        .into()
+                        .into_server_result("request_state", template_name)?;
+                    let template_state: TemplateState = user_state.into();
+                    Ok((template_state, meta))
+                }
+            },
+        ));
         self
     }
-    /// Enables the *build paths* strategy with the given function.
+    /// Enables the *request state* strategy with the given function, which
+    /// should return [`ResponseMeta`] alongside the state, allowing it to
+    /// set headers (e.g. `Set-Cookie`) and override the status code of the
+    /// response. This should be used instead of `.request_state_fn()`.
     #[cfg(any(client, doc))]
-    pub fn build_paths_fn(self, _val: impl Fn() + 'static) -> Self {
+    pub fn request_state_with_meta_fn(self, _val: impl Fn() + 'static) -> Self {
         self
     }
-
-    /// Enables the *incremental generation* strategy.
+    /// Sets a function that generates state for each request as a stream of
+    /// chunks, each of which will be rendered through this template's view
+    /// separately by `.render_request_stream()`. This allows you to begin
+    /// sending the HTML for very large pages (e.g. long listings) before all
+    /// their state has been computed.
+    ///
+    /// Your function should return a [`Stream`](futures::Stream) of states
+    /// (or `Result`s of them), like an async generator. Errors in individual
+    /// chunks will be returned in place of those chunks.
     #[cfg(engine)]
-    pub fn incremental_generation(mut self) -> Self {
-        self.incremental_generation = true;
+    pub fn stream_request_fn<S, B, V, St>(
+        mut self,
+        val: impl StreamRequestStateUserFnType<S, B, V, St> + Send + Sync + 'static,
+    ) -> Self
+    where
+        S: Serialize + DeserializeOwned + MakeRx,
+        B: Serialize + DeserializeOwned + Send + Sync + 'static,
+        V: Into<BlamedGeneratorResult<S>>,
+        St: futures::Stream<Item = V> + Send + 'static,
+    {
+        use futures::StreamExt;
+
+        let template_name = self.get_path();
+        self.stream_request_state = Some(Box::new(
+            move |info: StateGeneratorInfo<UnknownStateType>, req| {
+                let template_name = template_name.clone();
+                val(info.change_type::<B>(), req)
+                    .map(move |chunk| {
+                        let user_state = chunk
+                            .into()
+                            .into_server_result("stream_request_state", template_name.clone())?;
+                        let template_state: TemplateState = user_state.into();
+                        Ok(template_state)
+                    })
+                    .boxed()
+            },
+        ));
         self
     }
-    /// Enables the *incremental generation* strategy.
+    /// Sets a function that generates state for each request as a stream of
+    /// chunks.
     #[cfg(any(client, doc))]
-    pub fn incremental_generation(self) -> Self {
+    pub fn stream_request_fn(self, _val: impl Fn() + 'static) -> Self {
         self
     }
-
-    /// Enables the *build state* strategy with the given function.
+    /// Enables the *request state* strategy with the given function, which
+    /// can decide, for each request, whether to render the page with some
+    /// state, or to redirect the user elsewhere (by returning
+    /// [`RenderOutcome::Redirect`]). Redirects will be sent with a *301* or
+    /// *302* status code and a `Location` header, and the page won't be
+    /// rendered at all. On subsequent loads, the app shell will be told where
+    /// to go, and will navigate there itself. This should be used instead of
+    /// `.request_state_fn()`.
     #[cfg(engine)]
-    pub fn build_state_fn<S, B, V>(
+    pub fn redirecting_request_state_fn<S, B, V>(
         mut self,
-        val: impl GetBuildStateUserFnType<S, B, V> + Clone + Send + Sync + 'static,
+        val: impl GetRedirectingRequestStateUserFnType<S, B, V> + Clone + Send + Sync + 'static,
     ) -> Self
     where
         S: Serialize + DeserializeOwned + MakeRx,
         B: Serialize + DeserializeOwned + Send + Sync + 'static,
-        V: Into<BlamedGeneratorResult<S>>,
+        V: Into<BlamedGeneratorResult<RenderOutcome<S>>>,
     {
         let template_name = self.get_path();
-        self.get_build_state = Some(Box::new(
-            move |info: StateGeneratorInfo<UnknownStateType>| {
+        self.get_request_state = Some(Box::new(
+            move |info: StateGeneratorInfo<UnknownStateType>, req| {
                 let val = val.clone();
                 let template_name = template_name.clone();
                 async move {
                     let user_info = info.change_type::<B>();
-                    let user_state = val
-                        .call(user_info)
+                    let outcome = val
+                        .call(user_info, req)
                         .await
                         .into()
-                        .into_server_result("build_state", template_name)?;
-                    let template_state: TemplateState = user_state.into();
-                    Ok(template_state)
+                        .into_server_result("request_state", template_name.clone())?;
+                    match outcome {
+                        RenderOutcome::Render(state) => {
+                            let template_state: TemplateState = state.into();
+                            Ok((template_state, ResponseMeta::default()))
+                        }
+                        RenderOutcome::Redirect { to, permanent } => {
+                            let meta = ResponseMeta::redirect(&to, permanent).map_err(|err| {
+                                ServerError::RenderFnFailed {
+                                    fn_name: "request_state".to_string(),
+                                    template_name,
+                                    blame: ErrorBlame::Server(None),
+                                    source: Box::new(err),
+                                }
+                            })?;
+                            Ok((TemplateState::empty(), meta))
+                        }
+                    }
                 }
             },
         ));
         self
     }
-    /// Enables the *build state* strategy with the given function.
+    /// Enables the *request state* strategy with the given function, which
+    /// can decide, for each request, whether to render the page with some
+    /// state, or to redirect the user elsewhere.
     #[cfg(any(client, doc))]
-    pub fn build_state_fn(self, _val: impl Fn() + 'static) -> Self {
+    pub fn redirecting_request_state_fn(self, _val: impl Fn() + 'static) -> Self {
         self
     }
-
-    /// Enables the *request state* strategy with the given function.
+    /// Enables the *request state* strategy with the given function, which
+    /// can decide, for each request, whether to generate state or to just
+    /// use the page's build state (by returning
+    /// [`RequestStateOutcome::UseBuildState`]). This is useful when request
+    /// state is expensive to generate, but only needed for some requests
+    /// (e.g. those from logged-in users).
+    ///
+    /// This should be used instead of `.request_state_fn()`, and it requires
+    /// the *build state* strategy. If both states are used for a request,
+    /// they'll be amalgamated as usual.
     #[cfg(engine)]
-    pub fn request_state_fn<S, B, V>(
+    pub fn conditional_request_state_fn<S, B, V>(
         mut self,
-        val: impl GetRequestStateUserFnType<S, B, V> + Clone + Send + Sync + 'static,
+        val: impl GetConditionalRequestStateUserFnType<S, B, V> + Clone + Send + Sync + 'static,
     ) -> Self
     where
         S: Serialize + DeserializeOwned + MakeRx,
         B: Serialize + DeserializeOwned + Send + Sync + 'static,
-        V: Into<BlamedGeneratorResult<S>>,
+        V: Into<BlamedGeneratorResult<RequestStateOutcome<S>>>,
     {
         let template_name = self.get_path();
         self.get_request_state = Some(Box::new(
@@ -166,26 +1212,131 @@ impl<G: Html> TemplateInner<G> {
                 let template_name = template_name.clone();
                 async move {
                     let user_info = info.change_type::<B>();
-                    let user_state = val
+                    let outcome = val
                         .call(user_info, req)
                         .await
                         .into()
                         .into_server_result("request_state", template_name)?;
-                    let template_state: TemplateState = user_state.into();
-                    Ok(template_state)
+                    // An empty request state means the build state will be the only defined state
+                    // when the two are combined
+                    let template_state: TemplateState = match outcome {
+                        RequestStateOutcome::State(state) => state.into(),
+                        RequestStateOutcome::UseBuildState => TemplateState::empty(),
+                    };
+                    Ok((template_state, ResponseMeta::default()))
                 }
             },
         ));
+        self.request_state_falls_back = true;
         self
     }
-    /// Enables the *request state* strategy with the given function.
+    /// Enables the *request state* strategy with the given function, which
+    /// can decide, for each request, whether to generate state or to just
+    /// use the page's build state.
     #[cfg(any(client, doc))]
-    pub fn request_state_fn(self, _val: impl Fn() + 'static) -> Self {
+    pub fn conditional_request_state_fn(self, _val: impl Fn() + 'static) -> Self {
+        self
+    }
+
+    /// Marks the pages of this template as requiring authorization, using the
+    /// given function to check each request for them (e.g. by inspecting its
+    /// `Authorization` header). Any requests for which this returns `false`
+    /// will receive a 401 error, without your request state function being
+    /// run.
+    ///
+    /// This requires the *request state* strategy, since static pages can't
+    /// be protected, and building your app will fail if it isn't used.
+    #[cfg(engine)]
+    pub fn requires_auth(mut self, val: impl Fn(&Request) -> bool + Send + Sync + 'static) -> Self {
+        self.auth_check = Some(Box::new(val));
+        self
+    }
+    /// Marks the pages of this template as requiring authorization, using the
+    /// given function to check each request for them (e.g. by inspecting its
+    /// `Authorization` header). Any requests for which this returns `false`
+    /// will receive a 401 error, without your request state function being
+    /// run.
+    ///
+    /// This requires the *request state* strategy, since static pages can't
+    /// be protected, and building your app will fail if it isn't used.
+    #[cfg(any(client, doc))]
+    pub fn requires_auth(self, _val: impl Fn() + 'static) -> Self {
+        self
+    }
+    /// Sets the maximum amount of time that generating request state for this
+    /// template's pages may take. If your request state function takes any
+    /// longer (e.g. because of a slow upstream API), it will be abandoned,
+    /// and the request will fail with a *504 Gateway Timeout*.
+    ///
+    /// This uses Tokio's timer, so your server must run on a Tokio runtime
+    /// with the timer enabled (which all the official server integrations
+    /// do).
+    #[cfg(engine)]
+    pub fn request_timeout(mut self, val: std::time::Duration) -> Self {
+        self.request_timeout = Some(val);
+        self
+    }
+    /// Sets the maximum amount of time that generating request state for this
+    /// template's pages may take.
+    #[cfg(any(client, doc))]
+    pub fn request_timeout(self, _val: std::time::Duration) -> Self {
+        self
+    }
+    /// Sets the HTTP methods (e.g. `GET`) that requests for this template's
+    /// pages may use, replacing the default of `GET` and `HEAD`. Requests
+    /// with any other method will receive a *405 Method Not Allowed* error,
+    /// without your request state function being run.
+    #[cfg(engine)]
+    pub fn allowed_methods<M: Into<String>>(mut self, val: impl IntoIterator<Item = M>) -> Self {
+        self.allowed_methods = val
+            .into_iter()
+            .map(|method| method.into().to_uppercase())
+            .collect();
+        self
+    }
+    /// Sets the HTTP methods (e.g. `GET`) that requests for this template's
+    /// pages may use, replacing the default of `GET` and `HEAD`.
+    #[cfg(any(client, doc))]
+    pub fn allowed_methods<M: Into<String>>(self, _val: impl IntoIterator<Item = M>) -> Self {
+        self
+    }
+    /// Adds a middleware function that will be run on each request for this
+    /// template's pages before request state is generated, which is useful
+    /// for cross-cutting behavior like logging or locale detection.
+    /// Middleware will be run in the order it was added, and it can modify
+    /// the request (e.g. by adding headers), which later middleware and your
+    /// request state function will see.
+    ///
+    /// If a middleware function returns an error, the request will fail with
+    /// it immediately, and no further middleware will be run. (You can use a
+    /// [`BlamedError`](crate::errors::BlamedError) to blame the client for
+    /// this.)
+    #[cfg(engine)]
+    pub fn request_middleware<E>(
+        mut self,
+        val: impl Fn(&mut Request) -> Result<(), BlamedError<E>> + Send + Sync + 'static,
+    ) -> Self
+    where
+        E: Into<Box<dyn std::error::Error + Send + Sync + 'static>> + Send + Sync,
+    {
+        self.request_middleware.push(Box::new(move |req| {
+            val(req).map_err(|err| err.into_boxed())
+        }));
+        self
+    }
+    /// Adds a middleware function that will be run on each request for this
+    /// template's pages before request state is generated.
+    #[cfg(any(client, doc))]
+    pub fn request_middleware(self, _val: impl Fn() + 'static) -> Self {
         self
     }
 
     /// Enables the *revalidation* strategy (logic variant) with the given
     /// function.
+    ///
+    /// Your function will be given the [`Request`](crate::Request) that
+    /// triggered the check, so you could, for example, force revalidation
+    /// whenever a `?flush=1` query parameter is present.
     #[cfg(engine)]
     pub fn should_revalidate_fn<B, V>(
         mut self,
@@ -197,7 +1348,7 @@ impl<G: Html> TemplateInner<G> {
     {
         let template_name = self.get_path();
         self.should_revalidate = Some(Box::new(
-            move |info: StateGeneratorInfo<UnknownStateType>, req| {
+            move |info: StateGeneratorInfo<UnknownStateType>, req, _cached_state| {
                 let val = val.clone();
                 let template_name = template_name.clone();
                 async move {
@@ -209,6 +1360,7 @@ impl<G: Html> TemplateInner<G> {
                 }
             },
         ));
+        self.should_revalidate_uses_state = false;
         self
     }
     /// Enables the *revalidation* strategy (logic variant) with the given
@@ -217,6 +1369,113 @@ impl<G: Html> TemplateInner<G> {
     pub fn should_revalidate_fn(self, _val: impl Fn() + 'static) -> Self {
         self
     }
+    /// Enables the *revalidation* strategy (logic variant) with the given
+    /// function, which will also be given the state the page was last
+    /// generated with. This allows you to, for example, only revalidate
+    /// when some upstream version number has changed.
+    ///
+    /// This requires the *build state* strategy, since there will otherwise be
+    /// no state to compare against.
+    #[cfg(engine)]
+    pub fn should_revalidate_with_state_fn<S, B, V>(
+        mut self,
+        val: impl ShouldRevalidateWithStateUserFnType<S, B, V> + Clone + Send + Sync + 'static,
+    ) -> Self
+    where
+        S: Serialize + DeserializeOwned + MakeRx + Send + Sync + 'static,
+        B: Serialize + DeserializeOwned + Send + Sync + 'static,
+        V: Into<BlamedGeneratorResult<bool>>,
+    {
+        let template_name = self.get_path();
+        self.should_revalidate = Some(Box::new(
+            move |info: StateGeneratorInfo<UnknownStateType>, req, cached_state: TemplateState| {
+                let val = val.clone();
+                let template_name = template_name.clone();
+                async move {
+                    let user_cached_state = match cached_state.change_type::<S>().into_concrete() {
+                        Ok(state) => state,
+                        Err(err) => {
+                            return Err(ClientError::InvariantError(
+                                ClientInvariantError::InvalidState { source: err },
+                            )
+                            .into())
+                        }
+                    };
+                    let user_info = info.change_type::<B>();
+                    val.call(user_info, req, user_cached_state)
+                        .await
+                        .into()
+                        .into_server_result("should_revalidate", template_name)
+                }
+            },
+        ));
+        self.should_revalidate_uses_state = true;
+        self
+    }
+    /// Enables the *revalidation* strategy (logic variant) with the given
+    /// function, which will also be given the state the page was last
+    /// generated with. This allows you to, for example, only revalidate
+    /// when some upstream version number has changed.
+    ///
+    /// This requires the *build state* strategy, since there will otherwise be
+    /// no state to compare against.
+    #[cfg(any(client, doc))]
+    pub fn should_revalidate_with_state_fn(self, _val: impl Fn() + 'static) -> Self {
+        self
+    }
+    /// Sets a function that will be given the state a page was last generated
+    /// with whenever it revalidates, and that should return the updated
+    /// state. This will be used instead of running `get_build_state` again,
+    /// which is useful when only a small part of the state (e.g. a comment
+    /// count) needs to be refreshed.
+    ///
+    /// This requires the *revalidation* strategy, and building your app will
+    /// fail if it isn't used.
+    #[cfg(engine)]
+    pub fn revalidate_state_fn<S, B, V>(
+        mut self,
+        val: impl RevalidateStateUserFnType<S, B, V> + Clone + Send + Sync + 'static,
+    ) -> Self
+    where
+        S: Serialize + DeserializeOwned + MakeRx + Send + Sync + 'static,
+        B: Serialize + DeserializeOwned + Send + Sync + 'static,
+        V: Into<BlamedGeneratorResult<S>>,
+    {
+        let template_name = self.get_path();
+        self.revalidate_state = Some(Box::new(
+            move |info: StateGeneratorInfo<UnknownStateType>, old_state: TemplateState| {
+                let val = val.clone();
+                let template_name = template_name.clone();
+                async move {
+                    let user_old_state = match old_state.change_type::<S>().into_concrete() {
+                        Ok(state) => state,
+                        Err(err) => {
+                            return Err(ClientError::InvariantError(
+                                ClientInvariantError::InvalidState { source: err },
+                            )
+                            .into())
+                        }
+                    };
+                    let user_info = info.change_type::<B>();
+                    let user_state = val
+                        .call(user_info, user_old_state)
+                        .await
+                        .into()
+                        .into_server_result("revalidate_state", template_name)?;
+                    let template_state: TemplateState = user_state.into();
+                    Ok(template_state)
+                }
+            },
+        ));
+        self
+    }
+    /// Sets a function that will be given the state a page was last generated
+    /// with whenever it revalidates, and that should return the updated
+    /// state. This will be used instead of running `get_build_state` again.
+    #[cfg(any(client, doc))]
+    pub fn revalidate_state_fn(self, _val: impl Fn() + 'static) -> Self {
+        self
+    }
 
     /// Enables the *revalidation* strategy (time variant). This takes a time
     /// string of a form like `1w` for one week.
@@ -229,6 +1488,12 @@ impl<G: Html> TemplateInner<G> {
     ///    - M: month (30 days used here, 12M ≠ 1y!),
     ///    - y: year (365 days always, leap years ignored, if you want them add
     ///      them as days)
+    ///
+    /// These can be combined, as in `1h30m`. You can also provide a
+    /// [`std::time::Duration`] directly. An invalid time string (e.g. one
+    /// with no number before an indicator, or a trailing number with no
+    /// indicator) will cause a panic when the template is defined, so typos
+    /// will be caught as soon as you try to build your app.
     #[cfg(engine)]
     pub fn revalidate_after<I: PerseusDuration>(mut self, val: I) -> Self {
         let computed_duration = match val.into_computed() {
@@ -251,10 +1516,87 @@ impl<G: Html> TemplateInner<G> {
     ///    - M: month (30 days used here, 12M ≠ 1y!),
     ///    - y: year (365 days always, leap years ignored, if you want them add
     ///      them as days)
+    ///
+    /// These can be combined, as in `1h30m`. You can also provide a
+    /// [`std::time::Duration`] directly. An invalid time string (e.g. one
+    /// with no number before an indicator, or a trailing number with no
+    /// indicator) will cause a panic when the template is defined, so typos
+    /// will be caught as soon as you try to build your app.
     #[cfg(any(client, doc))]
     pub fn revalidate_after<I: PerseusDuration>(self, _val: I) -> Self {
         self
     }
+    /// Enables the *revalidation* strategy (time variant) on a schedule given
+    /// by a standard five-field cron expression (minute, hour, day of month,
+    /// month, day of week), like `0 3 * * *` for 3am every day. Each field
+    /// may be `*`, a number, a range like `1-5`, a list like `1,3,5`, or any
+    /// of these with a step like `*/15`. All times are in UTC.
+    ///
+    /// The next request after each scheduled time will lead to a
+    /// revalidation. If this is used alongside `.revalidate_after()`, the
+    /// fixed interval will take precedence. An invalid expression will cause
+    /// an error when you try to build your app.
+    #[cfg(engine)]
+    pub fn revalidate_cron(mut self, val: &str) -> Self {
+        self.revalidate_cron = Some(val.to_string());
+        self
+    }
+    /// Enables the *revalidation* strategy (time variant) on a schedule given
+    /// by a standard five-field cron expression (minute, hour, day of month,
+    /// month, day of week), like `0 3 * * *` for 3am every day. Each field
+    /// may be `*`, a number, a range like `1-5`, a list like `1,3,5`, or any
+    /// of these with a step like `*/15`. All times are in UTC.
+    #[cfg(any(client, doc))]
+    pub fn revalidate_cron(self, _val: &str) -> Self {
+        self
+    }
+    /// Sets whether or not pages of this template that need revalidation may
+    /// be served stale while they're revalidated in the background
+    /// (*stale-while-revalidate*), rather than making requests wait for
+    /// revalidation. This is reported through `.revalidation_policy()` for
+    /// integrations and external caches to act on.
+    ///
+    /// This requires the *revalidation* strategy, and building your app will
+    /// fail if it isn't used.
+    #[cfg(engine)]
+    pub fn stale_while_revalidate(mut self, val: bool) -> Self {
+        self.stale_while_revalidate = val;
+        self
+    }
+    /// Sets whether or not pages of this template that need revalidation may
+    /// be served stale while they're revalidated in the background
+    /// (*stale-while-revalidate*), rather than making requests wait for
+    /// revalidation.
+    #[cfg(any(client, doc))]
+    pub fn stale_while_revalidate(self, _val: bool) -> Self {
+        self
+    }
+
+    /// Applies the given [`TemplateDefaults`] to this template, filling in any
+    /// values this template hasn't set itself. Since defaults can depend on
+    /// which strategies the template uses, this should be called after all
+    /// your other template settings.
+    #[cfg(engine)]
+    pub fn apply_defaults(mut self, defaults: &TemplateDefaults) -> Self {
+        if self.revalidate_after.is_none()
+            && self.revalidate_cron.is_none()
+            && self.uses_build_state()
+        {
+            self.revalidate_after = defaults.revalidate_after.clone();
+        }
+        if self.cache_control.is_none() {
+            self.cache_control = defaults.cache_control.clone();
+        }
+        self
+    }
+    /// Applies the given [`TemplateDefaults`] to this template, filling in any
+    /// values this template hasn't set itself. Since defaults can depend on
+    /// which strategies the template uses, this should be called after all
+    /// your other template settings.
+    #[cfg(any(client, doc))]
+    pub fn apply_defaults(self, _defaults: &TemplateDefaults) -> Self {
+        self
+    }
 
     /// Enables state amalgamation with the given function. State amalgamation
     /// allows you to have one template generate state at both build time
@@ -262,6 +1604,10 @@ impl<G: Html> TemplateInner<G> {
     /// rationalizing the two into one single state to be sent to the client,
     /// and this will be run just after the request state function
     /// completes.
+    ///
+    /// If a template generates both build and request state without an
+    /// amalgamation function, the request state will be used, since it's more
+    /// personalized and more recent.
     #[cfg(engine)]
     pub fn amalgamate_states_fn<S, B, V>(
         mut self,
@@ -280,22 +1626,27 @@ impl<G: Html> TemplateInner<G> {
                 let val = val.clone();
                 let template_name = template_name.clone();
                 async move {
-                    // Amalgamation logic will only be called if both states are indeed defined
+                    // Amalgamation logic will only be called if both states are indeed defined,
+                    // but we still return an error if either is of the wrong type
                     let typed_build_state = build_state.change_type::<S>();
                     let user_build_state = match typed_build_state.into_concrete() {
                         Ok(state) => state,
-                        Err(err) => panic!(
-                            "unrecoverable error in state amalgamation parameter derivation: {:#?}",
-                            err
-                        ),
+                        Err(err) => {
+                            return Err(ClientError::InvariantError(
+                                ClientInvariantError::InvalidState { source: err },
+                            )
+                            .into())
+                        }
                     };
                     let typed_request_state = request_state.change_type::<S>();
                     let user_request_state = match typed_request_state.into_concrete() {
                         Ok(state) => state,
-                        Err(err) => panic!(
-                            "unrecoverable error in state amalgamation parameter derivation: {:#?}",
-                            err
-                        ),
+                        Err(err) => {
+                            return Err(ClientError::InvariantError(
+                                ClientInvariantError::InvalidState { source: err },
+                            )
+                            .into())
+                        }
                     };
                     let user_info = info.change_type::<B>();
                     let user_state = val
@@ -320,6 +1671,77 @@ impl<G: Html> TemplateInner<G> {
     pub fn amalgamate_states_fn(self, _val: impl Fn() + 'static) -> Self {
         self
     }
+    /// Enables state amalgamation with the given function, exactly as with
+    /// `.amalgamate_states_fn()`, except that the build state and request
+    /// state may be of different types to each other, and to the state your
+    /// view takes. This is useful when your build state is just a
+    /// lightweight summary, and your request state is much richer: the
+    /// function you provide here should convert the two into the state your
+    /// view will be rendered with.
+    ///
+    /// Since your view will only ever be given the amalgamated state, this
+    /// can't be used with `.conditional_request_state_fn()`, as the build
+    /// state would sometimes be rendered on its own.
+    #[cfg(engine)]
+    pub fn amalgamate_mixed_states_fn<S, BS, RS, B, V>(
+        mut self,
+        val: impl AmalgamateMixedStatesUserFnType<S, BS, RS, B, V> + Clone + Send + Sync + 'static,
+    ) -> Self
+    where
+        S: Serialize + DeserializeOwned + MakeRx + Send + Sync + 'static,
+        BS: Serialize + DeserializeOwned + Send + Sync + 'static,
+        RS: Serialize + DeserializeOwned + Send + Sync + 'static,
+        B: Serialize + DeserializeOwned + Send + Sync + 'static,
+        V: Into<BlamedGeneratorResult<S>>,
+    {
+        let template_name = self.get_path();
+        self.amalgamate_states = Some(Box::new(
+            move |info: StateGeneratorInfo<UnknownStateType>,
+                  build_state: TemplateState,
+                  request_state: TemplateState| {
+                let val = val.clone();
+                let template_name = template_name.clone();
+                async move {
+                    let user_build_state = match build_state.change_type::<BS>().into_concrete() {
+                        Ok(state) => state,
+                        Err(err) => {
+                            return Err(ClientError::InvariantError(
+                                ClientInvariantError::InvalidState { source: err },
+                            )
+                            .into())
+                        }
+                    };
+                    let user_request_state = match request_state.change_type::<RS>().into_concrete()
+                    {
+                        Ok(state) => state,
+                        Err(err) => {
+                            return Err(ClientError::InvariantError(
+                                ClientInvariantError::InvalidState { source: err },
+                            )
+                            .into())
+                        }
+                    };
+                    let user_info = info.change_type::<B>();
+                    let user_state = val
+                        .call(user_info, user_build_state, user_request_state)
+                        .await
+                        .into()
+                        .into_server_result("amalgamate_states", template_name)?;
+                    let template_state: TemplateState = user_state.into();
+                    Ok(template_state)
+                }
+            },
+        ));
+        self
+    }
+    /// Enables state amalgamation with the given function, exactly as with
+    /// `.amalgamate_states_fn()`, except that the build state and request
+    /// state may be of different types to each other, and to the state your
+    /// view takes.
+    #[cfg(any(client, doc))]
+    pub fn amalgamate_mixed_states_fn(self, _val: impl Fn() + 'static) -> Self {
+        self
+    }
     /// Allow the building of this page's templates to be rescheduled from
     /// build-tim to request-time.
     ///
@@ -340,3 +1762,38 @@ impl<G: Html> TemplateInner<G> {
         self
     }
 }
+
+#[cfg(all(test, engine))]
+mod tests {
+    use crate::state::StateGeneratorInfo;
+    use crate::template::core::test_utils::*;
+    use crate::template::{Template, TemplateDefaults};
+    use sycamore::web::SsrNode;
+
+    #[test]
+    fn apply_defaults_fills_unset_values() {
+        let defaults = TemplateDefaults::new()
+            .revalidate_after("1h")
+            .cache_control("public, max-age=3600");
+        let build_state =
+            |info: StateGeneratorInfo<()>| async move { TestState { title: info.path } };
+
+        let inheriting = Template::<SsrNode>::build("test")
+            .build_state_fn(build_state)
+            .apply_defaults(&defaults)
+            .build();
+        assert!(inheriting.revalidates_with_time());
+        assert_eq!(inheriting.cache_control_header(), "public, max-age=3600");
+
+        let explicit = Template::<SsrNode>::build("test")
+            .build_state_fn(build_state)
+            .revalidate_after("1w")
+            .apply_defaults(&defaults)
+            .build();
+        let interval = explicit.get_revalidate_interval().unwrap();
+        // The explicit interval should push the revalidation time well past an hour
+        let timestamp =
+            chrono::DateTime::parse_from_rfc3339(&interval.compute_timestamp()).unwrap();
+        assert!(timestamp > chrono::Utc::now() + chrono::Duration::days(6));
+    }
+}
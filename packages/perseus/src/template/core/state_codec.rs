@@ -0,0 +1,228 @@
+#[cfg(engine)]
+use serde_json::{Map, Number, Value};
+
+/// The format used to encode a template's state with `.encode_state()`,
+/// which can be set with `.state_codec()`. This doesn't affect the format
+/// Perseus uses internally, it's provided for storing state in external
+/// caches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum StateCodec {
+    /// State will be encoded as JSON, exactly as with `.serialize_state()`.
+    #[default]
+    Json,
+    /// State will be encoded in a compact, self-describing binary format,
+    /// which is usually much smaller than JSON for large states (especially
+    /// those with lots of numbers). This can encode any state that can be
+    /// represented as JSON.
+    Binary,
+}
+
+// Tags for each kind of value in the binary format
+#[cfg(engine)]
+const NULL: u8 = 0;
+#[cfg(engine)]
+const FALSE: u8 = 1;
+#[cfg(engine)]
+const TRUE: u8 = 2;
+#[cfg(engine)]
+const UINT: u8 = 3;
+#[cfg(engine)]
+const NEG_INT: u8 = 4;
+#[cfg(engine)]
+const FLOAT: u8 = 5;
+#[cfg(engine)]
+const STRING: u8 = 6;
+#[cfg(engine)]
+const ARRAY: u8 = 7;
+#[cfg(engine)]
+const OBJECT: u8 = 8;
+
+#[cfg(engine)]
+impl StateCodec {
+    /// Encodes the given state with this codec.
+    pub(crate) fn encode(&self, state: &Value) -> Vec<u8> {
+        match self {
+            Self::Json => state.to_string().into_bytes(),
+            Self::Binary => {
+                let mut bytes = Vec::new();
+                encode_value(state, &mut bytes);
+                bytes
+            }
+        }
+    }
+    /// Decodes the given bytes with this codec, returning the reason they
+    /// couldn't be decoded if they're invalid.
+    pub(crate) fn decode(&self, bytes: &[u8]) -> Result<Value, String> {
+        match self {
+            Self::Json => serde_json::from_slice(bytes).map_err(|err| err.to_string()),
+            Self::Binary => {
+                let mut decoder = Decoder { bytes, pos: 0 };
+                let value = decoder.value()?;
+                if decoder.pos != bytes.len() {
+                    return Err("trailing bytes after state".to_string());
+                }
+                Ok(value)
+            }
+        }
+    }
+}
+
+#[cfg(engine)]
+fn encode_varint(mut val: u64, bytes: &mut Vec<u8>) {
+    while val >= 0x80 {
+        bytes.push((val as u8) | 0x80);
+        val >>= 7;
+    }
+    bytes.push(val as u8);
+}
+
+#[cfg(engine)]
+fn encode_str(val: &str, bytes: &mut Vec<u8>) {
+    encode_varint(val.len() as u64, bytes);
+    bytes.extend_from_slice(val.as_bytes());
+}
+
+#[cfg(engine)]
+fn encode_value(val: &Value, bytes: &mut Vec<u8>) {
+    match val {
+        Value::Null => bytes.push(NULL),
+        Value::Bool(false) => bytes.push(FALSE),
+        Value::Bool(true) => bytes.push(TRUE),
+        Value::Number(num) => {
+            if let Some(num) = num.as_u64() {
+                bytes.push(UINT);
+                encode_varint(num, bytes);
+            } else if let Some(num) = num.as_i64() {
+                // This must be negative, so we store `-num - 1`, which fits in a `u64`
+                bytes.push(NEG_INT);
+                encode_varint(!(num as u64), bytes);
+            } else {
+                bytes.push(FLOAT);
+                // Numbers from JSON are always representable as `f64`
+                bytes.extend_from_slice(&num.as_f64().unwrap().to_le_bytes());
+            }
+        }
+        Value::String(val) => {
+            bytes.push(STRING);
+            encode_str(val, bytes);
+        }
+        Value::Array(vals) => {
+            bytes.push(ARRAY);
+            encode_varint(vals.len() as u64, bytes);
+            for val in vals {
+                encode_value(val, bytes);
+            }
+        }
+        Value::Object(map) => {
+            bytes.push(OBJECT);
+            encode_varint(map.len() as u64, bytes);
+            for (key, val) in map {
+                encode_str(key, bytes);
+                encode_value(val, bytes);
+            }
+        }
+    }
+}
+
+#[cfg(engine)]
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+#[cfg(engine)]
+impl<'a> Decoder<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| "unexpected end of state".to_string())?;
+        let taken = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(taken)
+    }
+    fn varint(&mut self) -> Result<u64, String> {
+        let mut val = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.take(1)?[0];
+            val |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(val);
+            }
+        }
+        Err("integer too large".to_string())
+    }
+    fn len(&mut self) -> Result<usize, String> {
+        let len = self.varint()?;
+        // No item takes up less than a byte, so this stops us allocating huge amounts of memory for
+        // corrupted lengths
+        if len > (self.bytes.len() - self.pos) as u64 {
+            return Err("length exceeds remaining state".to_string());
+        }
+        Ok(len as usize)
+    }
+    fn string(&mut self) -> Result<String, String> {
+        let len = self.len()?;
+        let bytes = self.take(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|err| err.to_string())
+    }
+    fn value(&mut self) -> Result<Value, String> {
+        let tag = self.take(1)?[0];
+        Ok(match tag {
+            NULL => Value::Null,
+            FALSE => Value::Bool(false),
+            TRUE => Value::Bool(true),
+            UINT => Value::Number(self.varint()?.into()),
+            NEG_INT => Value::Number((!self.varint()? as i64).into()),
+            FLOAT => {
+                let bytes = self.take(8)?.try_into().unwrap();
+                let num = f64::from_le_bytes(bytes);
+                Value::Number(Number::from_f64(num).ok_or("invalid float")?)
+            }
+            STRING => Value::String(self.string()?),
+            ARRAY => {
+                let len = self.len()?;
+                let mut vals = Vec::with_capacity(len);
+                for _ in 0..len {
+                    vals.push(self.value()?);
+                }
+                Value::Array(vals)
+            }
+            OBJECT => {
+                let len = self.len()?;
+                let mut map = Map::new();
+                for _ in 0..len {
+                    let key = self.string()?;
+                    map.insert(key, self.value()?);
+                }
+                Value::Object(map)
+            }
+            _ => return Err(format!("invalid tag {}", tag)),
+        })
+    }
+}
+
+#[cfg(all(test, engine))]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn binary_codec_round_trips_compactly() {
+        let values: Vec<i64> = (-500..1500).map(|i| i * 37).collect();
+        let state = json!({
+            "title": "Lots of numbers",
+            "values": values,
+            "ratio": 0.75,
+            "flags": [true, null, false],
+        });
+        let encoded = StateCodec::Binary.encode(&state);
+        assert_eq!(StateCodec::Binary.decode(&encoded).unwrap(), state);
+        assert!(encoded.len() < StateCodec::Json.encode(&state).len());
+
+        assert!(StateCodec::Binary
+            .decode(&encoded[..encoded.len() - 1])
+            .is_err());
+        assert!(StateCodec::Binary.decode(&[42]).is_err());
+    }
+}
@@ -4,12 +4,14 @@ use super::TemplateInner;
 #[cfg(engine)]
 use crate::errors::*;
 use crate::{
+    errors::ClientError,
     reactor::Reactor,
     state::{AnyFreeze, MakeRx, MakeUnrx, UnreactiveState},
 };
 #[cfg(engine)]
 use http::HeaderMap;
 use serde::{de::DeserializeOwned, Serialize};
+use std::convert::Infallible;
 use sycamore::prelude::BoundedScope;
 use sycamore::prelude::{create_child_scope, create_ref};
 #[cfg(engine)]
@@ -27,7 +29,7 @@ impl<G: Html> TemplateInner<G> {
     /// The closure wrapping this performs will automatically handle suspense
     /// state.
     // Generics are swapped here for nicer manual specification
-    pub fn view_with_state<I, F>(mut self, val: F) -> Self
+    pub fn view_with_state<I, F>(self, val: F) -> Self
     where
         // The state is made reactive on the child
         F: for<'app, 'child> Fn(BoundedScope<'app, 'child>, &'child I) -> View<G>
@@ -37,6 +39,27 @@ impl<G: Html> TemplateInner<G> {
         I: MakeUnrx + AnyFreeze + Clone,
         I::Unrx: MakeRx<Rx = I> + Serialize + DeserializeOwned + Send + Sync + Clone + 'static,
     {
+        self.view_with_state_fallible(move |cx, state: &I| Ok::<_, Infallible>(val(cx, state)))
+    }
+    /// Sets the template rendering function to use, if the template takes
+    /// state and its rendering can fail. Any error returned will be
+    /// propagated as a [`ClientError::TemplateRenderFailed`], rather than
+    /// requiring a panic inside your view. Templates that can't fail
+    /// should use `.view_with_state()` instead.
+    ///
+    /// The closure wrapping this performs will automatically handle suspense
+    /// state.
+    pub fn view_with_state_fallible<I, F, E>(mut self, val: F) -> Self
+    where
+        F: for<'app, 'child> Fn(BoundedScope<'app, 'child>, &'child I) -> Result<View<G>, E>
+            + Send
+            + Sync
+            + 'static,
+        E: Into<Box<dyn std::error::Error + Send + Sync>>,
+        I: MakeUnrx + AnyFreeze + Clone,
+        I::Unrx: MakeRx<Rx = I> + Serialize + DeserializeOwned + Send + Sync + Clone + 'static,
+    {
+        self.view_set = true;
         self.view = Box::new(
             #[allow(unused_variables)]
             move |app_cx, preload_info, template_state, path| {
@@ -48,14 +71,18 @@ impl<G: Html> TemplateInner<G> {
                 // when the page ends (otherwise we basically get a series of
                 // continuous pseudo-memory leaks, which can also cause accumulations of
                 // listeners on things like the router state)
-                let mut view = View::empty();
+                let mut view_res = Ok(View::empty());
                 let disposer = ::sycamore::reactive::create_child_scope(app_cx, |child_cx| {
                     // Compute suspended states
                     #[cfg(any(client, doc))]
                     intermediate_state.compute_suspense(child_cx);
 
-                    view = val(child_cx, create_ref(child_cx, intermediate_state));
+                    view_res = val(child_cx, create_ref(child_cx, intermediate_state));
                 });
+                let view = view_res.map_err(|err| ClientError::TemplateRenderFailed {
+                    path: path.to_string(),
+                    source: err.into(),
+                })?;
                 Ok((view, disposer))
             },
         );
@@ -69,6 +96,7 @@ impl<G: Html> TemplateInner<G> {
         S: MakeRx + Serialize + DeserializeOwned + UnreactiveState + 'static,
         <S as MakeRx>::Rx: AnyFreeze + Clone + MakeUnrx<Unrx = S>,
     {
+        self.view_set = true;
         self.view = Box::new(
             #[allow(unused_variables)]
             move |app_cx, preload_info, template_state, path| {
@@ -90,10 +118,22 @@ impl<G: Html> TemplateInner<G> {
     /// Sets the template rendering function to use for templates that take no
     /// state. Templates that do take state should use
     /// `.template_with_state()` instead.
-    pub fn view<F>(mut self, val: F) -> Self
+    pub fn view<F>(self, val: F) -> Self
     where
         F: Fn(Scope) -> View<G> + Send + Sync + 'static,
     {
+        self.view_fallible(move |cx| Ok::<_, Infallible>(val(cx)))
+    }
+    /// Sets the template rendering function to use for templates that take no
+    /// state, but whose rendering can fail. Any error returned will be
+    /// propagated as a [`ClientError::TemplateRenderFailed`]. Templates that
+    /// can't fail should use `.view()` instead.
+    pub fn view_fallible<F, E>(mut self, val: F) -> Self
+    where
+        F: Fn(Scope) -> Result<View<G>, E> + Send + Sync + 'static,
+        E: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        self.view_set = true;
         self.view = Box::new(move |app_cx, _preload_info, _template_state, path| {
             let reactor = Reactor::<G>::from_cx(app_cx);
             // Declare that this page/widget will never take any state to enable full
@@ -102,9 +142,63 @@ impl<G: Html> TemplateInner<G> {
 
             // Nicely, if this is a widget, this means there need be no network requests
             // at all!
+            let mut view_res = Ok(View::empty());
+            let disposer = ::sycamore::reactive::create_child_scope(app_cx, |child_cx| {
+                view_res = val(child_cx);
+            });
+            let view = view_res.map_err(|err| ClientError::TemplateRenderFailed {
+                path: path.to_string(),
+                source: err.into(),
+            })?;
+            Ok((view, disposer))
+        });
+        self
+    }
+    /// Enables strict mode for this template, in which rendering it without
+    /// ever having set a view (e.g. with `.view()`) will return a
+    /// [`ClientError::ViewNotSet`], rather than silently rendering an empty
+    /// page. Views can be set before or after calling this.
+    pub fn strict(mut self) -> Self {
+        if !self.view_set {
+            let template_name = self.get_path();
+            self.view = Box::new(move |_, _, _, _| {
+                Err(ClientError::ViewNotSet {
+                    template_name: template_name.clone(),
+                })
+            });
+        }
+        self
+    }
+    /// Wraps the views of this template's pages in the given layout, which
+    /// will be given the page's own view (e.g. to put it between a shared
+    /// header and footer). This can be called multiple times, in which case
+    /// each layout will wrap the ones added before it. Layouts are applied
+    /// on both the engine-side and the browser-side, so they won't break
+    /// hydration.
+    pub fn layout_fn<F>(mut self, val: F) -> Self
+    where
+        F: Fn(Scope, View<G>) -> View<G> + Send + Sync + 'static,
+    {
+        self.layouts.push(Box::new(val));
+        self
+    }
+    /// Sets the template rendering function to use for templates that take no
+    /// state, but that need to know the path of the page being rendered
+    /// (e.g. for breadcrumbs). The path will be given without a leading
+    /// slash, and it will include the locale if your app uses i18n.
+    pub fn view_with_path<F>(mut self, val: F) -> Self
+    where
+        F: Fn(Scope, &str) -> View<G> + Send + Sync + 'static,
+    {
+        self.view_set = true;
+        self.view = Box::new(move |app_cx, _preload_info, _template_state, path| {
+            let reactor = Reactor::<G>::from_cx(app_cx);
+            // As for `.view()`, this will never take any state
+            reactor.register_no_state(&path, false);
+
             let mut view = View::empty();
             let disposer = ::sycamore::reactive::create_child_scope(app_cx, |child_cx| {
-                view = val(child_cx);
+                view = val(child_cx, &path);
             });
             Ok((view, disposer))
         });
@@ -213,3 +307,65 @@ impl<G: Html> TemplateInner<G> {
         self
     }
 }
+
+#[cfg(all(test, engine))]
+mod tests {
+    use crate::errors::*;
+    use crate::state::TemplateState;
+    use crate::template::core::test_utils::*;
+    use crate::template::Template;
+    use sycamore::web::SsrNode;
+
+    #[test]
+    fn view_with_path_receives_page_path() {
+        let template = Template::<SsrNode>::build("docs")
+            .view_with_path(|cx, path| {
+                let path = path.to_string();
+                sycamore::view! { cx, p { (path) } }
+            })
+            .build();
+        let translator = translator();
+        let html = template
+            .render_to_string_at(TemplateState::empty(), "docs/intro", &translator)
+            .unwrap();
+        assert!(html.contains("docs/intro"));
+    }
+
+    #[test]
+    fn strict_template_without_view_errors() {
+        let translator = translator();
+        let template = Template::<SsrNode>::build("test").strict().build();
+        assert!(matches!(
+            template.render_to_string(TemplateState::empty(), &translator),
+            Err(ClientError::ViewNotSet { template_name }) if template_name == "test"
+        ));
+
+        let template = Template::<SsrNode>::build("test")
+            .strict()
+            .view(|cx| sycamore::view! { cx, p { "Hello!" } })
+            .build();
+        assert!(template
+            .render_to_string(TemplateState::empty(), &translator)
+            .unwrap()
+            .contains("Hello!"));
+    }
+
+    #[test]
+    fn layouts_wrap_page_view() {
+        let template = Template::<SsrNode>::build("test")
+            .view(|cx| sycamore::view! { cx, p { "Content" } })
+            .layout_fn(|cx, page| sycamore::view! { cx, main { (page) } })
+            .layout_fn(|cx, page| sycamore::view! { cx, div(class = "app") { (page) } })
+            .build();
+        let translator = translator();
+
+        let html = template
+            .render_to_string(TemplateState::empty(), &translator)
+            .unwrap();
+        let main = html.find("<main").unwrap();
+        let content = html.find("Content").unwrap();
+        let outer = html.find("class=\"app\"").unwrap();
+        assert!(outer < main && main < content);
+        assert!(html.contains("</p></main></div>"));
+    }
+}
@@ -0,0 +1,95 @@
+//! Fixtures shared by the template tests.
+
+use crate::i18n::{FsTranslationsManager, Translator};
+use crate::init::PerseusApp;
+use crate::state::{StateGeneratorInfo, TemplateState, UnknownStateType, UnreactiveState};
+use crate::stores::{FsMutableStore, ImmutableStore};
+use crate::template::Template;
+use crate::turbine::Turbine;
+use crate::Request;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::ops::Deref;
+use std::path::PathBuf;
+use sycamore::web::SsrNode;
+
+/// A minimal unreactive state for test templates.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct TestState {
+    pub(crate) title: String,
+}
+impl UnreactiveState for TestState {}
+
+/// Creates generator information for the given path in the test locale, with
+/// no extra build paths state.
+pub(crate) fn info(path: &str) -> StateGeneratorInfo<UnknownStateType> {
+    StateGeneratorInfo {
+        path: path.to_string(),
+        locale: "xx-XX".to_string(),
+        extra: TemplateState::empty(),
+    }
+}
+
+/// Creates an empty translator for the test locale.
+pub(crate) fn translator() -> Translator {
+    Translator::new("xx-XX".to_string(), String::new()).unwrap()
+}
+
+/// Creates an empty `GET` request.
+pub(crate) fn request() -> Request {
+    Request::builder().body(()).unwrap()
+}
+
+/// A built test app, along with a runtime to serve it from. Its build
+/// artifacts will be deleted when this is dropped, even if the test panics.
+pub(crate) struct TestTurbine {
+    turbine: Turbine<FsMutableStore, FsTranslationsManager>,
+    rt: tokio::runtime::Runtime,
+    dist: PathBuf,
+}
+impl TestTurbine {
+    /// Runs the given future (which will usually use this app) to completion.
+    pub(crate) fn block_on<F: Future>(&self, fut: F) -> F::Output {
+        self.rt.block_on(fut)
+    }
+}
+impl Deref for TestTurbine {
+    type Target = Turbine<FsMutableStore, FsTranslationsManager>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.turbine
+    }
+}
+impl Drop for TestTurbine {
+    fn drop(&mut self) {
+        // There's nothing useful to be done if this fails
+        let _ = std::fs::remove_dir_all(&self.dist);
+    }
+}
+
+/// Creates and builds an app with the given templates, whose build artifacts
+/// will be stored in a directory unique to the given name and this process.
+pub(crate) fn test_turbine(name: &str, templates: Vec<Template<SsrNode>>) -> TestTurbine {
+    // The immutable store strips leading slashes, so this has to be relative
+    let dist_str = format!("target/perseus-{}-{}", name, std::process::id());
+    let app = templates.into_iter().fold(
+        PerseusApp::new_with_mutable_store(FsMutableStore::new(format!("{}/mutable", dist_str)))
+            .immutable_store(ImmutableStore::new(dist_str.clone())),
+        |app, template| app.template(template),
+    );
+    // This is created before building so the directory is still cleaned up if
+    // the build fails
+    let mut test_turbine = TestTurbine {
+        turbine: Turbine::try_from(app).unwrap(),
+        rt: tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap(),
+        dist: PathBuf::from(dist_str),
+    };
+    test_turbine
+        .rt
+        .block_on(test_turbine.turbine.build())
+        .unwrap();
+
+    test_turbine
+}
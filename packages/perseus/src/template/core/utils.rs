@@ -9,3 +9,83 @@ pub(crate) struct PreloadInfo {
     #[cfg(any(client, doc))]
     pub(crate) was_incremental_match: bool,
 }
+
+/// Checks whether or not the given path is suitable for use as either the root
+/// path of a template, or a path generated by the *build paths* strategy,
+/// returning the reason it's unsuitable if it isn't. Empty paths are only
+/// permitted if `allow_empty` is set (as they are for build paths, where they
+/// denote the root of the template).
+///
+/// Build paths are normalized (see `normalize_build_path()`) before they get
+/// here, so they'll never have empty segments: duplicate slashes in them are
+/// collapsed rather than rejected.
+#[cfg(engine)]
+pub(crate) fn validate_path(path: &str, allow_empty: bool) -> Result<(), &'static str> {
+    if path.is_empty() {
+        if allow_empty {
+            return Ok(());
+        } else {
+            return Err("path is empty");
+        }
+    }
+    if path.trim() != path {
+        return Err("path has leading or trailing whitespace");
+    }
+    if path.contains("//") {
+        return Err("path contains an empty segment");
+    }
+    if path.split('/').any(|segment| segment == "..") {
+        return Err("path contains a `..` segment");
+    }
+
+    Ok(())
+}
+
+/// Normalizes the given path generated by the *build paths* strategy into
+/// the form Perseus uses internally, collapsing any duplicate slashes and
+/// stripping leading and trailing ones (so both `/about/` and `about//`
+/// become `about`). The root of a template will always be an empty string.
+#[cfg(engine)]
+pub(crate) fn normalize_build_path(path: &str) -> String {
+    path.split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(all(test, engine))]
+mod tests {
+    use super::*;
+    use crate::template::Template;
+    use sycamore::web::SsrNode;
+
+    #[test]
+    fn validates_template_paths() {
+        assert!(Template::<SsrNode>::build("/blog//post")
+            .build()
+            .validate()
+            .is_err());
+        assert!(Template::<SsrNode>::build("/blog/post")
+            .build()
+            .validate()
+            .is_ok());
+    }
+
+    #[test]
+    fn validates_build_paths() {
+        assert!(validate_path(&normalize_build_path("/blog//post"), true).is_ok());
+        assert!(validate_path("", true).is_ok());
+        assert!(validate_path("", false).is_err());
+        assert!(validate_path(" post", true).is_err());
+        assert!(validate_path("post/../secret", true).is_err());
+        assert!(validate_path("post/..hidden", true).is_ok());
+    }
+
+    #[test]
+    fn normalizes_build_paths() {
+        assert_eq!(normalize_build_path("about/"), "about");
+        assert_eq!(normalize_build_path("/blog//post/"), "blog/post");
+        assert_eq!(normalize_build_path("/"), "");
+        assert_eq!(normalize_build_path(""), "");
+    }
+}
@@ -1,11 +1,15 @@
 use crate::{
     errors::*,
     make_async_trait,
-    state::{BuildPaths, MakeRx, StateGeneratorInfo, TemplateState, UnknownStateType},
+    state::{
+        BuildPathMeta, BuildPaths, BuildStateOutcome, MakeRx, RenderOutcome, RequestStateOutcome,
+        ResponseMeta, StateGeneratorInfo, TemplateState, UnknownStateType,
+    },
     utils::AsyncFnReturn,
     Request,
 };
-use futures::Future;
+use futures::stream::BoxStream;
+use futures::{Future, Stream};
 use http::HeaderMap;
 use serde::{de::DeserializeOwned, Serialize};
 use sycamore::{prelude::Scope, view::View, web::SsrNode};
@@ -117,6 +121,41 @@ impl<E: Into<Box<dyn std::error::Error + Send + Sync + 'static>> + Send + Sync>
         }
     }
 }
+impl From<Vec<BuildPathMeta>> for GeneratorResult<Vec<BuildPathMeta>> {
+    fn from(val: Vec<BuildPathMeta>) -> Self {
+        Self::Ok(val)
+    }
+}
+impl<E: Into<Box<dyn std::error::Error + Send + Sync + 'static>> + Send + Sync>
+    From<Result<Vec<BuildPathMeta>, E>> for GeneratorResult<Vec<BuildPathMeta>>
+{
+    fn from(val: Result<Vec<BuildPathMeta>, E>) -> Self {
+        match val {
+            Ok(val) => Self::Ok(val),
+            Err(err) => Self::Err(err.into()),
+        }
+    }
+}
+// Pagination
+impl<S: Serialize + DeserializeOwned + MakeRx> From<Vec<(String, S)>>
+    for GeneratorResult<Vec<(String, S)>>
+{
+    fn from(val: Vec<(String, S)>) -> Self {
+        Self::Ok(val)
+    }
+}
+impl<
+        S: Serialize + DeserializeOwned + MakeRx,
+        E: Into<Box<dyn std::error::Error + Send + Sync + 'static>> + Send + Sync,
+    > From<Result<Vec<(String, S)>, E>> for GeneratorResult<Vec<(String, S)>>
+{
+    fn from(val: Result<Vec<(String, S)>, E>) -> Self {
+        match val {
+            Ok(val) => Self::Ok(val),
+            Err(err) => Self::Err(err.into()),
+        }
+    }
+}
 // Global build state (*not* blamed)
 impl<S: Serialize + DeserializeOwned + MakeRx> From<S> for GeneratorResult<S> {
     fn from(val: S) -> Self {
@@ -186,6 +225,88 @@ impl<
         }
     }
 }
+// Skippable build state (blamed)
+impl<S: Serialize + DeserializeOwned + MakeRx> From<BuildStateOutcome<S>>
+    for BlamedGeneratorResult<BuildStateOutcome<S>>
+{
+    fn from(val: BuildStateOutcome<S>) -> Self {
+        Self::Ok(val)
+    }
+}
+impl<
+        S: Serialize + DeserializeOwned + MakeRx,
+        E: Into<Box<dyn std::error::Error + Send + Sync + 'static>> + Send + Sync,
+    > From<Result<BuildStateOutcome<S>, BlamedError<E>>>
+    for BlamedGeneratorResult<BuildStateOutcome<S>>
+{
+    fn from(val: Result<BuildStateOutcome<S>, BlamedError<E>>) -> Self {
+        match val {
+            Ok(val) => Self::Ok(val),
+            Err(err) => Self::Err(err.into_boxed()),
+        }
+    }
+}
+// Conditional request state (blamed)
+impl<S: Serialize + DeserializeOwned + MakeRx> From<RequestStateOutcome<S>>
+    for BlamedGeneratorResult<RequestStateOutcome<S>>
+{
+    fn from(val: RequestStateOutcome<S>) -> Self {
+        Self::Ok(val)
+    }
+}
+impl<
+        S: Serialize + DeserializeOwned + MakeRx,
+        E: Into<Box<dyn std::error::Error + Send + Sync + 'static>> + Send + Sync,
+    > From<Result<RequestStateOutcome<S>, BlamedError<E>>>
+    for BlamedGeneratorResult<RequestStateOutcome<S>>
+{
+    fn from(val: Result<RequestStateOutcome<S>, BlamedError<E>>) -> Self {
+        match val {
+            Ok(val) => Self::Ok(val),
+            Err(err) => Self::Err(err.into_boxed()),
+        }
+    }
+}
+// Request state with response metadata (blamed)
+impl<S: Serialize + DeserializeOwned + MakeRx> From<(S, ResponseMeta)>
+    for BlamedGeneratorResult<(S, ResponseMeta)>
+{
+    fn from(val: (S, ResponseMeta)) -> Self {
+        Self::Ok(val)
+    }
+}
+impl<
+        S: Serialize + DeserializeOwned + MakeRx,
+        E: Into<Box<dyn std::error::Error + Send + Sync + 'static>> + Send + Sync,
+    > From<Result<(S, ResponseMeta), BlamedError<E>>> for BlamedGeneratorResult<(S, ResponseMeta)>
+{
+    fn from(val: Result<(S, ResponseMeta), BlamedError<E>>) -> Self {
+        match val {
+            Ok(val) => Self::Ok(val),
+            Err(err) => Self::Err(err.into_boxed()),
+        }
+    }
+}
+// Redirecting request state (blamed)
+impl<S: Serialize + DeserializeOwned + MakeRx> From<RenderOutcome<S>>
+    for BlamedGeneratorResult<RenderOutcome<S>>
+{
+    fn from(val: RenderOutcome<S>) -> Self {
+        Self::Ok(val)
+    }
+}
+impl<
+        S: Serialize + DeserializeOwned + MakeRx,
+        E: Into<Box<dyn std::error::Error + Send + Sync + 'static>> + Send + Sync,
+    > From<Result<RenderOutcome<S>, BlamedError<E>>> for BlamedGeneratorResult<RenderOutcome<S>>
+{
+    fn from(val: Result<RenderOutcome<S>, BlamedError<E>>) -> Self {
+        match val {
+            Ok(val) => Self::Ok(val),
+            Err(err) => Self::Err(err.into_boxed()),
+        }
+    }
+}
 // Should revalidate (blamed)
 impl From<bool> for BlamedGeneratorResult<bool> {
     fn from(val: bool) -> Self {
@@ -206,30 +327,46 @@ impl<E: Into<Box<dyn std::error::Error + Send + Sync + 'static>> + Send + Sync>
 // A series of asynchronous closure traits that prevent the user from having to
 // pin their functions
 make_async_trait!(
-    pub(crate) GetBuildPathsFnType,
+    pub GetBuildPathsFnType,
     Result<BuildPaths, ServerError>
 );
+make_async_trait!(
+    pub GetBuildPathsMetaFnType,
+    Result<Vec<BuildPathMeta>, ServerError>
+);
 // The build state strategy needs an error cause if it's invoked from
 // incremental
 make_async_trait!(
-    pub(super) GetBuildStateFnType,
+    pub GetBuildStateFnType,
     Result<TemplateState, ServerError>,
     info: StateGeneratorInfo<UnknownStateType>
 );
 make_async_trait!(
-    pub(super) GetRequestStateFnType,
-    Result<TemplateState, ServerError>,
+    pub GetBuildStatesBatchFnType,
+    Vec<Result<TemplateState, ServerError>>,
+    infos: Vec<StateGeneratorInfo<UnknownStateType>>
+);
+make_async_trait!(
+    pub GetRequestStateFnType,
+    Result<(TemplateState, ResponseMeta), ServerError>,
     info: StateGeneratorInfo<UnknownStateType>,
     req: Request
 );
 make_async_trait!(
-    pub(super) ShouldRevalidateFnType,
+    pub ShouldRevalidateFnType,
     Result<bool, ServerError>,
     info: StateGeneratorInfo<UnknownStateType>,
-    req: Request
+    req: Request,
+    cached_state: TemplateState
 );
 make_async_trait!(
-    pub(super) AmalgamateStatesFnType,
+    pub RevalidateStateFnType,
+    Result<TemplateState, ServerError>,
+    info: StateGeneratorInfo<UnknownStateType>,
+    old_state: TemplateState
+);
+make_async_trait!(
+    pub AmalgamateStatesFnType,
     Result<TemplateState, ServerError>,
     info: StateGeneratorInfo<UnknownStateType>,
     build_state: TemplateState,
@@ -244,23 +381,106 @@ make_async_trait!(
     pub GetBuildPathsUserFnType< V: Into< GeneratorResult<BuildPaths> > >,
     V
 );
+// The macro can't handle three levels of generics
+type BuildPathMetas = Vec<BuildPathMeta>;
+make_async_trait!(
+    pub GetBuildPathsMetaUserFnType< V: Into< GeneratorResult<BuildPathMetas> > >,
+    V
+);
+// The macro can't handle three levels of generics
+type PaginateResult<S> = GeneratorResult<Vec<(String, S)>>;
+make_async_trait!(
+    pub PaginateUserFnType< S: Serialize + DeserializeOwned + MakeRx, V: Into< PaginateResult<S> > >,
+    V
+);
 make_async_trait!(
     pub GetBuildStateUserFnType< S: Serialize + DeserializeOwned + MakeRx, B: Serialize + DeserializeOwned + Send + Sync, V: Into< BlamedGeneratorResult<S> > >,
     V,
     info: StateGeneratorInfo<B>
 );
+// The macro can't handle three levels of generics
+type SkippableBuildStateResult<S> = BlamedGeneratorResult<BuildStateOutcome<S>>;
+make_async_trait!(
+    pub GetSkippableBuildStateUserFnType< S: Serialize + DeserializeOwned + MakeRx, B: Serialize + DeserializeOwned + Send + Sync, V: Into< SkippableBuildStateResult<S> > >,
+    V,
+    info: StateGeneratorInfo<B>
+);
+make_async_trait!(
+    pub GetBuildStatesBatchUserFnType< S: Serialize + DeserializeOwned + MakeRx, B: Serialize + DeserializeOwned + Send + Sync, V: Into< BlamedGeneratorResult<S> > >,
+    Vec<V>,
+    infos: Vec<StateGeneratorInfo<B>>
+);
 make_async_trait!(
     pub GetRequestStateUserFnType< S: Serialize + DeserializeOwned + MakeRx, B: Serialize + DeserializeOwned + Send + Sync, V: Into< BlamedGeneratorResult<S> > >,
     V,
     info: StateGeneratorInfo<B>,
     req: Request
 );
+/// The trait of functions that produce request state as a stream of chunks,
+/// each of which will be rendered separately. This is implemented for all
+/// `Fn`s that return a [`Stream`], so you can use something like
+/// `futures::stream::iter()`, or an `async-stream` generator.
+pub trait StreamRequestStateUserFnType<S, B, V, St>:
+    Fn(StateGeneratorInfo<B>, Request) -> St
+where
+    S: Serialize + DeserializeOwned + MakeRx,
+    B: Serialize + DeserializeOwned + Send + Sync,
+    V: Into<BlamedGeneratorResult<S>>,
+    St: Stream<Item = V> + Send + 'static,
+{
+}
+impl<S, B, V, St, F> StreamRequestStateUserFnType<S, B, V, St> for F
+where
+    S: Serialize + DeserializeOwned + MakeRx,
+    B: Serialize + DeserializeOwned + Send + Sync,
+    V: Into<BlamedGeneratorResult<S>>,
+    St: Stream<Item = V> + Send + 'static,
+    F: Fn(StateGeneratorInfo<B>, Request) -> St,
+{
+}
+// The macro can't handle three levels of generics
+type ConditionalRequestStateResult<S> = BlamedGeneratorResult<RequestStateOutcome<S>>;
+make_async_trait!(
+    pub GetConditionalRequestStateUserFnType< S: Serialize + DeserializeOwned + MakeRx, B: Serialize + DeserializeOwned + Send + Sync, V: Into< ConditionalRequestStateResult<S> > >,
+    V,
+    info: StateGeneratorInfo<B>,
+    req: Request
+);
+// The macro can't handle three levels of generics
+type RequestStateWithMetaResult<S> = BlamedGeneratorResult<(S, ResponseMeta)>;
+make_async_trait!(
+    pub GetRequestStateWithMetaUserFnType< S: Serialize + DeserializeOwned + MakeRx, B: Serialize + DeserializeOwned + Send + Sync, V: Into< RequestStateWithMetaResult<S> > >,
+    V,
+    info: StateGeneratorInfo<B>,
+    req: Request
+);
+// The macro can't handle three levels of generics
+type RedirectingRequestStateResult<S> = BlamedGeneratorResult<RenderOutcome<S>>;
+make_async_trait!(
+    pub GetRedirectingRequestStateUserFnType< S: Serialize + DeserializeOwned + MakeRx, B: Serialize + DeserializeOwned + Send + Sync, V: Into< RedirectingRequestStateResult<S> > >,
+    V,
+    info: StateGeneratorInfo<B>,
+    req: Request
+);
 make_async_trait!(
     pub ShouldRevalidateUserFnType< B: Serialize + DeserializeOwned + Send + Sync, V: Into< BlamedGeneratorResult<bool> >  >,
     V,
     info: StateGeneratorInfo<B>,
     req: Request
 );
+make_async_trait!(
+    pub ShouldRevalidateWithStateUserFnType< S: Serialize + DeserializeOwned + MakeRx, B: Serialize + DeserializeOwned + Send + Sync, V: Into< BlamedGeneratorResult<bool> > >,
+    V,
+    info: StateGeneratorInfo<B>,
+    req: Request,
+    cached_state: S
+);
+make_async_trait!(
+    pub RevalidateStateUserFnType< S: Serialize + DeserializeOwned + MakeRx, B: Serialize + DeserializeOwned + Send + Sync, V: Into< BlamedGeneratorResult<S> > >,
+    V,
+    info: StateGeneratorInfo<B>,
+    old_state: S
+);
 make_async_trait!(
     pub AmalgamateStatesUserFnType< S: Serialize + DeserializeOwned + MakeRx, B: Serialize + DeserializeOwned + Send + Sync, V: Into< BlamedGeneratorResult<S> > >,
     V,
@@ -268,8 +488,16 @@ make_async_trait!(
     build_state: S,
     request_state: S
 );
+make_async_trait!(
+    pub AmalgamateMixedStatesUserFnType< S: Serialize + DeserializeOwned + MakeRx, BS: Serialize + DeserializeOwned + Send + Sync, RS: Serialize + DeserializeOwned + Send + Sync, B: Serialize + DeserializeOwned + Send + Sync, V: Into< BlamedGeneratorResult<S> > >,
+    V,
+    info: StateGeneratorInfo<B>,
+    build_state: BS,
+    request_state: RS
+);
 
-// A series of closure types that should not be typed out more than once
+// A series of closure types that should not be typed out more than once (these
+// are public so that users can name them in their own helper functions)
 
 // Note: the head and header functions have render errors constructed inside
 // their closures!
@@ -277,18 +505,66 @@ make_async_trait!(
 /// a template function that will always be server-side rendered in function (it
 /// may be rendered on the client, but it will always be used to create an HTML
 /// string, rather than a reactive template).
-pub(crate) type HeadFn =
+pub type HeadFn =
     Box<dyn Fn(Scope, TemplateState) -> Result<View<SsrNode>, ServerError> + Send + Sync>;
 /// The type of functions that modify HTTP response headers.
-pub(crate) type SetHeadersFn =
+pub type SetHeadersFn =
     Box<dyn Fn(Scope, TemplateState) -> Result<HeaderMap, ServerError> + Send + Sync>;
+/// The type of functions that render inline errors for a template's pages
+/// when their views fail.
+pub type ErrorViewFn<G> = Box<dyn Fn(Scope, &ClientError) -> View<G> + Send + Sync>;
+/// The type of functions that render the body of a 404 page for a
+/// template's missing pages.
+pub type NotFoundFn = Box<dyn Fn(Scope, &str) -> View<SsrNode> + Send + Sync>;
+/// The type of functions that transform the HTML a template's pages render
+/// to.
+pub type TransformHtmlFn =
+    Box<dyn Fn(String) -> Result<String, Box<dyn std::error::Error + Send + Sync>> + Send + Sync>;
+/// The type of functions that generate the canonical URLs of a template's
+/// pages.
+pub type CanonicalUrlFn = Box<dyn Fn(&str, &TemplateState) -> String + Send + Sync>;
+/// The type of functions that run once before a template is built.
+pub type BuildStartFn =
+    Box<dyn Fn() -> Result<(), Box<dyn std::error::Error + Send + Sync>> + Send + Sync>;
 /// The type of functions that get build paths.
-pub(crate) type GetBuildPathsFn = Box<dyn GetBuildPathsFnType + Send + Sync>;
+pub type GetBuildPathsFn = Box<dyn GetBuildPathsFnType + Send + Sync>;
+/// The type of functions that get build paths with metadata.
+pub type GetBuildPathsMetaFn = Box<dyn GetBuildPathsMetaFnType + Send + Sync>;
+/// The type of functions that decide whether or not an incremental path is
+/// allowed.
+pub type IncrementalPathMatcherFn = Box<dyn Fn(&str) -> bool + Send + Sync>;
+/// The type of functions that check if a request is authorized to access a
+/// template's pages.
+pub type AuthCheckFn = Box<dyn Fn(&Request) -> bool + Send + Sync>;
 /// The type of functions that get build state.
-pub(crate) type GetBuildStateFn = Box<dyn GetBuildStateFnType + Send + Sync>;
+pub type GetBuildStateFn = Box<dyn GetBuildStateFnType + Send + Sync>;
+/// The type of functions that get build state for many paths at once.
+pub type GetBuildStatesBatchFn = Box<dyn GetBuildStatesBatchFnType + Send + Sync>;
+/// The type of functions that validate generated state.
+pub type ValidateStateFn = Box<
+    dyn Fn(&TemplateState) -> Result<(), Box<dyn std::error::Error + Send + Sync>> + Send + Sync,
+>;
+/// The type of middleware functions that are run on requests before request
+/// state is generated.
+pub type RequestMiddlewareFn = Box<
+    dyn Fn(&mut Request) -> Result<(), BlamedError<Box<dyn std::error::Error + Send + Sync>>>
+        + Send
+        + Sync,
+>;
 /// The type of functions that get request state.
-pub(crate) type GetRequestStateFn = Box<dyn GetRequestStateFnType + Send + Sync>;
+pub type GetRequestStateFn = Box<dyn GetRequestStateFnType + Send + Sync>;
+/// The type of functions that get request state as a stream of chunks.
+pub type StreamRequestStateFn = Box<
+    dyn Fn(
+            StateGeneratorInfo<UnknownStateType>,
+            Request,
+        ) -> BoxStream<'static, Result<TemplateState, ServerError>>
+        + Send
+        + Sync,
+>;
 /// The type of functions that check if a template should revalidate.
-pub(crate) type ShouldRevalidateFn = Box<dyn ShouldRevalidateFnType + Send + Sync>;
+pub type ShouldRevalidateFn = Box<dyn ShouldRevalidateFnType + Send + Sync>;
+/// The type of functions that update the state of a page on revalidation.
+pub type RevalidateStateFn = Box<dyn RevalidateStateFnType + Send + Sync>;
 /// The type of functions that amalgamate build and request states.
-pub(crate) type AmalgamateStatesFn = Box<dyn AmalgamateStatesFnType + Send + Sync>;
+pub type AmalgamateStatesFn = Box<dyn AmalgamateStatesFnType + Send + Sync>;
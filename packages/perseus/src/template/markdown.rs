@@ -0,0 +1,163 @@
+use super::{Template, TemplateInner};
+#[cfg(engine)]
+use crate::state::StateGeneratorInfo;
+use crate::state::UnreactiveState;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use sycamore::web::Html;
+
+/// The state of a page created with `Template::from_markdown()`, which holds
+/// the page's front matter and its Markdown body, rendered to HTML.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct MarkdownState {
+    /// The `key: value` pairs from the page's front matter, if it had any.
+    pub front_matter: BTreeMap<String, String>,
+    /// The body of the page, rendered to HTML.
+    pub content: String,
+}
+impl UnreactiveState for MarkdownState {}
+
+impl<G: Html> Template<G> {
+    /// Creates a new [`TemplateInner`] for a page that just renders the given
+    /// Markdown source. The page will have a [`MarkdownState`] generated
+    /// with the *build state* strategy, which holds the rendered HTML and any
+    /// front matter.
+    ///
+    /// Front matter should be at the very start of the source, delimited by
+    /// `---` lines, and should consist of simple `key: value` pairs (nested
+    /// YAML is not supported). For example:
+    ///
+    /// ```text
+    /// ---
+    /// title: Hello world!
+    /// ---
+    ///
+    /// # Hello world!
+    /// ```
+    ///
+    /// You can still set things like a head function on the returned
+    /// template, which can use the front matter through the state.
+    pub fn from_markdown(path: &str, md_source: &str) -> TemplateInner<G> {
+        let template =
+            Template::build(path).view_with_unreactive_state(|cx, state: MarkdownState| {
+                sycamore::view! { cx,
+                    div(dangerously_set_inner_html = &state.content)
+                }
+            });
+
+        #[cfg(engine)]
+        {
+            let state = parse_markdown(md_source);
+            template.build_state_fn(move |_info: StateGeneratorInfo<()>| {
+                let state = state.clone();
+                async move { state }
+            })
+        }
+        #[cfg(any(client, doc))]
+        {
+            // The state is only generated on the engine-side
+            let _ = md_source;
+            template
+        }
+    }
+}
+
+/// Splits the given Markdown source into its front matter and body, and
+/// renders the body to HTML.
+#[cfg(engine)]
+fn parse_markdown(md_source: &str) -> MarkdownState {
+    use pulldown_cmark::{html, Options, Parser};
+
+    let (front_matter, body) = split_front_matter(md_source);
+
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_TABLES);
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+    options.insert(Options::ENABLE_FOOTNOTES);
+    let parser = Parser::new_ext(body, options);
+    let mut content = String::new();
+    html::push_html(&mut content, parser);
+
+    MarkdownState {
+        front_matter,
+        content,
+    }
+}
+
+/// Splits any front matter off the start of the given Markdown source. If
+/// there's no front matter (or it's never closed), the whole source will be
+/// treated as the body.
+#[cfg(engine)]
+fn split_front_matter(md_source: &str) -> (BTreeMap<String, String>, &str) {
+    let mut front_matter = BTreeMap::new();
+    let rest = match md_source
+        .strip_prefix("---\n")
+        .or_else(|| md_source.strip_prefix("---\r\n"))
+    {
+        Some(rest) => rest,
+        None => return (front_matter, md_source),
+    };
+
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        offset += line.len();
+        let line = line.trim();
+        if line == "---" {
+            return (front_matter, &rest[offset..]);
+        }
+        if let Some((key, val)) = line.split_once(':') {
+            let val = val.trim();
+            let val = val
+                .strip_prefix('"')
+                .and_then(|val| val.strip_suffix('"'))
+                .unwrap_or(val);
+            front_matter.insert(key.trim().to_string(), val.to_string());
+        }
+    }
+
+    (BTreeMap::new(), md_source)
+}
+
+#[cfg(all(test, engine))]
+mod tests {
+    use super::*;
+    use crate::{i18n::Translator, state::TemplateState};
+    use futures::executor::block_on;
+    use sycamore::web::SsrNode;
+
+    #[test]
+    fn from_markdown_generates_front_matter_and_body() {
+        let template = Template::<SsrNode>::from_markdown(
+            "post",
+            "---\ntitle: \"Hello world!\"\nauthor: Someone\n---\n\n# Hello\n\nSome *text*.\n",
+        )
+        .build();
+        let state = block_on(template.get_build_state(crate::state::StateGeneratorInfo {
+            path: "post".to_string(),
+            locale: "xx-XX".to_string(),
+            extra: TemplateState::empty(),
+        }))
+        .unwrap();
+
+        let props = state
+            .clone()
+            .change_type::<MarkdownState>()
+            .into_concrete()
+            .unwrap();
+        assert_eq!(props.front_matter["title"], "Hello world!");
+        assert_eq!(props.front_matter["author"], "Someone");
+        assert!(!props.content.contains("title:"));
+
+        let translator = Translator::new("xx-XX".to_string(), String::new()).unwrap();
+        let html = template.render_to_string(state, &translator).unwrap();
+        assert!(html.contains("<h1>Hello</h1>"));
+        assert!(html.contains("<em>text</em>"));
+    }
+
+    #[test]
+    fn from_markdown_without_front_matter_renders_everything() {
+        let (front_matter, body) = split_front_matter("# Title\n---\n");
+        assert!(front_matter.is_empty());
+        assert_eq!(body, "# Title\n---\n");
+    }
+}
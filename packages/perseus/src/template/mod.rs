@@ -3,8 +3,11 @@ mod core; // So called because this contains what is essentially the core expose
 mod default_headers;
 // mod render_ctx;
 mod capsule;
+mod collection;
 #[cfg(engine)]
 mod fn_types;
+#[cfg(feature = "markdown")]
+mod markdown;
 #[cfg(engine)]
 mod states;
 mod widget_component;
@@ -17,7 +20,12 @@ pub use fn_types::*; /* There are a lot of render function traits in here, there
 pub(crate) use default_headers::default_headers;
 // pub use render_ctx::RenderCtx;
 // pub(crate) use render_ctx::{RenderMode, RenderStatus};
+#[cfg(all(test, engine))]
+pub(crate) use self::core::test_utils;
 pub use capsule::{Capsule, CapsuleInner};
+pub use collection::{MergeStrategy, TemplateCollection};
+#[cfg(feature = "markdown")]
+pub use markdown::MarkdownState;
 #[cfg(engine)]
 pub(crate) use states::States;
 
@@ -38,3 +46,9 @@ pub(crate) type TemplateFn<G> = Box<
         + Send
         + Sync,
 >;
+/// The type of functions that wrap the views of a template's pages in a
+/// layout (e.g. a shared header and footer).
+pub type LayoutFn<G> = Box<dyn Fn(Scope, View<G>) -> View<G> + Send + Sync>;
+/// The type of functions that render a placeholder for a template's pages
+/// while they're being incrementally generated.
+pub type FallbackFn<G> = Box<dyn Fn(Scope) -> View<G> + Send + Sync>;
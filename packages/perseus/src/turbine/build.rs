@@ -10,11 +10,11 @@ use crate::{
     server::get_path_slice,
     state::{BuildPaths, StateGeneratorInfo, TemplateState},
     stores::MutableStore,
-    template::Entity,
+    template::{validate_path, Entity},
     utils::{minify, ssr_fallible},
 };
 use futures::{
-    future::{try_join_all, BoxFuture},
+    future::{join_all, try_join_all, BoxFuture},
     FutureExt,
 };
 use std::{cell::RefCell, collections::HashMap, rc::Rc, sync::Arc};
@@ -82,10 +82,12 @@ impl<M: MutableStore, T: TranslationsManager> Turbine<M, T> {
         // and would cancel the render).
         self.render_cfg = render_cfg.clone();
 
-        // Now build every template's state in parallel
+        // Now build every template's state in parallel (leaving out drafts in
+        // production). Disabled templates are still built, since they're only gated at
+        // request-time, and they might be enabled without a rebuild.
         let mut template_futs = Vec::new();
         for template in self.entities.values() {
-            if !template.is_capsule {
+            if !template.is_capsule && template.include_in_build(!cfg!(debug_assertions)) {
                 template_futs.push(self.build_template_or_capsule(template, exporting));
             }
         }
@@ -180,21 +182,29 @@ impl<M: MutableStore, T: TranslationsManager> Turbine<M, T> {
             .into());
         }
 
+        entity.validate()?;
+        entity.run_build_start()?;
+
         let mut render_cfg_frag = HashMap::new();
 
         // We extract the paths and extra state for rendering outside, but we handle the
         // render config inside this block
         let (paths, extra) = if entity.uses_build_paths() {
             let BuildPaths { mut paths, extra } = entity.get_build_paths().await?;
+            // Anything beyond the limit will be generated incrementally, and so mustn't be
+            // in the render config
+            entity.apply_incremental_build_limit(&mut paths);
+
+            // Add all the paths to the render config (these have already been normalized, so
+            // there are no erroneous slashes to strip)
+            for page_path in paths.iter() {
+                validate_path(page_path, true).map_err(|reason| BuildError::InvalidPagePath {
+                    template_name: entity.get_path(),
+                    path: page_path.to_string(),
+                    reason: reason.to_string(),
+                })?;
 
-            // Add all the paths to the render config (stripping erroneous slashes as we go)
-            for mut page_path in paths.iter_mut() {
-                // Strip any erroneous slashes
-                let stripped = page_path.strip_prefix('/').unwrap_or(page_path);
-                let mut stripped = stripped.to_string();
-                page_path = &mut stripped;
-
-                let full_path = format!("{}/{}", &entity.get_path(), &page_path);
+                let full_path = format!("{}/{}", &entity.get_path(), page_path);
                 // And perform another strip for index pages to work
                 let full_path = full_path.strip_suffix('/').unwrap_or(&full_path);
                 let full_path = full_path.strip_prefix('/').unwrap_or(full_path);
@@ -218,6 +228,11 @@ impl<M: MutableStore, T: TranslationsManager> Turbine<M, T> {
             // No extra state, one empty path for the index
             (vec![String::new()], TemplateState::empty())
         };
+        // Any aliases just point to the template's root page, which the router will
+        // resolve them to
+        for alias in entity.all_paths().into_iter().skip(1) {
+            render_cfg_frag.insert(alias, entity.get_path());
+        }
         // We write the extra state even if it's empty
         self.immutable_store
             .write(
@@ -236,9 +251,43 @@ impl<M: MutableStore, T: TranslationsManager> Turbine<M, T> {
         // revalidation is completely irrelevant, since you're revalidating on
         // every load.
         if entity.uses_build_state() || entity.is_basic() {
+            // If the entity can generate its states in batches, we'll do that up-front for each
+            // locale
+            let mut batched_states = HashMap::new();
+            if entity.uses_build_state_batch() {
+                for locale in self.locales.get_all() {
+                    let infos = paths
+                        .iter()
+                        .map(|path| StateGeneratorInfo {
+                            path: path.clone(),
+                            locale: locale.to_string(),
+                            extra: extra.clone(),
+                        })
+                        .collect();
+                    let states = entity.get_build_states(infos).await?;
+                    for (path, state) in paths.iter().zip(states) {
+                        batched_states.insert((path.clone(), locale.to_string()), state);
+                    }
+                }
+            }
+
+            // Any paths the build state function skips will be removed from the render
+            // configuration (in all locales) once everything is built
+            let mut skipped_paths = Vec::new();
             let mut path_futs = Vec::new();
             for path in paths.into_iter() {
                 for locale in self.locales.get_all() {
+                    let precomputed_state = match batched_states
+                        .remove(&(path.clone(), locale.to_string()))
+                        .transpose()
+                    {
+                        Ok(state) => state,
+                        Err(ServerError::BuildError(BuildError::PathSkipped { .. })) => {
+                            skipped_paths.push(path.clone());
+                            continue;
+                        }
+                        Err(err) => return Err(err),
+                    };
                     let path = PurePath(path.clone());
                     path_futs.push(self.build_path_or_widget_for_locale(
                         path,
@@ -248,13 +297,28 @@ impl<M: MutableStore, T: TranslationsManager> Turbine<M, T> {
                         self.global_state.clone(),
                         exporting,
                         false,
+                        precomputed_state,
                     ));
                 }
             }
             // Extend the render configuration with any incrementally generated widgets
-            let render_cfg_exts = try_join_all(path_futs).await?;
+            let render_cfg_exts = join_all(path_futs).await;
             for ext in render_cfg_exts {
-                render_cfg_frag.extend(ext.into_iter());
+                match ext {
+                    Ok(ext) => render_cfg_frag.extend(ext),
+                    Err(ServerError::BuildError(BuildError::PathSkipped { path, .. })) => {
+                        skipped_paths.push(path)
+                    }
+                    Err(err) => return Err(err),
+                }
+            }
+            for path in skipped_paths {
+                // This is the same transformation we used to add the path to the render
+                // configuration
+                let full_path = format!("{}/{}", &entity.get_path(), path);
+                let full_path = full_path.strip_suffix('/').unwrap_or(&full_path);
+                let full_path = full_path.strip_prefix('/').unwrap_or(full_path);
+                render_cfg_frag.remove(full_path);
             }
         }
 
@@ -284,6 +348,8 @@ impl<M: MutableStore, T: TranslationsManager> Turbine<M, T> {
         exporting: bool,
         // This is used in request-time incremental generation
         force_mutable: bool,
+        // This is used if the entity generated its state in a batch
+        precomputed_state: Option<TemplateState>,
     ) -> Result<HashMap<String, String>, ServerError> {
         let translator = self
             .translations_manager
@@ -318,11 +384,15 @@ impl<M: MutableStore, T: TranslationsManager> Turbine<M, T> {
 
         // First, if this page revalidates, write a timestamp about when it was built to
         // the mutable store (this will be updated to keep track)
-        if entity.revalidates_with_time() {
-            let datetime_to_revalidate = entity
-                .get_revalidate_interval()
-                .unwrap()
-                .compute_timestamp();
+        let datetime_to_revalidate = match entity.get_revalidate_interval() {
+            Some(interval) => Some(interval.compute_timestamp()),
+            // Otherwise, this might revalidate on a cron schedule (which could have stopped
+            // matching since the build was validated, so we check it again)
+            None => entity
+                .next_revalidation_cron_checked(std::time::SystemTime::now())?
+                .map(|next| chrono::DateTime::<chrono::Utc>::from(next).to_rfc3339()),
+        };
+        if let Some(datetime_to_revalidate) = datetime_to_revalidate {
             // Note that different locales do have different revalidation schedules
             self.mutable_store
                 .write(
@@ -336,16 +406,21 @@ impl<M: MutableStore, T: TranslationsManager> Turbine<M, T> {
             // We don't bother writing the state of basic entities
             TemplateState::empty()
         } else if entity.uses_build_state() {
-            let build_state = entity
-                .get_build_state(StateGeneratorInfo {
-                    // IMPORTANT: It is very easy to break Perseus here; always make sure this is
-                    // the pure path, without the template name!
-                    // TODO Compat mode for v0.3.0x?
-                    path: (*path).clone(),
-                    locale: translator.get_locale(),
-                    extra: extra.clone(),
-                })
-                .await?;
+            let build_state = match precomputed_state {
+                Some(state) => state,
+                None => {
+                    entity
+                        .get_build_state(StateGeneratorInfo {
+                            // IMPORTANT: It is very easy to break Perseus here; always make sure
+                            // this is the pure path, without the template name!
+                            // TODO Compat mode for v0.3.0x?
+                            path: (*path).clone(),
+                            locale: translator.get_locale(),
+                            extra: extra.clone(),
+                        })
+                        .await?
+                }
+            };
             // Write the state to the appropriate store (mutable if the entity revalidates)
             let state_str = build_state.state.to_string();
             if force_mutable || entity.revalidates() {
@@ -488,6 +563,7 @@ impl<M: MutableStore, T: TranslationsManager> Turbine<M, T> {
                     // constitutes a widget that could not be rendered because it wasn't in the
                     // render config (either needs to be incrementally rendered, or it doesn't exist).
                     if paps.is_empty() {
+                        let prerendered = entity.transform_html(prerendered, &full_path)?;
                         let prerendered = minify(&prerendered, true)?;
                         // Write that prerendered HTML to a static file (whose presence is used to
                         // indicate that this page/widget was fine to be built at
@@ -587,6 +663,7 @@ impl<M: MutableStore, T: TranslationsManager> Turbine<M, T> {
                                             // like it was in build paths all along, so don't force the mutable
                                             // store unless we're being asked to from a higher level
                                             force_mutable,
+                                            None,
                                         )
                                                        .await?;
 
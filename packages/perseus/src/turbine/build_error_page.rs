@@ -29,6 +29,19 @@ impl<M: MutableStore, T: TranslationsManager> Turbine<M, T> {
         data: ServerErrorData,
         // Translator and translations string
         i18n_data: Option<(&Translator, &str)>,
+    ) -> String {
+        self.build_error_page_with_body(data, None, i18n_data)
+    }
+    /// Prepares an HTML error page exactly as with `.build_error_page()`,
+    /// except the given body will be used instead of the one from the app's
+    /// error views, if one is provided. The head will still come from the
+    /// error views.
+    pub(crate) fn build_error_page_with_body(
+        &self,
+        data: ServerErrorData,
+        body: Option<&str>,
+        // Translator and translations string
+        i18n_data: Option<(&Translator, &str)>,
     ) -> String {
         let (translator, translations_str, locale) = if let Some((t, s)) = i18n_data {
             (Some(t), Some(s), Some(t.get_locale()))
@@ -36,14 +49,15 @@ impl<M: MutableStore, T: TranslationsManager> Turbine<M, T> {
             (None, None, None)
         };
 
-        let (head, body) = self.error_views.render_to_string(data.clone(), translator);
+        let (head, error_body) = self.error_views.render_to_string(data.clone(), translator);
+        let body = body.unwrap_or(&error_body);
 
         self.html_shell
             .as_ref()
             .unwrap()
             .clone()
             // This will inject the translations string if it's available
-            .error_page(&data, &body, &head, locale, translations_str)
+            .error_page(&data, body, &head, locale, translations_str)
             .to_string()
     }
 }
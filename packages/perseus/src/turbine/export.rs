@@ -106,20 +106,6 @@ impl<M: MutableStore, T: TranslationsManager> Turbine<M, T> {
         let html_shell = self.html_shell.as_ref().unwrap();
 
         let path_prefix = get_path_prefix_server();
-        // We need the encoded path to reference flattened build artifacts
-        // But we don't create a flattened system with exporting, everything is properly
-        // created in a directory structure
-        let path_encoded = urlencoding::encode(path).to_string();
-        // All initial load pages should be written into their own folders, which
-        // prevents a situation of a template root page outside the directory for the
-        // rest of that template's pages (see #73). The `.html` file extension is
-        // added when this variable is used (for contrast to the `.json`s)
-        let initial_load_path = if path.ends_with("index") {
-            // However, if it's already an index page, we don't want `index/index.html`
-            path.to_string()
-        } else {
-            format!("{}/index", &path)
-        };
 
         // Get the template itself
         let template = self.entities.get(template_path);
@@ -132,6 +118,23 @@ impl<M: MutableStore, T: TranslationsManager> Turbine<M, T> {
                 .into())
             }
         };
+        // Exported apps have no request-time at which to check this, so disabled templates are
+        // left out entirely
+        if !template.is_capsule && !template.is_enabled() {
+            return Ok(());
+        }
+        // We need the encoded path to reference flattened build artifacts
+        // But we don't create a flattened system with exporting, everything is properly
+        // created in a directory structure
+        // Aliases are exported as copies of their template's root page, since they have no build
+        // artifacts of their own
+        let path_encoded = match template.is_alias(path) {
+            true => urlencoding::encode(&template.get_path()).to_string(),
+            false => urlencoding::encode(path).to_string(),
+        };
+        // This is where initial load pages will be written (usually into their own folders, see
+        // #73)
+        let initial_load_path = template.output_filename(path);
 
         // Create a locale detection file for it if we're using i18n
         // These just send the app shell, which will perform a redirect as necessary
@@ -140,7 +143,7 @@ impl<M: MutableStore, T: TranslationsManager> Turbine<M, T> {
         if self.locales.using_i18n && !template.is_capsule {
             self.immutable_store
                 .write(
-                    &format!("exported/{}.html", &initial_load_path),
+                    &format!("exported/{}", &initial_load_path),
                     &html_shell
                         .clone()
                         .locale_redirection_fallback(
@@ -181,7 +184,7 @@ impl<M: MutableStore, T: TranslationsManager> Turbine<M, T> {
                         .to_string();
                     self.immutable_store
                         .write(
-                            &format!("exported/{}/{}.html", locale, initial_load_path),
+                            &format!("exported/{}/{}", locale, initial_load_path),
                             &full_html,
                         )
                         .await?;
@@ -222,7 +225,7 @@ impl<M: MutableStore, T: TranslationsManager> Turbine<M, T> {
                 // We don't add an extension because this will be queried directly by the
                 // browser
                 self.immutable_store
-                    .write(&format!("exported/{}.html", initial_load_path), &full_html)
+                    .write(&format!("exported/{}", initial_load_path), &full_html)
                     .await?;
             }
 
@@ -27,7 +27,7 @@ use crate::{
     Request,
 };
 use crate::{
-    state::{TemplateState, UnknownStateType},
+    state::{ResponseMeta, TemplateState, UnknownStateType},
     utils::ssr_fallible,
 };
 
@@ -36,6 +36,8 @@ use crate::{
 struct StateAndHead {
     state: TemplateState,
     head: String,
+    /// Any response metadata generated by the request state function.
+    meta: ResponseMeta,
 }
 
 impl<M: MutableStore, T: TranslationsManager> Turbine<M, T> {
@@ -45,6 +47,9 @@ impl<M: MutableStore, T: TranslationsManager> Turbine<M, T> {
     /// get the state of a capsule, the head will of course be empty.
     ///
     /// This assumes the given locale is actually supported.
+    ///
+    /// This also returns any response metadata generated alongside the
+    /// request state.
     pub async fn get_state_for_path(
         &self,
         path: PathWithoutLocale,
@@ -52,12 +57,12 @@ impl<M: MutableStore, T: TranslationsManager> Turbine<M, T> {
         entity_name: &str,
         was_incremental: bool,
         req: Request,
-    ) -> Result<PageDataPartial, ServerError> {
+    ) -> Result<(PageDataPartial, ResponseMeta), ServerError> {
         let translator = self
             .translations_manager
             .get_translator_for_locale(locale)
             .await?;
-        let StateAndHead { state, head } = self
+        let StateAndHead { state, head, meta } = self
             .get_state_for_path_internal(
                 path,
                 &translator,
@@ -71,10 +76,13 @@ impl<M: MutableStore, T: TranslationsManager> Turbine<M, T> {
             )
             .await?;
 
-        Ok(PageDataPartial {
-            state: state.state,
-            head,
-        })
+        Ok((
+            PageDataPartial {
+                state: state.state,
+                head,
+            },
+            meta,
+        ))
     }
     /// Gets the full page data for the given path. This will generate the
     /// state, render the head, and render the content of the page,
@@ -84,8 +92,8 @@ impl<M: MutableStore, T: TranslationsManager> Turbine<M, T> {
     /// respects the likely need to know the translations string as well,
     /// for error page interpolation.
     ///
-    /// Like `.get_state_for_path()`, this returns the page data and the global
-    /// state in a tuple.
+    /// This returns the page data, the global state, and any response metadata
+    /// generated alongside the request state in a tuple.
     ///
     /// # Pitfalls
     /// This currently uses a layer-based dependency resolution algorithm, as a
@@ -108,7 +116,7 @@ impl<M: MutableStore, T: TranslationsManager> Turbine<M, T> {
         template: &Entity<SsrNode>,
         was_incremental: bool,
         req: Request,
-    ) -> Result<(PageData, TemplateState), ServerError> {
+    ) -> Result<(PageData, TemplateState, ResponseMeta), ServerError> {
         let locale = translator.get_locale();
         // Get the latest global state, which we'll share around
         let global_state = self.get_full_global_state(clone_req(&req)).await?;
@@ -125,6 +133,15 @@ impl<M: MutableStore, T: TranslationsManager> Turbine<M, T> {
                 true, // This is an initial load
             )
             .await?;
+        if page_state.meta.is_redirect() {
+            let page_data = PageData {
+                content: String::new(),
+                state: Value::Null,
+                widget_states: HashMap::new(),
+                head: String::new(),
+            };
+            return Ok((page_data, global_state, page_state.meta));
+        }
 
         let path = PathWithoutLocale(path.strip_suffix('/').unwrap_or(&*path).to_string());
         // Yes, this is created twice; no, we don't care
@@ -191,13 +208,14 @@ impl<M: MutableStore, T: TranslationsManager> Turbine<M, T> {
                         .collect(),
                 },
                 global_state,
+                page_state.meta,
             ))
         } else {
             // This will block
             let (final_widget_states, prerendered) = self
                 .render_all(
                     HashMap::new(), // This starts empty
-                    path,
+                    path.clone(),
                     locale.to_string(),
                     page_state.state.clone(),
                     template,
@@ -206,6 +224,7 @@ impl<M: MutableStore, T: TranslationsManager> Turbine<M, T> {
                     translator,
                 )?
                 .await?;
+            let prerendered = template.transform_html(prerendered, &path)?;
             // Convert the `TemplateState`s into `Value`s
             let final_widget_states = final_widget_states
                 .into_iter()
@@ -221,6 +240,7 @@ impl<M: MutableStore, T: TranslationsManager> Turbine<M, T> {
                     widget_states: final_widget_states,
                 },
                 global_state,
+                page_state.meta,
             ))
         }
     }
@@ -444,6 +464,7 @@ impl<M: MutableStore, T: TranslationsManager> Turbine<M, T> {
         };
 
         let path = PathWithoutLocale(path.strip_suffix('/').unwrap_or(&*path).to_string());
+        check_request_allowed(&path, entity, &req)?;
         // If we're interacting with the stores, this is the path this page/widget will
         // be under
         let path_encoded = format!("{}-{}", locale, urlencoding::encode(&path));
@@ -474,6 +495,7 @@ impl<M: MutableStore, T: TranslationsManager> Turbine<M, T> {
                 // No, this state is never written anywhere at build-time
                 state: TemplateState::empty(),
                 head,
+                meta: ResponseMeta::default(),
             });
         }
 
@@ -525,6 +547,13 @@ impl<M: MutableStore, T: TranslationsManager> Turbine<M, T> {
         // If not incremental and not revalidating; get
         // from immutable.
         if was_incremental {
+            // Make sure the user actually wants this path to exist before we do any work
+            if !entity.can_render_incremental(&pure_path) {
+                return Err(ServeError::PageNotFound {
+                    path: path.to_string(),
+                }
+                .into());
+            }
             // If we have something in the mutable store, then this has already been
             // generated
             let res = self
@@ -538,7 +567,6 @@ impl<M: MutableStore, T: TranslationsManager> Turbine<M, T> {
                 Err(StoreError::NotFound { .. }) => None,
                 Err(err) => return Err(err.into()),
             };
-
             if built_state.is_some() {
                 // This has been generated already, so we need to check for the possibility of
                 // revalidation
@@ -551,6 +579,9 @@ impl<M: MutableStore, T: TranslationsManager> Turbine<M, T> {
                     )
                     .await?;
                 if should_revalidate {
+                    let revalidated_state = self
+                        .revalidated_state(&path_encoded, entity, build_info.clone())
+                        .await?;
                     // We need to rebuild, which we can do with the build-time logic (which will use
                     // the mutable store)
                     self.build_path_or_widget_for_locale(
@@ -561,6 +592,7 @@ impl<M: MutableStore, T: TranslationsManager> Turbine<M, T> {
                         global_state.clone(),
                         false,
                         true,
+                        revalidated_state,
                     )
                     .await?;
                 } else {
@@ -583,9 +615,15 @@ impl<M: MutableStore, T: TranslationsManager> Turbine<M, T> {
                     false,
                     // This makes sure we use the mutable store no matter what (incremental)
                     true,
+                    None,
                 )
                 .await?;
             }
+            // Now that this page definitely exists, record it as the most recently used,
+            // and clean up anything that's been evicted to make room for it
+            for evicted in entity.record_incremental_path(&path_encoded) {
+                self.delete_incremental_path(&evicted).await?;
+            }
         } else {
             let should_revalidate = self
                 .page_or_widget_should_revalidate(
@@ -596,6 +634,9 @@ impl<M: MutableStore, T: TranslationsManager> Turbine<M, T> {
                 )
                 .await?;
             if should_revalidate {
+                let revalidated_state = self
+                    .revalidated_state(&path_encoded, entity, build_info.clone())
+                    .await?;
                 // We need to rebuild, which we can do with the build-time logic
                 self.build_path_or_widget_for_locale(
                     pure_path,
@@ -605,6 +646,7 @@ impl<M: MutableStore, T: TranslationsManager> Turbine<M, T> {
                     global_state.clone(),
                     false,
                     false,
+                    revalidated_state,
                 )
                 .await?;
             } else {
@@ -632,13 +674,21 @@ impl<M: MutableStore, T: TranslationsManager> Turbine<M, T> {
 
         // Now get the request state if we're using it (of course, this must be
         // re-generated for every request)
-        let request_state = if entity.uses_request_state() {
+        let (request_state, meta) = if entity.uses_request_state() {
             entity
-                .get_request_state(build_info.clone(), clone_req(&req))
+                .get_request_state_with_meta(build_info.clone(), clone_req(&req))
                 .await?
         } else {
-            TemplateState::empty()
+            (TemplateState::empty(), ResponseMeta::default())
         };
+        // If we're redirecting, there's nothing to render
+        if meta.is_redirect() {
+            return Ok(StateAndHead {
+                state: TemplateState::empty(),
+                head: String::new(),
+                meta,
+            });
+        }
 
         // Now handle the possibility of amalgamation
         let states = States {
@@ -705,9 +755,56 @@ impl<M: MutableStore, T: TranslationsManager> Turbine<M, T> {
         Ok(StateAndHead {
             state: final_state,
             head: head_str,
+            meta,
         })
     }
 
+    /// Renders the fallback for the given incremental page of the given
+    /// template, along with the latest global state, if the template has a
+    /// fallback and the page is yet to be generated. Otherwise, this will
+    /// return `None`, and the page should be served as usual.
+    pub(crate) async fn get_fallback_for_path(
+        &self,
+        path: &PathWithoutLocale,
+        translator: &Translator,
+        entity: &Entity<SsrNode>,
+        req: &Request,
+    ) -> Result<Option<(String, TemplateState)>, ServerError> {
+        if !entity.has_fallback() {
+            return Ok(None);
+        }
+        let path = path.strip_suffix('/').unwrap_or(path);
+        // Fallbacks are subject to exactly the same restrictions as the real page
+        check_request_allowed(path, entity, req)?;
+        let path_encoded = format!("{}-{}", translator.get_locale(), urlencoding::encode(path));
+        match self
+            .mutable_store
+            .read(&format!("static/{}.json", path_encoded))
+            .await
+        {
+            // This has already been generated
+            Ok(_) => return Ok(None),
+            Err(StoreError::NotFound { .. }) => (),
+            Err(err) => return Err(err.into()),
+        };
+
+        let global_state = self.get_full_global_state(clone_req(req)).await?;
+        Ok(entity
+            .render_fallback(global_state.clone(), translator)
+            .map(|html| (html, global_state)))
+    }
+
+    /// Deletes all the artifacts of the given incrementally generated page
+    /// from the mutable store, after it's been evicted from the cache. The
+    /// path given here should be encoded, including the locale.
+    async fn delete_incremental_path(&self, path_encoded: &str) -> Result<(), ServerError> {
+        for ext in ["json", "head.html", "html", "widgets.json", "revld.txt"] {
+            self.mutable_store
+                .delete(&format!("static/{}.{}", path_encoded, ext))
+                .await?;
+        }
+        Ok(())
+    }
     /// Checks timestamps and runs user-provided logic to determine if the given
     /// widget/path should revalidate at the present time.
     async fn page_or_widget_should_revalidate(
@@ -744,10 +841,47 @@ impl<M: MutableStore, T: TranslationsManager> Turbine<M, T> {
 
         // Now run the user's custom revalidation logic
         if entity.revalidates_with_logic() {
-            should_revalidate = entity.should_revalidate(build_info, req).await?;
+            // Revalidating pages always have their build state in the mutable store, but we
+            // only need to read it if the user's logic actually uses it
+            let cached_state = if entity.revalidates_with_cached_state() {
+                let state_str = self
+                    .mutable_store
+                    .read(&format!("static/{}.json", path_encoded))
+                    .await?;
+                TemplateState::from_str(&state_str)
+                    .map_err(|err| ServerError::InvalidPageState { source: err })?
+            } else {
+                TemplateState::empty()
+            };
+            should_revalidate = entity
+                .should_revalidate(build_info, req, cached_state)
+                .await?;
         }
         Ok(should_revalidate)
     }
+    /// Updates the state the given page/widget was last generated with, if
+    /// its entity has custom logic for doing so on revalidation. If this
+    /// returns `None`, the build state should be generated again from scratch.
+    async fn revalidated_state(
+        &self,
+        path_encoded: &str,
+        entity: &Entity<SsrNode>,
+        build_info: StateGeneratorInfo<UnknownStateType>,
+    ) -> Result<Option<TemplateState>, ServerError> {
+        if !entity.uses_revalidate_state() {
+            return Ok(None);
+        }
+
+        // Revalidating pages always have their build state in the mutable store
+        let state_str = self
+            .mutable_store
+            .read(&format!("static/{}.json", path_encoded))
+            .await?;
+        let old_state = TemplateState::from_str(&state_str)
+            .map_err(|err| ServerError::InvalidPageState { source: err })?;
+        let state = entity.revalidate_state(build_info, old_state).await?;
+        Ok(Some(state))
+    }
     /// Gets the full global state from the state generated at build-time and
     /// the generator itself.
     ///
@@ -801,3 +935,88 @@ fn clone_req(raw: &Request) -> Request {
         .body(())
         .unwrap() // This should never fail...
 }
+
+/// Checks whether or not the given request for the given path may be served
+/// by the given entity at all, returning an error if it's disabled, if the
+/// request's method isn't allowed, or if the request isn't authorized. This
+/// should be called before any work is done for a request.
+fn check_request_allowed(
+    path: &str,
+    entity: &Entity<SsrNode>,
+    req: &Request,
+) -> Result<(), ServerError> {
+    // Disabled pages don't exist as far as the user is concerned
+    if !entity.is_capsule && !entity.is_enabled() {
+        return Err(ServeError::PageNotFound {
+            path: path.to_string(),
+        }
+        .into());
+    }
+    if !entity.is_capsule && !entity.method_allowed(req.method().as_str()) {
+        return Err(ServeError::MethodNotAllowed {
+            path: path.to_string(),
+            method: req.method().to_string(),
+        }
+        .into());
+    }
+    if !entity.is_authorized(req) {
+        return Err(ServeError::Unauthorized {
+            path: path.to_string(),
+        }
+        .into());
+    }
+
+    Ok(())
+}
+
+#[cfg(all(test, engine))]
+mod tests {
+    use super::*;
+    use crate::state::BuildPaths;
+    use crate::template::test_utils::*;
+    use crate::template::Template;
+
+    #[test]
+    fn incremental_cache_limit_deletes_evicted_pages() {
+        let template = Template::build("post")
+            .build_paths_fn(|| async {
+                BuildPaths {
+                    paths: Vec::new(),
+                    extra: ().into(),
+                }
+            })
+            .incremental_generation()
+            .incremental_cache_limit(2)
+            .build_state_fn(
+                |info: StateGeneratorInfo<()>| async move { TestState { title: info.path } },
+            )
+            .view_with_unreactive_state(|cx, state: TestState| {
+                sycamore::view! { cx, p { (state.title) } }
+            })
+            .build();
+        let turbine = test_turbine("lru", vec![template]);
+        turbine.block_on(async {
+            // One more page than the cache can hold
+            for id in ["1", "2", "3"] {
+                turbine
+                    .get_state_for_path(
+                        PathWithoutLocale(format!("post/{}", id)),
+                        "xx-XX".to_string(),
+                        "post",
+                        true,
+                        request(),
+                    )
+                    .await
+                    .unwrap();
+            }
+
+            let page = |id: &str| format!("static/xx-XX-post%2F{}.json", id);
+            assert!(matches!(
+                turbine.mutable_store.read(&page("1")).await,
+                Err(StoreError::NotFound { .. })
+            ));
+            assert!(turbine.mutable_store.read(&page("2")).await.is_ok());
+            assert!(turbine.mutable_store.read(&page("3")).await.is_ok());
+        });
+    }
+}
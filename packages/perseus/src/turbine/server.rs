@@ -1,12 +1,13 @@
 use super::Turbine;
 use crate::{
     error_views::ServerErrorData,
-    errors::{err_to_status_code, ServerError},
+    errors::{err_to_status_code, ServeError, ServerError},
     i18n::{TranslationsManager, Translator},
+    page_data::PageRedirect,
     path::{PathMaybeWithLocale, PathWithoutLocale},
     router::{match_route, FullRouteInfo, FullRouteVerdict},
     server::get_path_slice,
-    state::TemplateState,
+    state::{ResponseMeta, TemplateState},
     stores::MutableStore,
     utils::get_path_prefix_server,
     Request,
@@ -79,6 +80,15 @@ impl ApiResponse {
         );
         self
     }
+    /// Applies the given response metadata to this response, replacing any
+    /// existing values of the headers it sets, and overriding the status code
+    /// if it sets one.
+    pub fn apply_meta(&mut self, meta: ResponseMeta) {
+        self.headers.extend(meta.headers);
+        if let Some(status) = meta.status {
+            self.status = status;
+        }
+    }
 }
 
 /// The query parameters used in subsequent load requests. This is provided for
@@ -123,6 +133,10 @@ impl<M: MutableStore, T: TranslationsManager> Turbine<M, T> {
     /// handled. It is expected to end in `.json` (needed for compatibility
     /// with the exporting system).
     ///
+    /// If the request state of the page redirects the user elsewhere, this
+    /// will respond with a [`PageRedirect`] rather than the page's data.
+    /// Status code overrides from request state are ignored here.
+    ///
     /// Subsequent loads have the MIME type `application/json`.
     pub async fn get_subsequent_load(
         &self,
@@ -147,8 +161,8 @@ impl<M: MutableStore, T: TranslationsManager> Turbine<M, T> {
             let page_data_partial = self
                 .get_state_for_path(path, locale, &entity_name, was_incremental_match, req)
                 .await;
-            let page_data_partial = match page_data_partial {
-                Ok(partial) => partial,
+            let (page_data_partial, mut meta) = match page_data_partial {
+                Ok(data) => data,
                 Err(err) => {
                     // Parse the error to an appropriate status code
                     let status = StatusCode::from_u16(err_to_status_code(&err)).unwrap();
@@ -157,9 +171,18 @@ impl<M: MutableStore, T: TranslationsManager> Turbine<M, T> {
                 }
             };
 
-            // We know the form of this, and it should never fail
-            let page_data_str = serde_json::to_string(&page_data_partial).unwrap();
-            ApiResponse::ok(&page_data_str).content_type("application/json")
+            // The browser would follow a real redirect to an HTML page, so we tell the app
+            // shell where to go instead (we know the form of these, so serialization
+            // should never fail)
+            let page_data_str = match meta.take_redirect() {
+                Some(redirect) => serde_json::to_string(&PageRedirect { redirect }).unwrap(),
+                None => serde_json::to_string(&page_data_partial).unwrap(),
+            };
+            let mut response = ApiResponse::ok(&page_data_str).content_type("application/json");
+            // Status code overrides only make sense for initial loads, the app shell
+            // expects any page data it's given to come with a 200 OK
+            response.headers.extend(meta.headers);
+            response
         } else {
             ApiResponse::not_found("locale not supported")
         }
@@ -231,18 +254,76 @@ impl<M: MutableStore, T: TranslationsManager> Turbine<M, T> {
                     Err(err) => return self.html_err(500, fmt_err(&err), None),
                 };
 
+                // Templates can send a fallback for pages that are yet to be generated, in
+                // which case the app shell will fetch (and so generate) the real page itself
+                if was_incremental_match && !entity.rejects_incremental_path(&path) {
+                    match self
+                        .get_fallback_for_path(&path, &translator, entity, &req)
+                        .await
+                    {
+                        Ok(Some((fallback_html, global_state))) => {
+                            let final_html = self
+                                .html_shell
+                                .as_ref()
+                                .unwrap()
+                                .clone()
+                                .fallback_page(
+                                    &fallback_html,
+                                    &global_state,
+                                    &locale,
+                                    &translations_str,
+                                )
+                                .to_string();
+                            let mut response =
+                                ApiResponse::ok(&final_html).content_type("text/html");
+                            // This will be replaced by the real page as soon as it's generated
+                            response.add_header(
+                                header::CACHE_CONTROL,
+                                HeaderValue::from_static("no-store"),
+                            );
+                            return response;
+                        }
+                        Ok(None) => (),
+                        Err(err) => {
+                            return self.html_err(
+                                err_to_status_code(&err),
+                                fmt_err(&err),
+                                Some((&translator, &translations_str)),
+                            )
+                        }
+                    }
+                }
+
                 // This returns both the page data and the most up-to-date global state
                 let res = self
                     .get_initial_load_for_path(
-                        path,
+                        path.clone(),
                         &translator,
                         entity,
                         was_incremental_match,
                         req,
                     )
                     .await;
-                let (page_data, global_state) = match res {
+                let (page_data, global_state, meta) = match res {
                     Ok(data) => data,
+                    // Templates can render their own bodies for the pages their incremental path
+                    // matchers reject (but not for any other errors that map to a 404)
+                    Err(err @ ServerError::ServeError(ServeError::PageNotFound { .. }))
+                        if was_incremental_match && entity.rejects_incremental_path(&path) =>
+                    {
+                        let body = entity.render_not_found(&path, &translator);
+                        let err_data = ServerErrorData {
+                            status: 404,
+                            msg: fmt_err(&err),
+                        };
+                        let html = self.build_error_page_with_body(
+                            err_data,
+                            body.as_deref(),
+                            Some((&translator, &translations_str)),
+                        );
+                        return ApiResponse::err(StatusCode::NOT_FOUND, &html)
+                            .content_type("text/html");
+                    }
                     Err(err) => {
                         return self.html_err(
                             err_to_status_code(&err),
@@ -251,6 +332,12 @@ impl<M: MutableStore, T: TranslationsManager> Turbine<M, T> {
                         )
                     }
                 };
+                // Redirects shouldn't have a body
+                if meta.is_redirect() {
+                    let mut response = ApiResponse::ok("");
+                    response.apply_meta(meta);
+                    return response;
+                }
 
                 let final_html = self
                     .html_shell
@@ -283,6 +370,10 @@ impl<M: MutableStore, T: TranslationsManager> Turbine<M, T> {
                 for (key, val) in headers {
                     response.add_header(key.unwrap(), val);
                 }
+                if let Ok(status) = StatusCode::from_u16(entity.get_status_code()) {
+                    response.status = status;
+                }
+                response.apply_meta(meta);
 
                 response
             }
@@ -377,3 +468,245 @@ impl<M: MutableStore, T: TranslationsManager> Turbine<M, T> {
         ApiResponse::err(StatusCode::from_u16(status).unwrap(), &html).content_type("text/html")
     }
 }
+
+#[cfg(all(test, engine))]
+mod tests {
+    use super::*;
+    use crate::errors::BlamedError;
+    use crate::page_data::PageDataPartial;
+    use crate::state::{BuildPaths, RenderOutcome, StateGeneratorInfo};
+    use crate::template::test_utils::*;
+    use crate::template::Template;
+
+    #[test]
+    fn rejected_incremental_paths_use_custom_not_found_body() {
+        let template = Template::build("blog")
+            .build_paths_fn(|| async {
+                BuildPaths {
+                    paths: Vec::new(),
+                    extra: ().into(),
+                }
+            })
+            .incremental_generation()
+            .incremental_path_matcher_fn(|path| path != "rejected")
+            .build_state_fn(|info: StateGeneratorInfo<()>| async move {
+                if info.path == "gone" {
+                    Err(BlamedError::not_found(std::io::Error::from(
+                        std::io::ErrorKind::NotFound,
+                    )))
+                } else {
+                    Ok(TestState { title: info.path })
+                }
+            })
+            .view_with_unreactive_state(|cx, state: TestState| {
+                sycamore::view! { cx, p { (state.title) } }
+            })
+            .not_found_fn(|cx, path| {
+                let path = path.to_string();
+                sycamore::view! { cx, p { "No post at " (path) } }
+            })
+            .build();
+        let turbine = test_turbine("404", vec![template]);
+        turbine.block_on(async {
+            let res = turbine
+                .get_initial_load(PathMaybeWithLocale("blog/rejected".to_string()), request())
+                .await;
+            assert_eq!(res.status, StatusCode::NOT_FOUND);
+            assert!(res.body.contains("No post at blog/rejected"));
+            // This should be a full page, not just the body
+            assert!(res.body.contains("<head>"));
+            assert!(res.body.contains("__PERSEUS_INITIAL_STATE"));
+
+            // Other 404s should still use the error views
+            let res = turbine
+                .get_initial_load(PathMaybeWithLocale("blog/gone".to_string()), request())
+                .await;
+            assert_eq!(res.status, StatusCode::NOT_FOUND);
+            assert!(!res.body.contains("No post at"));
+        });
+    }
+
+    #[test]
+    fn subsequent_loads_send_redirects_as_page_data() {
+        let private = Template::build("private")
+            .redirecting_request_state_fn(
+                |_info: StateGeneratorInfo<()>, _req: Request| async move {
+                    RenderOutcome::<TestState>::Redirect {
+                        to: "/login".to_string(),
+                        permanent: false,
+                    }
+                },
+            )
+            .view_with_unreactive_state(|cx, state: TestState| {
+                sycamore::view! { cx, p { (state.title) } }
+            })
+            .build();
+        let teapot = Template::build("teapot")
+            .request_state_with_meta_fn(|info: StateGeneratorInfo<()>, _req: Request| async move {
+                let meta = ResponseMeta::new()
+                    .header(header::SET_COOKIE, HeaderValue::from_static("session=abc"))
+                    .status(StatusCode::IM_A_TEAPOT);
+                (TestState { title: info.path }, meta)
+            })
+            .view_with_unreactive_state(|cx, state: TestState| {
+                sycamore::view! { cx, p { (state.title) } }
+            })
+            .build();
+        let turbine = test_turbine("redirect", vec![private, teapot]);
+        turbine.block_on(async {
+            // Initial loads get a real redirect
+            let res = turbine
+                .get_initial_load(PathMaybeWithLocale("private".to_string()), request())
+                .await;
+            assert_eq!(res.status, StatusCode::FOUND);
+            assert_eq!(res.headers.get(header::LOCATION).unwrap(), "/login");
+
+            // Subsequent loads tell the app shell where to go
+            let res = turbine
+                .get_subsequent_load(
+                    PathWithoutLocale("private.json".to_string()),
+                    "xx-XX".to_string(),
+                    "private".to_string(),
+                    false,
+                    request(),
+                )
+                .await;
+            assert_eq!(res.status, StatusCode::OK);
+            assert!(res.headers.get(header::LOCATION).is_none());
+            let redirect: PageRedirect = serde_json::from_str(&res.body).unwrap();
+            assert_eq!(redirect.redirect, "/login");
+
+            // Status overrides only apply to initial loads, but headers still go through
+            let res = turbine
+                .get_subsequent_load(
+                    PathWithoutLocale("teapot.json".to_string()),
+                    "xx-XX".to_string(),
+                    "teapot".to_string(),
+                    false,
+                    request(),
+                )
+                .await;
+            assert_eq!(res.status, StatusCode::OK);
+            assert_eq!(res.headers.get(header::SET_COOKIE).unwrap(), "session=abc");
+            let page_data: PageDataPartial = serde_json::from_str(&res.body).unwrap();
+            assert_eq!(page_data.state["title"], "teapot");
+        });
+    }
+
+    #[test]
+    fn ungenerated_incremental_pages_send_fallbacks() {
+        let template = Template::build("blog")
+            .build_paths_fn(|| async {
+                BuildPaths {
+                    paths: Vec::new(),
+                    extra: ().into(),
+                }
+            })
+            .incremental_generation()
+            .build_state_fn(
+                |info: StateGeneratorInfo<()>| async move { TestState { title: info.path } },
+            )
+            .view_with_unreactive_state(|cx, state: TestState| {
+                sycamore::view! { cx, p { (state.title) } }
+            })
+            .fallback_fn(|cx| sycamore::view! { cx, p { "Loading post..." } })
+            .build();
+        let private_template = Template::build("private")
+            .build_paths_fn(|| async {
+                BuildPaths {
+                    paths: Vec::new(),
+                    extra: ().into(),
+                }
+            })
+            .incremental_generation()
+            .request_state_fn(|info: StateGeneratorInfo<()>, _req| async move {
+                TestState { title: info.path }
+            })
+            .requires_auth(|_| false)
+            .view_with_unreactive_state(|cx, state: TestState| {
+                sycamore::view! { cx, p { (state.title) } }
+            })
+            .fallback_fn(|cx| sycamore::view! { cx, p { "Loading..." } })
+            .build();
+        let turbine = test_turbine("fallback", vec![template, private_template]);
+        turbine.block_on(async {
+            // Fallbacks shouldn't get around method or authorization checks
+            let post = Request::builder().method("POST").body(()).unwrap();
+            let res = turbine
+                .get_initial_load(PathMaybeWithLocale("blog/new".to_string()), post)
+                .await;
+            assert_eq!(res.status, StatusCode::METHOD_NOT_ALLOWED);
+            assert!(!res.body.contains("Loading post..."));
+            let res = turbine
+                .get_initial_load(PathMaybeWithLocale("private/new".to_string()), request())
+                .await;
+            assert_eq!(res.status, StatusCode::UNAUTHORIZED);
+            assert!(!res.body.contains("Loading..."));
+
+            let res = turbine
+                .get_initial_load(PathMaybeWithLocale("blog/new".to_string()), request())
+                .await;
+            assert_eq!(res.status, StatusCode::OK);
+            assert!(res.body.contains("Loading post..."));
+            assert!(res.body.contains("__PERSEUS_INITIAL_STATE = `fallback`"));
+            assert_eq!(res.headers.get(header::CACHE_CONTROL).unwrap(), "no-store");
+
+            // The app shell will then fetch the page, generating it
+            let res = turbine
+                .get_subsequent_load(
+                    PathWithoutLocale("blog/new.json".to_string()),
+                    "xx-XX".to_string(),
+                    "blog".to_string(),
+                    true,
+                    request(),
+                )
+                .await;
+            assert_eq!(res.status, StatusCode::OK);
+
+            // And now the page exists, it should be sent directly
+            let res = turbine
+                .get_initial_load(PathMaybeWithLocale("blog/new".to_string()), request())
+                .await;
+            assert_eq!(res.status, StatusCode::OK);
+            assert!(res.body.contains(r#"{"title":"new"}"#));
+            assert!(!res.body.contains("Loading post..."));
+        });
+    }
+
+    #[test]
+    fn templates_can_be_enabled_after_build() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::Arc;
+
+        let flag = Arc::new(AtomicBool::new(false));
+        let template_flag = flag.clone();
+        let template = Template::build("beta")
+            .build_state_fn(
+                |info: StateGeneratorInfo<()>| async move { TestState { title: info.path } },
+            )
+            .view_with_unreactive_state(|cx, state: TestState| {
+                sycamore::view! { cx, p { (state.title) } }
+            })
+            .enabled_when(move || template_flag.load(Ordering::SeqCst))
+            .build();
+        let turbine = test_turbine("enabled", vec![template]);
+        turbine.block_on(async {
+            let res = turbine
+                .get_initial_load(PathMaybeWithLocale("beta".to_string()), request())
+                .await;
+            assert_eq!(res.status, StatusCode::NOT_FOUND);
+
+            flag.store(true, Ordering::SeqCst);
+            let res = turbine
+                .get_initial_load(PathMaybeWithLocale("beta".to_string()), request())
+                .await;
+            assert_eq!(res.status, StatusCode::OK);
+
+            flag.store(false, Ordering::SeqCst);
+            let res = turbine
+                .get_initial_load(PathMaybeWithLocale("beta".to_string()), request())
+                .await;
+            assert_eq!(res.status, StatusCode::NOT_FOUND);
+        });
+    }
+}
@@ -0,0 +1,171 @@
+use chrono::{DateTime, Datelike, Duration, TimeZone, Timelike, Utc};
+
+/// A parsed cron expression, in the standard five-field format (minute, hour,
+/// day of month, month, day of week). Each field may be `*`, a number, a
+/// range (`1-5`), a list (`1,3,5`), or any of these with a step (`*/15`).
+/// Days of the week start from Sunday as `0` (`7` is also accepted for
+/// Sunday), and all times are in UTC.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CronSchedule {
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    // If both of these are restricted, a day matches if either does (as in standard cron)
+    days_of_month_restricted: bool,
+    days_of_week_restricted: bool,
+}
+impl CronSchedule {
+    /// Parses the given cron expression, returning a description of the
+    /// problem if it's invalid.
+    pub(crate) fn parse(expr: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = expr.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(format!("expected 5 fields, found {}", fields.len()));
+        }
+
+        let mut days_of_week = parse_field(fields[4], 0, 7)?;
+        // Sunday can be either `0` or `7`
+        if days_of_week & (1 << 7) != 0 {
+            days_of_week = (days_of_week & !(1 << 7)) | 1;
+        }
+
+        Ok(Self {
+            minutes: parse_field(fields[0], 0, 59)?,
+            hours: parse_field(fields[1], 0, 23)?,
+            days_of_month: parse_field(fields[2], 1, 31)?,
+            months: parse_field(fields[3], 1, 12)?,
+            days_of_week,
+            days_of_month_restricted: fields[2] != "*",
+            days_of_week_restricted: fields[4] != "*",
+        })
+    }
+    /// Gets the first time strictly after the given time that matches this
+    /// schedule, if there is one within the next few years.
+    pub(crate) fn next_after(&self, time: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        // Cron has minute precision, so we start from the next whole minute
+        let mut next = time.with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        // Schedules like `0 0 30 2 *` never match, so we have to stop somewhere (this
+        // covers all leap year cycles)
+        let limit = time + Duration::days(366 * 5);
+
+        while next <= limit {
+            if !has(self.months, next.month()) {
+                // Skip to the start of the next month
+                let (year, month) = if next.month() == 12 {
+                    (next.year() + 1, 1)
+                } else {
+                    (next.year(), next.month() + 1)
+                };
+                next = Utc.with_ymd_and_hms(year, month, 1, 0, 0, 0).single()?;
+            } else if !self.day_matches(&next) {
+                // Skip to the start of the next day
+                let date = next.date_naive().succ_opt()?;
+                next = Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0)?);
+            } else if !has(self.hours, next.hour()) {
+                // Skip to the start of the next hour
+                next = next.with_minute(0)? + Duration::hours(1);
+            } else if !has(self.minutes, next.minute()) {
+                next += Duration::minutes(1);
+            } else {
+                return Some(next);
+            }
+        }
+
+        None
+    }
+    fn day_matches(&self, time: &DateTime<Utc>) -> bool {
+        let day_of_month = has(self.days_of_month, time.day());
+        let day_of_week = has(self.days_of_week, time.weekday().num_days_from_sunday());
+        if self.days_of_month_restricted && self.days_of_week_restricted {
+            day_of_month || day_of_week
+        } else {
+            day_of_month && day_of_week
+        }
+    }
+}
+
+/// Checks if the given value is set in the given bitmask.
+fn has(mask: u64, val: u32) -> bool {
+    mask & (1 << val) != 0
+}
+
+/// Parses a single field of a cron expression into a bitmask of the values it
+/// matches, which must all be between `min` and `max` (inclusive).
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64, String> {
+    let parse_num = |num: &str| {
+        num.parse::<u32>()
+            .map_err(|_| format!("'{}' is not a number", num))
+    };
+
+    let mut mask = 0;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, Some(parse_num(step)?)),
+            None => (part, None),
+        };
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (parse_num(start)?, parse_num(end)?)
+        } else {
+            let start = parse_num(range)?;
+            // A step on a single value means 'from here to the end'
+            (start, if step.is_some() { max } else { start })
+        };
+        if start < min || end > max || start > end {
+            return Err(format!(
+                "'{}' is out of range (must be between {} and {})",
+                range, min, max
+            ));
+        }
+        let step = match step {
+            Some(0) => return Err("steps must be greater than zero".to_string()),
+            Some(step) => step,
+            None => 1,
+        };
+
+        for val in (start..=end).step_by(step as usize) {
+            mask |= 1 << val;
+        }
+    }
+
+    Ok(mask)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2023, 5, day, hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn next_after_finds_next_matching_time() {
+        let schedule = CronSchedule::parse("*/15 9-17 * * 1-5").unwrap();
+        // 10 May 2023 was a Wednesday
+        assert_eq!(schedule.next_after(time(10, 9, 0)), Some(time(10, 9, 15)));
+        assert_eq!(schedule.next_after(time(10, 17, 50)), Some(time(11, 9, 0)));
+        // Friday evening rolls over to Monday morning
+        assert_eq!(schedule.next_after(time(12, 18, 0)), Some(time(15, 9, 0)));
+    }
+
+    #[test]
+    fn sunday_can_be_zero_or_seven() {
+        assert_eq!(
+            CronSchedule::parse("0 0 * * 0").unwrap(),
+            CronSchedule::parse("0 0 * * 7").unwrap()
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_expressions() {
+        assert!(CronSchedule::parse("0 3 * *").is_err());
+        assert!(CronSchedule::parse("0 25 * * *").is_err());
+        assert!(CronSchedule::parse("*/0 * * * *").is_err());
+        assert!(CronSchedule::parse("a * * * *").is_err());
+        assert!(CronSchedule::parse("5-1 * * * *").is_err());
+    }
+}
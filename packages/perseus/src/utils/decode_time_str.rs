@@ -6,7 +6,7 @@ mod engine {
 
     /// Represents a duration that can be computed relative to the current time.
     /// This should be created through [`PerseusDuration::into_computed`] only.
-    #[derive(Debug, Clone)]
+    #[derive(Debug, Clone, PartialEq, Eq, Hash)]
     pub struct ComputedDuration(chrono::Duration);
 
     impl ComputedDuration {
@@ -16,6 +16,11 @@ mod engine {
             let datetime = current + self.0;
             datetime.to_rfc3339()
         }
+        /// Get the time of the duration added to the given time, if it can be
+        /// represented.
+        pub fn add_to(&self, time: std::time::SystemTime) -> Option<std::time::SystemTime> {
+            time.checked_add(self.0.to_std().ok()?)
+        }
     }
 
     /// A trait that represents anything we'll accept for specifying durations
@@ -51,7 +56,9 @@ mod engine {
                 if c.is_numeric() {
                     curr_duration_length.push(c);
                 } else {
-                    let interval_length: i64 = curr_duration_length.parse().unwrap(); // It's just a string of numbers, we know more than the compiler
+                    // This will fail if there was no number before the indicator (e.g. `banana`)
+                    let interval_length: i64 =
+                        curr_duration_length.parse().map_err(|_| InvalidDuration)?;
                     if interval_length <= 0 {
                         return Err(InvalidDuration);
                     }
@@ -70,6 +77,10 @@ mod engine {
                     curr_duration_length = String::new();
                 }
             }
+            // A number without an indicator (e.g. the `30` in `1h30`) is ambiguous
+            if !curr_duration_length.is_empty() {
+                return Err(InvalidDuration);
+            }
 
             Ok(ComputedDuration(duration))
         }
@@ -80,6 +91,30 @@ mod engine {
             Ok(ComputedDuration(duration))
         }
     }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn parses_compound_time_str() {
+            let duration = "1h30m".into_computed().unwrap();
+            assert_eq!(duration.0.num_seconds(), 5400);
+        }
+
+        #[test]
+        fn rejects_invalid_time_str() {
+            assert!("banana".into_computed().is_err());
+            assert!("1h30".into_computed().is_err());
+            assert!("0s".into_computed().is_err());
+        }
+
+        #[test]
+        fn accepts_std_duration() {
+            let duration = time::Duration::from_secs(90).into_computed().unwrap();
+            assert_eq!(duration.0.num_seconds(), 90);
+        }
+    }
 }
 #[cfg(client)]
 mod browser {
@@ -4,6 +4,8 @@ mod async_fn_trait;
 mod cache_res;
 #[cfg(any(client, doc))]
 mod checkpoint;
+#[cfg(engine)]
+mod cron;
 mod decode_time_str;
 #[cfg(any(client, doc))]
 mod fetch;
@@ -11,9 +13,13 @@ mod log;
 #[cfg(engine)]
 mod minify;
 mod path_prefix;
+#[cfg(engine)]
+mod query;
 mod render;
 #[cfg(any(client, doc))]
 mod replace_head;
+#[cfg(engine)]
+mod stable_hash;
 mod test;
 
 #[cfg(engine)]
@@ -22,15 +28,21 @@ pub(crate) use async_fn_trait::AsyncFnReturn;
 pub use cache_res::{cache_fallible_res, cache_res};
 #[cfg(any(client, doc))]
 pub use checkpoint::checkpoint;
+#[cfg(engine)]
+pub(crate) use cron::CronSchedule;
 pub use decode_time_str::{ComputedDuration, InvalidDuration, PerseusDuration}; /* These have dummy equivalents for the browser */
 #[cfg(any(client, doc))]
 pub(crate) use fetch::fetch;
 #[cfg(engine)]
 pub(crate) use minify::minify;
 pub use path_prefix::*;
+#[cfg(engine)]
+pub use query::RequestQueryExt;
 #[cfg(any(client, doc))]
 pub(crate) use render::render_or_hydrate;
 #[cfg(engine)]
-pub(crate) use render::ssr_fallible;
+pub(crate) use render::{ssr_fallible, ssr_fallible_to_writer};
 #[cfg(any(client, doc))]
 pub(crate) use replace_head::replace_head;
+#[cfg(engine)]
+pub(crate) use stable_hash::StableHasher;
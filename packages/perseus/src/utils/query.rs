@@ -0,0 +1,72 @@
+use crate::Request;
+use std::collections::HashMap;
+
+/// An extension trait for [`Request`]s that allows easily reading their query
+/// parameters, which is mostly useful in request state functions (e.g. for
+/// preview flags or pagination cursors).
+///
+/// Keys and values will be percent-decoded (with `+` decoded as a space), and
+/// parameters without a value (e.g. `?preview`) will have an empty value.
+pub trait RequestQueryExt {
+    /// Gets the value of the first query parameter with the given key, if
+    /// there is one.
+    fn query_param(&self, key: &str) -> Option<String>;
+    /// Gets all the query parameters of this request. If a key is repeated,
+    /// the last value will be used.
+    fn query_params(&self) -> HashMap<String, String>;
+}
+impl RequestQueryExt for Request {
+    fn query_param(&self, key: &str) -> Option<String> {
+        parse_query(self.uri().query()?)
+            .find(|(param_key, _)| param_key == key)
+            .map(|(_, value)| value)
+    }
+    fn query_params(&self) -> HashMap<String, String> {
+        match self.uri().query() {
+            Some(query) => parse_query(query).collect(),
+            None => HashMap::new(),
+        }
+    }
+}
+
+/// Parses the given query string into decoded key/value pairs.
+fn parse_query(query: &str) -> impl Iterator<Item = (String, String)> + '_ {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| match pair.split_once('=') {
+            Some((key, value)) => (decode(key), decode(value)),
+            None => (decode(pair), String::new()),
+        })
+}
+
+/// Decodes a single query string component, leaving it as-is if it isn't
+/// valid UTF-8 once decoded.
+fn decode(component: &str) -> String {
+    let component = component.replace('+', " ");
+    match urlencoding::decode(&component) {
+        Ok(decoded) => decoded.into_owned(),
+        Err(_) => component,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn query_params_are_decoded() {
+        let req = Request::builder()
+            .uri("/search?page=2&q=hello%20world&preview")
+            .body(())
+            .unwrap();
+        assert_eq!(req.query_param("page"), Some("2".to_string()));
+        assert_eq!(req.query_param("q"), Some("hello world".to_string()));
+        assert_eq!(req.query_param("missing"), None);
+
+        let params = req.query_params();
+        assert_eq!(params.len(), 3);
+        assert_eq!(params["q"], "hello world");
+        assert_eq!(params["preview"], "");
+    }
+}
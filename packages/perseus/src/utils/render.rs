@@ -111,3 +111,46 @@ pub(crate) fn ssr_fallible<E>(
 
     ret
 }
+
+/// Renders the given view to the given writer in a fallible manner, managing
+/// hydration automatically. This is equivalent to [`ssr_fallible`], except
+/// the whole view is never materialized as a single string: each top-level
+/// node is written out as soon as it's been rendered.
+// XXX This is *highly* dependent on internal Sycamore implementation
+// details!
+#[cfg(engine)]
+pub(crate) fn ssr_fallible_to_writer<E>(
+    view_fn: impl FnOnce(Scope) -> Result<View<SsrNode>, E>,
+    w: &mut dyn std::io::Write,
+) -> Result<(), crate::errors::ServerError>
+where
+    crate::errors::ServerError: From<E>,
+{
+    use crate::errors::ServerError;
+    use sycamore::web::WriteToString;
+    use sycamore::{prelude::create_scope_immediate, utils::hydrate::with_hydration_context};
+
+    let mut ret = Ok(());
+    create_scope_immediate(|cx| {
+        let view_res = with_hydration_context(|| view_fn(cx));
+        match view_res {
+            Ok(view) => {
+                // This buffer is reused for each node
+                let mut node_str = String::new();
+                for node in view.flatten() {
+                    node_str.clear();
+                    node.write_to_string(&mut node_str);
+                    if let Err(err) = w.write_all(node_str.as_bytes()) {
+                        ret = Err(ServerError::WriteFailed { source: err });
+                        return;
+                    }
+                }
+            }
+            Err(err) => {
+                ret = Err(err.into());
+            }
+        }
+    });
+
+    ret
+}
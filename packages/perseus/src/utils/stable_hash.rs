@@ -0,0 +1,80 @@
+use std::hash::Hasher;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// A [`Hasher`] that uses 64-bit FNV-1a, which, unlike the standard
+/// library's default hasher, will produce the same output for the same input
+/// across Rust versions and platforms (integers are always hashed as
+/// little-endian, and `usize`s as 64 bits). This makes it suitable for things
+/// like `ETag`s, but it is *not* suitable for any security-sensitive purposes.
+#[derive(Debug, Clone)]
+pub(crate) struct StableHasher {
+    hash: u64,
+}
+impl StableHasher {
+    /// Creates a new hasher with nothing written to it yet.
+    pub(crate) fn new() -> Self {
+        Self {
+            hash: FNV_OFFSET_BASIS,
+        }
+    }
+}
+impl Default for StableHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Hasher for StableHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.hash = (self.hash ^ *byte as u64).wrapping_mul(FNV_PRIME);
+        }
+    }
+    fn finish(&self) -> u64 {
+        self.hash
+    }
+    // The defaults for these use the platform's endianness (and the signed
+    // integer methods all delegate to these)
+    fn write_u16(&mut self, i: u16) {
+        self.write(&i.to_le_bytes());
+    }
+    fn write_u32(&mut self, i: u32) {
+        self.write(&i.to_le_bytes());
+    }
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes());
+    }
+    fn write_u128(&mut self, i: u128) {
+        self.write(&i.to_le_bytes());
+    }
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_fnv1a_reference_values() {
+        let hash = |bytes: &[u8]| {
+            let mut hasher = StableHasher::new();
+            hasher.write(bytes);
+            hasher.finish()
+        };
+        assert_eq!(hash(b""), 0xcbf29ce484222325);
+        assert_eq!(hash(b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(hash(b"foobar"), 0x85944171f73967e8);
+    }
+
+    #[test]
+    fn integers_hash_independently_of_platform() {
+        let mut usize_hasher = StableHasher::new();
+        usize_hasher.write_usize(42);
+        let mut u64_hasher = StableHasher::new();
+        u64_hasher.write(&42u64.to_le_bytes());
+        assert_eq!(usize_hasher.finish(), u64_hasher.finish());
+    }
+}